@@ -1,15 +1,63 @@
+//! Scriptable in-memory `UsbBackend` for tests.
+//!
+//! Devices can be plugged and unplugged at runtime, `allow_ephemeral`, `revoke` and `block`
+//! update their `allowed` flag, and any operation can be made to fail or to take a while.
+//! Every call is recorded for assertions. Plugging, unplugging and authorization changes are
+//! reported through `UsbBackend::events`, so they drive the daemon like real hardware.
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
 use async_trait::async_trait;
-use lusby_common::backend::UsbBackend;
+use lusby_common::backend::{DeviceEvent, DeviceEventStream, UsbBackend};
 use lusby_common::types::DeviceInfo;
+use tokio::sync::mpsc;
+
+/// A backend operation, for failure injection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MockOp {
+    ListDevices,
+    GetDevice,
+    AllowEphemeral,
+    Revoke,
+    Block,
+    ApplyRules,
+    Healthy,
+}
+
+/// A recorded call with its arguments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MockCall {
+    ListDevices,
+    GetDevice(String),
+    AllowEphemeral { device_id: String, ttl_secs: u32 },
+    Revoke(String),
+    Block(String),
+    ApplyRules(String),
+    Healthy,
+}
+
+#[derive(Default)]
+struct MockState {
+    devices: Vec<DeviceInfo>,
+    rules: String,
+    failing: HashSet<MockOp>,
+    latency: Duration,
+    calls: Vec<MockCall>,
+    subscribers: Vec<mpsc::UnboundedSender<DeviceEvent>>,
+}
 
 #[derive(Clone, Default)]
 pub struct MockBackend {
-    devices: std::sync::Arc<std::sync::Mutex<Vec<DeviceInfo>>>,
+    state: Arc<Mutex<MockState>>,
 }
 
 impl MockBackend {
-    pub fn new_with_sample() -> Self {
-        let sample = DeviceInfo {
+    /// The device of `new_with_sample`: a blocked HID receiver with id `dev1`.
+    pub fn sample_device() -> DeviceInfo {
+        DeviceInfo {
             id: "dev1".into(),
             vendor_id: "0x046d".into(),
             product_id: "0xc534".into(),
@@ -18,33 +66,264 @@ impl MockBackend {
             device_type: "hid".into(),
             allowed: false,
             persistent: false,
+            interfaces: vec!["03:01:02".into()],
+            legacy_fingerprint: String::new(),
+        }
+    }
+
+    pub fn new_with_sample() -> Self {
+        Self::with_devices(vec![Self::sample_device()])
+    }
+
+    pub fn with_devices(devices: Vec<DeviceInfo>) -> Self {
+        let backend = Self::default();
+        backend.state.lock().unwrap().devices = devices;
+        backend
+    }
+
+    /// Devices from a JSON array of `DeviceInfo`.
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        Ok(Self::with_devices(serde_json::from_str(json)?))
+    }
+
+    /// Devices from a JSON fixture file (see `from_json`).
+    pub fn from_fixture(path: &Path) -> std::io::Result<Self> {
+        let json = std::fs::read_to_string(path)?;
+        Self::from_json(&json).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+
+    /// Connect a device, replacing one with the same id, and notify subscribers.
+    pub fn plug(&self, device: DeviceInfo) {
+        let mut state = self.state.lock().unwrap();
+        state.devices.retain(|d| d.id != device.id);
+        state.devices.push(device.clone());
+        state.notify(DeviceEvent::Inserted(device));
+    }
+
+    /// Disconnect a device and notify subscribers; returns it if it was connected.
+    pub fn unplug(&self, device_id: &str) -> Option<DeviceInfo> {
+        let mut state = self.state.lock().unwrap();
+        let index = state.devices.iter().position(|d| d.id == device_id)?;
+        let device = state.devices.remove(index);
+        state.notify(DeviceEvent::Removed {
+            device_id: device.id.clone(),
+        });
+        Some(device)
+    }
+
+    /// Events from now on; `events` wraps this as a stream.
+    pub fn subscribe(&self) -> mpsc::UnboundedReceiver<DeviceEvent> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.state.lock().unwrap().subscribers.push(tx);
+        rx
+    }
+
+    pub fn devices(&self) -> Vec<DeviceInfo> {
+        self.state.lock().unwrap().devices.clone()
+    }
+
+    /// Make `op` fail until `recover`: queries come back empty, actions return false.
+    pub fn fail(&self, op: MockOp) {
+        self.state.lock().unwrap().failing.insert(op);
+    }
+
+    pub fn recover(&self, op: MockOp) {
+        self.state.lock().unwrap().failing.remove(&op);
+    }
+
+    /// Delay every operation by `latency`.
+    pub fn set_latency(&self, latency: Duration) {
+        self.state.lock().unwrap().latency = latency;
+    }
+
+    /// Calls made so far, oldest first.
+    pub fn calls(&self) -> Vec<MockCall> {
+        self.state.lock().unwrap().calls.clone()
+    }
+
+    pub fn clear_calls(&self) {
+        self.state.lock().unwrap().calls.clear();
+    }
+
+    /// The rule set last passed to `apply_rules`.
+    pub fn applied_rules(&self) -> String {
+        self.state.lock().unwrap().rules.clone()
+    }
+
+    /// Record the call and wait out the latency; false if `op` is set to fail.
+    async fn enter(&self, op: MockOp, call: MockCall) -> bool {
+        let latency = {
+            let mut state = self.state.lock().unwrap();
+            state.calls.push(call);
+            state.latency
+        };
+        if !latency.is_zero() {
+            tokio::time::sleep(latency).await;
+        }
+        !self.state.lock().unwrap().failing.contains(&op)
+    }
+
+    /// Set the `allowed` flag of a connected device, reporting a change; false if there is
+    /// no such device.
+    fn set_allowed(&self, device_id: &str, allowed: bool) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let Some(device) = state.devices.iter_mut().find(|d| d.id == device_id) else {
+            return false;
         };
-        Self {
-            devices: std::sync::Arc::new(std::sync::Mutex::new(vec![sample])),
+        if device.allowed != allowed {
+            device.allowed = allowed;
+            let device = device.clone();
+            state.notify(DeviceEvent::PolicyChanged(device));
         }
+        true
+    }
+}
+
+impl MockState {
+    fn notify(&mut self, event: DeviceEvent) {
+        self.subscribers.retain(|tx| tx.send(event.clone()).is_ok());
     }
 }
 
 #[async_trait]
 impl UsbBackend for MockBackend {
     async fn list_devices(&self) -> Vec<DeviceInfo> {
-        self.devices.lock().unwrap().clone()
+        if !self.enter(MockOp::ListDevices, MockCall::ListDevices).await {
+            return Vec::new();
+        }
+        self.devices()
     }
 
     async fn get_device(&self, device_id: &str) -> Option<DeviceInfo> {
-        self.devices
-            .lock()
-            .unwrap()
-            .iter()
-            .find(|d| d.id == device_id)
-            .cloned()
+        let call = MockCall::GetDevice(device_id.to_string());
+        if !self.enter(MockOp::GetDevice, call).await {
+            return None;
+        }
+        self.devices().into_iter().find(|d| d.id == device_id)
     }
 
-    async fn allow_ephemeral(&self, _device_id: &str, _ttl_secs: u32) -> bool {
-        true
+    async fn allow_ephemeral(&self, device_id: &str, ttl_secs: u32) -> bool {
+        let call = MockCall::AllowEphemeral {
+            device_id: device_id.to_string(),
+            ttl_secs,
+        };
+        self.enter(MockOp::AllowEphemeral, call).await && self.set_allowed(device_id, true)
     }
 
-    async fn revoke(&self, _device_id: &str) -> bool {
-        true
+    async fn revoke(&self, device_id: &str) -> bool {
+        let call = MockCall::Revoke(device_id.to_string());
+        self.enter(MockOp::Revoke, call).await && self.set_allowed(device_id, false)
+    }
+
+    async fn block(&self, device_id: &str) -> bool {
+        let call = MockCall::Block(device_id.to_string());
+        self.enter(MockOp::Block, call).await && self.set_allowed(device_id, false)
+    }
+
+    fn apply_rules(&self, rules: &str) -> Result<(), String> {
+        let mut state = self.state.lock().unwrap();
+        state.calls.push(MockCall::ApplyRules(rules.to_string()));
+        if state.failing.contains(&MockOp::ApplyRules) {
+            return Err("injected failure".into());
+        }
+        state.rules = rules.to_string();
+        Ok(())
+    }
+
+    async fn healthy(&self) -> bool {
+        self.enter(MockOp::Healthy, MockCall::Healthy).await
+    }
+
+    fn events(&self) -> Option<DeviceEventStream> {
+        let events = self.subscribe();
+        Some(Box::pin(futures_util::stream::unfold(
+            events,
+            |mut events| async move { events.recv().await.map(|e| (e, events)) },
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn allow_and_revoke_change_state() {
+        let backend = MockBackend::new_with_sample();
+        assert!(backend.allow_ephemeral("dev1", 60).await);
+        assert!(backend.get_device("dev1").await.unwrap().allowed);
+        assert!(backend.revoke("dev1").await);
+        assert!(!backend.get_device("dev1").await.unwrap().allowed);
+        assert!(!backend.allow_ephemeral("dev9", 60).await);
+        assert_eq!(
+            backend.calls()[..2],
+            [
+                MockCall::AllowEphemeral {
+                    device_id: "dev1".into(),
+                    ttl_secs: 60
+                },
+                MockCall::GetDevice("dev1".into()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn injected_failures_and_latency() {
+        let backend = MockBackend::new_with_sample();
+        backend.fail(MockOp::ListDevices);
+        backend.fail(MockOp::Block);
+        assert!(backend.list_devices().await.is_empty());
+        assert!(!backend.block("dev1").await);
+        assert!(backend.healthy().await);
+        backend.recover(MockOp::ListDevices);
+        assert_eq!(backend.list_devices().await.len(), 1);
+        backend.fail(MockOp::ApplyRules);
+        assert!(backend.apply_rules("allow").is_err());
+        assert_eq!(backend.applied_rules(), "");
+
+        backend.set_latency(Duration::from_millis(20));
+        let started = std::time::Instant::now();
+        backend.get_device("dev1").await;
+        assert!(started.elapsed() >= Duration::from_millis(20));
+    }
+
+    #[tokio::test]
+    async fn plug_allow_and_unplug_are_streamed() {
+        use futures_util::StreamExt;
+
+        let backend = MockBackend::default();
+        let events = backend.events().unwrap();
+        let device = MockBackend::sample_device();
+        backend.plug(device.clone());
+        assert!(backend.allow_ephemeral("dev1", 60).await);
+        assert!(backend.allow_ephemeral("dev1", 60).await);
+        assert_eq!(backend.unplug("dev1").unwrap().id, "dev1");
+        assert!(backend.unplug("dev1").is_none());
+        drop(backend);
+
+        let allowed = DeviceInfo {
+            allowed: true,
+            ..device.clone()
+        };
+        assert_eq!(
+            events.collect::<Vec<_>>().await,
+            [
+                DeviceEvent::Inserted(device),
+                DeviceEvent::PolicyChanged(allowed),
+                DeviceEvent::Removed {
+                    device_id: "dev1".into()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn devices_load_from_json_fixtures() {
+        let json = r#"[{"id": "5", "vendor_id": "0781", "product_id": "5581", "serial": "S1",
+            "fingerprint": "", "device_type": "storage", "allowed": true, "persistent": true}]"#;
+        let backend = MockBackend::from_json(json).unwrap();
+        assert_eq!(backend.devices()[0].device_type, "storage");
+        assert!(backend.devices()[0].interfaces.is_empty());
+        assert!(MockBackend::from_json("{}").is_err());
     }
 }
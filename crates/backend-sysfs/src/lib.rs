@@ -0,0 +1,617 @@
+//! `UsbBackend` on the kernel's USB authorization attributes in sysfs, for systems without
+//! usbguard.
+//!
+//! Devices are the entries of `<root>/bus/usb/devices` with an `idVendor`; their id is the
+//! kernel name (`1-2`, `3-1.4`, `usb1` for root hubs). Each has an `authorized` flag, and so
+//! does each of its interfaces (`1-2:1.0`). Root hubs carry `authorized_default`, which decides
+//! whether new devices start out authorized.
+//!
+//! There is no rule engine in the kernel, so `apply_rules` keeps the `allow`/`block` rules by
+//! id and serial that the daemon generates and enforces them on connected devices and on
+//! every device that shows up afterwards. Arrivals, removals and authorization changes are
+//! found by polling the tree.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use lusby_common::backend::{DeviceEvent, DeviceEventStream, UsbBackend};
+use lusby_common::baseline::normalize_usb_id;
+use lusby_common::fingerprint::FingerprintSource;
+use lusby_common::types::DeviceInfo;
+
+/// Where sysfs is mounted on a running system.
+pub const DEFAULT_ROOT: &str = "/sys";
+
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A persistent rule: `allow id 046d:c534` or `block id 0781:5581 serial "X"`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rule {
+    allow: bool,
+    vendor_id: String,
+    product_id: String,
+    serial: Option<String>,
+}
+
+impl Rule {
+    /// Parse one rule line. Only the subset the daemon generates is understood.
+    fn parse(line: &str) -> Option<Self> {
+        let (target, rest) = line.trim().split_once(' ')?;
+        let allow = match target {
+            "allow" => true,
+            "block" | "reject" => false,
+            _ => return None,
+        };
+        let rest = rest.trim_start().strip_prefix("id ")?.trim_start();
+        let (id, rest) = rest.split_once(' ').unwrap_or((rest, ""));
+        let (vendor_id, product_id) = id.split_once(':')?;
+        let serial = match rest.trim() {
+            "" => None,
+            serial => Some(parse_quoted(serial.strip_prefix("serial ")?.trim_start())?),
+        };
+        Some(Self {
+            allow,
+            vendor_id: normalize_usb_id(vendor_id),
+            product_id: normalize_usb_id(product_id),
+            serial,
+        })
+    }
+
+    fn matches(&self, device: &DeviceInfo) -> bool {
+        normalize_usb_id(&device.vendor_id) == self.vendor_id
+            && normalize_usb_id(&device.product_id) == self.product_id
+            && self.serial.as_ref().is_none_or(|s| *s == device.serial)
+    }
+}
+
+/// The contents of a `"..."` string with `\"` and `\\` escapes, which must end the input.
+fn parse_quoted(s: &str) -> Option<String> {
+    let mut chars = s.strip_prefix('"')?.chars();
+    let mut out = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => out.push(chars.next()?),
+            '"' => return chars.as_str().is_empty().then_some(out),
+            c => out.push(c),
+        }
+    }
+    None
+}
+
+/// Device type from the interface classes, in `DeviceEntry::device_type` terms.
+fn device_type(interfaces: &[String]) -> &'static str {
+    interfaces
+        .iter()
+        .find_map(|i| match i.get(..2)? {
+            "01" => Some("audio"),
+            "02" => Some("serial"),
+            "03" => Some("hid"),
+            "07" => Some("printer"),
+            "08" => Some("storage"),
+            "09" => Some("hub"),
+            "0b" => Some("smartcard"),
+            "0e" => Some("video"),
+            "e0" => Some("wireless"),
+            _ => None,
+        })
+        .unwrap_or("")
+}
+
+#[derive(Clone)]
+pub struct SysfsBackend {
+    root: PathBuf,
+    poll_interval: Duration,
+    rules: Arc<Mutex<Vec<Rule>>>,
+}
+
+impl Default for SysfsBackend {
+    fn default() -> Self {
+        Self::new(DEFAULT_ROOT)
+    }
+}
+
+impl SysfsBackend {
+    /// A backend on the sysfs tree mounted at `root`.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            rules: Arc::default(),
+        }
+    }
+
+    /// How often `events` rescans the tree.
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    fn devices_dir(&self) -> PathBuf {
+        self.root.join("bus/usb/devices")
+    }
+
+    /// Whether devices plugged in from now on start out authorized, on every root hub.
+    pub fn set_authorized_default(&self, authorized: bool) -> io::Result<()> {
+        let value = if authorized { "1" } else { "0" };
+        for entry in fs::read_dir(self.devices_dir())? {
+            let path = entry?.path();
+            if path.join("authorized_default").exists() {
+                fs::write(path.join("authorized_default"), value)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Kernel names of the connected devices; interfaces are skipped.
+    fn device_names(&self) -> Vec<String> {
+        let Ok(entries) = fs::read_dir(self.devices_dir()) else {
+            return Vec::new();
+        };
+        let mut names: Vec<String> = entries
+            .flatten()
+            .filter_map(|e| e.file_name().into_string().ok())
+            .filter(|name| !name.contains(':'))
+            .filter(|name| self.devices_dir().join(name).join("idVendor").exists())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// A device directory for `device_id`, refusing anything that is not a plain name.
+    fn device_dir(&self, device_id: &str) -> Option<PathBuf> {
+        if device_id.is_empty()
+            || device_id.contains(['/', ':'])
+            || device_id.starts_with('.')
+            || !device_id.is_ascii()
+        {
+            return None;
+        }
+        let dir = self.devices_dir().join(device_id);
+        dir.join("idVendor").exists().then_some(dir)
+    }
+
+    /// The interface directories of a device, e.g. `1-2/1-2:1.0`.
+    fn interface_dirs(dir: &Path) -> Vec<PathBuf> {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        let mut dirs: Vec<PathBuf> = entries
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.join("bInterfaceClass").exists())
+            .collect();
+        dirs.sort();
+        dirs
+    }
+
+    fn read_device(&self, device_id: &str) -> Option<DeviceInfo> {
+        let dir = self.device_dir(device_id)?;
+        let attr = |name: &str| {
+            fs::read_to_string(dir.join(name))
+                .ok()
+                .map(|s| s.trim().to_string())
+        };
+        let vendor_id = attr("idVendor")?.to_ascii_lowercase();
+        let product_id = attr("idProduct")?.to_ascii_lowercase();
+        let serial = attr("serial").unwrap_or_default();
+        let interface_dirs = Self::interface_dirs(&dir);
+        let interfaces: Vec<String> = interface_dirs
+            .iter()
+            .filter_map(|i| {
+                let class = |name: &str| {
+                    fs::read_to_string(i.join(name))
+                        .ok()
+                        .map(|s| s.trim().to_ascii_lowercase())
+                };
+                Some(format!(
+                    "{}:{}:{}",
+                    class("bInterfaceClass")?,
+                    class("bInterfaceSubClass")?,
+                    class("bInterfaceProtocol")?
+                ))
+            })
+            .collect();
+        let allowed = attr("authorized").as_deref() == Some("1")
+            && interface_dirs.iter().all(|i| {
+                fs::read_to_string(i.join("authorized")).map_or(true, |s| s.trim() != "0")
+            });
+        let source = FingerprintSource::from_sysfs(&dir);
+        let mut device = DeviceInfo {
+            id: device_id.to_string(),
+            vendor_id: format!("0x{}", vendor_id),
+            product_id: format!("0x{}", product_id),
+            serial,
+            fingerprint: source.as_ref().map(|s| s.fingerprint()).unwrap_or_default(),
+            device_type: device_type(&interfaces).to_string(),
+            allowed,
+            persistent: false,
+            interfaces,
+            legacy_fingerprint: source.map(|s| s.legacy_fingerprint()).unwrap_or_default(),
+        };
+        device.persistent = self.matching_rule(&device).is_some_and(|rule| rule.allow);
+        Some(device)
+    }
+
+    fn matching_rule(&self, device: &DeviceInfo) -> Option<Rule> {
+        let rules = self.rules.lock().unwrap();
+        rules.iter().find(|r| r.matches(device)).cloned()
+    }
+
+    /// Authorize a device and its interfaces, or deauthorize its interfaces and then the
+    /// device, so that drivers are unbound before the configuration goes away.
+    fn set_authorized(&self, device_id: &str, authorized: bool) -> io::Result<()> {
+        let dir = self
+            .device_dir(device_id)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, device_id.to_string()))?;
+        let value = if authorized { "1" } else { "0" };
+        if authorized {
+            fs::write(dir.join("authorized"), value)?;
+        }
+        for interface in Self::interface_dirs(&dir) {
+            if interface.join("authorized").exists() {
+                fs::write(interface.join("authorized"), value)?;
+            }
+        }
+        if !authorized {
+            fs::write(dir.join("authorized"), value)?;
+        }
+        Ok(())
+    }
+
+    /// Apply the first matching rule to a device, if any.
+    fn enforce(&self, device: &DeviceInfo) -> io::Result<()> {
+        match self.matching_rule(device) {
+            Some(rule) if rule.allow != device.allowed => {
+                self.set_authorized(&device.id, rule.allow)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn snapshot(&self) -> BTreeMap<String, DeviceInfo> {
+        self.device_names()
+            .into_iter()
+            .filter_map(|name| Some((name.clone(), self.read_device(&name)?)))
+            .collect()
+    }
+
+    /// Events between two snapshots; arrivals are enforced first and reported as they end up.
+    fn diff(
+        &self,
+        old: &BTreeMap<String, DeviceInfo>,
+        new: &mut BTreeMap<String, DeviceInfo>,
+    ) -> Vec<DeviceEvent> {
+        let mut events = Vec::new();
+        for (id, device) in new.iter_mut() {
+            match old.get(id) {
+                None => {
+                    if self.enforce(device).is_ok() {
+                        if let Some(enforced) = self.read_device(id) {
+                            *device = enforced;
+                        }
+                    }
+                    events.push(DeviceEvent::Inserted(device.clone()));
+                }
+                Some(previous) if previous.allowed != device.allowed => {
+                    events.push(DeviceEvent::PolicyChanged(device.clone()));
+                }
+                Some(_) => {}
+            }
+        }
+        for id in old.keys().filter(|id| !new.contains_key(*id)) {
+            events.push(DeviceEvent::Removed {
+                device_id: id.clone(),
+            });
+        }
+        events
+    }
+}
+
+#[async_trait]
+impl UsbBackend for SysfsBackend {
+    async fn list_devices(&self) -> Vec<DeviceInfo> {
+        self.snapshot().into_values().collect()
+    }
+
+    async fn get_device(&self, device_id: &str) -> Option<DeviceInfo> {
+        self.read_device(device_id)
+    }
+
+    async fn allow_ephemeral(&self, device_id: &str, _ttl_secs: u32) -> bool {
+        // The kernel has no TTL; the daemon's sweep revokes the grant when it runs out
+        self.set_authorized(device_id, true).is_ok()
+    }
+
+    async fn revoke(&self, device_id: &str) -> bool {
+        if self.set_authorized(device_id, false).is_err() {
+            return false;
+        }
+        // Logically disconnect it, as usbguard's reject does; old kernels lack `remove`
+        match self.device_dir(device_id) {
+            Some(dir) if dir.join("remove").exists() => fs::write(dir.join("remove"), "1").is_ok(),
+            _ => true,
+        }
+    }
+
+    async fn block(&self, device_id: &str) -> bool {
+        self.set_authorized(device_id, false).is_ok()
+    }
+
+    fn apply_rules(&self, rules: &str) -> Result<(), String> {
+        let parsed = rules
+            .lines()
+            .filter(|l| !l.trim().is_empty() && !l.trim_start().starts_with('#'))
+            .map(|l| Rule::parse(l).ok_or_else(|| format!("unsupported rule: {}", l.trim())))
+            .collect::<Result<Vec<_>, _>>()?;
+        *self.rules.lock().unwrap() = parsed;
+        for device in self.snapshot().values() {
+            self.enforce(device)
+                .map_err(|e| format!("cannot authorize {}: {}", device.id, e))?;
+        }
+        Ok(())
+    }
+
+    async fn healthy(&self) -> bool {
+        self.devices_dir().is_dir()
+    }
+
+    fn events(&self) -> Option<DeviceEventStream> {
+        let known = self.snapshot();
+        Some(Box::pin(futures_util::stream::unfold(
+            (self.clone(), known, VecDeque::new()),
+            |(backend, mut known, mut pending)| async move {
+                loop {
+                    if let Some(event) = pending.pop_front() {
+                        return Some((event, (backend, known, pending)));
+                    }
+                    tokio::time::sleep(backend.poll_interval).await;
+                    let mut current = backend.snapshot();
+                    pending.extend(backend.diff(&known, &mut current));
+                    known = current;
+                }
+            },
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::StreamExt;
+    use lusby_common::fingerprint::sysfs_fingerprint;
+    use tempfile::TempDir;
+
+    /// A fake `bus/usb/devices` tree with a root hub.
+    struct FakeSysfs {
+        dir: TempDir,
+    }
+
+    impl FakeSysfs {
+        fn new() -> Self {
+            let fake = Self {
+                dir: tempfile::tempdir().unwrap(),
+            };
+            fake.add(
+                "usb1",
+                "1d6b",
+                "0002",
+                None,
+                &[("1-0:1.0", "09", "00", "00")],
+            );
+            fake.write("usb1/authorized_default", "1");
+            fake
+        }
+
+        fn backend(&self) -> SysfsBackend {
+            SysfsBackend::new(self.dir.path()).with_poll_interval(Duration::from_millis(10))
+        }
+
+        fn path(&self, rel: &str) -> PathBuf {
+            self.dir.path().join("bus/usb/devices").join(rel)
+        }
+
+        fn write(&self, rel: &str, value: &str) {
+            fs::write(self.path(rel), value).unwrap();
+        }
+
+        fn read(&self, rel: &str) -> String {
+            fs::read_to_string(self.path(rel)).unwrap()
+        }
+
+        /// A device with `(name, class, subclass, protocol)` interfaces, authorized. It is
+        /// built elsewhere and moved in, so a poll never sees half a device.
+        fn add(
+            &self,
+            name: &str,
+            vendor_id: &str,
+            product_id: &str,
+            serial: Option<&str>,
+            interfaces: &[(&str, &str, &str, &str)],
+        ) {
+            let staging = self.dir.path().join("staging").join(name);
+            let write = |rel: &str, value: &str| {
+                let path = staging.join(rel);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(path, value).unwrap();
+            };
+            write("idVendor", &format!("{vendor_id}\n"));
+            write("idProduct", &format!("{product_id}\n"));
+            write("authorized", "1\n");
+            write("descriptors", "\x12\x01\x00\x02");
+            write("remove", "");
+            if let Some(serial) = serial {
+                write("serial", &format!("{serial}\n"));
+            }
+            for (interface, class, subclass, protocol) in interfaces {
+                write(&format!("{interface}/bInterfaceClass"), class);
+                write(&format!("{interface}/bInterfaceSubClass"), subclass);
+                write(&format!("{interface}/bInterfaceProtocol"), protocol);
+                write(&format!("{interface}/authorized"), "1\n");
+                // The flat directory also lists interfaces
+                fs::create_dir_all(self.path(interface)).unwrap();
+            }
+            fs::create_dir_all(self.path("")).unwrap();
+            fs::rename(staging, self.path(name)).unwrap();
+        }
+
+        fn add_keyboard(&self) {
+            self.add(
+                "1-2",
+                "046d",
+                "c534",
+                Some("ABC"),
+                &[("1-2:1.0", "03", "01", "01"), ("1-2:1.1", "03", "00", "00")],
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn devices_are_read_from_sysfs() {
+        let fake = FakeSysfs::new();
+        fake.add_keyboard();
+        let backend = fake.backend();
+
+        let devices = backend.list_devices().await;
+        assert_eq!(
+            devices.iter().map(|d| d.id.as_str()).collect::<Vec<_>>(),
+            ["1-2", "usb1"]
+        );
+        let keyboard = &devices[0];
+        assert_eq!(keyboard.vendor_id, "0x046d");
+        assert_eq!(keyboard.product_id, "0xc534");
+        assert_eq!(keyboard.serial, "ABC");
+        assert_eq!(keyboard.interfaces, ["03:01:01", "03:00:00"]);
+        assert_eq!(keyboard.device_type, "hid");
+        assert!(keyboard.allowed);
+        assert!(keyboard.fingerprint.starts_with("lfp2:"));
+        assert!(keyboard.legacy_fingerprint.starts_with("sha256:"));
+        assert!(devices[1].is_hub());
+
+        // The fingerprint is the one every backend computes, descriptors included
+        assert_eq!(
+            keyboard.fingerprint,
+            sysfs_fingerprint(&fake.path("1-2")).unwrap()
+        );
+        fake.write("1-2/descriptors", "\x12\x01\x10\x02");
+        assert_ne!(
+            backend.get_device("1-2").await.unwrap().fingerprint,
+            keyboard.fingerprint
+        );
+        assert!(backend.get_device("1-2:1.0").await.is_none());
+        assert!(backend.get_device("../1-2").await.is_none());
+        assert!(backend.healthy().await);
+        assert!(!SysfsBackend::new(fake.path("nothing")).healthy().await);
+    }
+
+    #[tokio::test]
+    async fn allow_block_and_revoke_write_authorized() {
+        let fake = FakeSysfs::new();
+        fake.add_keyboard();
+        let backend = fake.backend();
+
+        assert!(backend.block("1-2").await);
+        assert_eq!(fake.read("1-2/authorized"), "0");
+        assert_eq!(fake.read("1-2/1-2:1.1/authorized"), "0");
+        assert!(!backend.get_device("1-2").await.unwrap().allowed);
+
+        assert!(backend.allow_ephemeral("1-2", 60).await);
+        assert_eq!(fake.read("1-2/authorized"), "1");
+        assert_eq!(fake.read("1-2/1-2:1.0/authorized"), "1");
+        assert!(backend.get_device("1-2").await.unwrap().allowed);
+
+        // A single deauthorized interface means the device is not fully allowed
+        fake.write("1-2/1-2:1.1/authorized", "0");
+        assert!(!backend.get_device("1-2").await.unwrap().allowed);
+
+        assert!(backend.revoke("1-2").await);
+        assert_eq!(fake.read("1-2/authorized"), "0");
+        assert_eq!(fake.read("1-2/remove"), "1");
+        assert!(!backend.block("9-9").await);
+        assert!(!backend.allow_ephemeral("", 60).await);
+
+        backend.set_authorized_default(false).unwrap();
+        assert_eq!(fake.read("usb1/authorized_default"), "0");
+    }
+
+    #[tokio::test]
+    async fn rules_are_enforced_on_connected_devices() {
+        let fake = FakeSysfs::new();
+        fake.add_keyboard();
+        fake.add(
+            "1-3",
+            "0781",
+            "5581",
+            Some("S\"1"),
+            &[("1-3:1.0", "08", "06", "50")],
+        );
+        fake.write("1-2/authorized", "0");
+        let backend = fake.backend();
+
+        let rules = "block id 0781:5581 serial \"S\\\"1\"\nallow id 046d:c534\n";
+        backend.apply_rules(rules).unwrap();
+        assert_eq!(fake.read("1-2/authorized"), "1");
+        assert_eq!(fake.read("1-3/authorized"), "0");
+        let keyboard = backend.get_device("1-2").await.unwrap();
+        assert!(keyboard.allowed && keyboard.persistent);
+        assert!(!backend.get_device("1-3").await.unwrap().persistent);
+
+        assert!(backend
+            .apply_rules("allow with-interface 03:*:*\n")
+            .is_err());
+        assert!(backend
+            .apply_rules("allow id 046d:c534 serial \"open\n")
+            .is_err());
+        // A failed apply keeps the previous rules
+        assert!(backend.get_device("1-2").await.unwrap().persistent);
+    }
+
+    #[tokio::test]
+    async fn arrivals_removals_and_policy_changes_are_polled() {
+        let fake = FakeSysfs::new();
+        let backend = fake.backend();
+        backend.apply_rules("block id 046d:c534\n").unwrap();
+        let mut events = backend.events().unwrap();
+
+        fake.add_keyboard();
+        let DeviceEvent::Inserted(keyboard) = events.next().await.unwrap() else {
+            panic!("expected an insert");
+        };
+        assert_eq!(keyboard.id, "1-2");
+        // The block rule was applied before the device was reported
+        assert!(!keyboard.allowed);
+        assert_eq!(fake.read("1-2/authorized"), "0");
+
+        assert!(backend.allow_ephemeral("1-2", 60).await);
+        assert!(matches!(events.next().await.unwrap(), DeviceEvent::PolicyChanged(d) if d.allowed));
+
+        fs::remove_dir_all(fake.path("1-2")).unwrap();
+        assert_eq!(
+            events.next().await.unwrap(),
+            DeviceEvent::Removed {
+                device_id: "1-2".into()
+            }
+        );
+    }
+
+    #[test]
+    fn rules_parse_the_generated_subset() {
+        assert_eq!(
+            Rule::parse("allow id 0x046D:c534 serial \"A\\\\B\""),
+            Some(Rule {
+                allow: true,
+                vendor_id: "046d".into(),
+                product_id: "c534".into(),
+                serial: Some("A\\B".into()),
+            })
+        );
+        assert!(!Rule::parse("block id 1234:5678").unwrap().allow);
+        assert!(Rule::parse("allow").is_none());
+        assert!(Rule::parse("permit id 1234:5678").is_none());
+        assert!(Rule::parse("allow id 1234:5678 serial \"x\" name \"y\"").is_none());
+    }
+}
@@ -1,11 +1,16 @@
 use async_trait::async_trait;
-use lusby_common::backend::UsbBackend;
+use lusby_common::backend::{DeviceEvent, DeviceEventStream, UsbBackend};
+use lusby_common::baseline::Baseline;
+use lusby_common::fingerprint::FingerprintSource;
+use lusby_common::revocation::RevocationList;
 use lusby_common::types::DeviceInfo;
 use std::fs::{self, File};
-use std::io::Write;
+use std::io::{BufRead, BufReader, Write};
 use std::os::unix::fs::PermissionsExt;
-use std::process::Command;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
 use std::str;
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -14,9 +19,61 @@ pub enum BackendError {
     Cmd(String),
 }
 
+fn sanitize_rule_string(s: &str) -> String {
+    s.chars()
+        .map(|c| if c.is_control() { '?' } else { c })
+        .collect()
+}
+
+fn device_rule(target: &str, vendor_id: &str, product_id: &str, serial: Option<&str>) -> String {
+    // Sanitize IDs
+    let vid = sanitize_rule_string(vendor_id)
+        .trim_start_matches("0x")
+        .to_lowercase();
+    let pid = sanitize_rule_string(product_id)
+        .trim_start_matches("0x")
+        .to_lowercase();
+    let id = format!("{}:{}", vid, pid);
+
+    if let Some(serial) = serial {
+        // Sanitize serial: reject control chars including newlines
+        let sanitized_serial = sanitize_rule_string(serial);
+        format!(
+            "{} id {} serial \"{}\"\n",
+            target,
+            id,
+            sanitized_serial.replace('"', "\\\"")
+        )
+    } else {
+        format!("{} id {}\n", target, id)
+    }
+}
+
+/// Build usbguard `allow` rules for every device entry in a baseline.
+pub fn generate_rules_from_baseline(b: &Baseline) -> String {
+    // Very conservative rule builder: allow by vid:pid and optional serial.
+    // Example: "allow id 046d:c534 serial \"ABC\" with-interface *:*:*".
+    b.devices
+        .iter()
+        .map(|d| device_rule("allow", &d.vendor_id, &d.product_id, d.serial.as_deref()))
+        .collect()
+}
+
+/// Build usbguard `block` rules for revoked devices. These must precede any allow rules,
+/// since usbguard applies the first matching rule.
+pub fn generate_block_rules(list: &RevocationList) -> String {
+    list.devices
+        .iter()
+        .map(|d| device_rule("block", &d.vendor_id, &d.product_id, d.serial.as_deref()))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use futures_util::StreamExt;
+    use lusby_common::baseline::DeviceEntry;
+    use lusby_common::revocation::RevokedDevice;
 
     #[test]
     fn parse_allows_basic_lines() {
@@ -25,7 +82,7 @@ mod tests {
                                     1: block id 046d:c534 serial "ABC123" name "USB Receiver" hash "efgh" parent-hash "..." via-port "2-1" with-interface 03:01:01 with-interface +hid
                                     2: allow id 0781:5581 serial "1234567890ABCDEF" name "SanDisk Ultra" hash "ijkl" parent-hash "..." via-port "2-2" with-interface +mass-storage
                                     "#;
-        let devices = UsbguardBackend::parse_list_devices(sample);
+        let devices = UsbguardBackend::default().parse_list_devices(sample);
         assert!(devices
             .iter()
             .any(|d| d.vendor_id == "0x1d6b" && d.product_id == "0x0002"));
@@ -37,12 +94,119 @@ mod tests {
         assert!(storage.is_some());
     }
 
+    #[test]
+    fn parse_interface_classes() {
+        let sample = r#"
+        4: allow id 1d6b:0002 serial "" name "Hub" with-interface 09:00:00
+        5: block id 0bda:8153 serial "" name "NIC" with-interface { FF:FF:00 02:06:00 0a:00:00 }
+        6: block id 046d:c534 serial "" name "Receiver" with-interface +hid
+        "#;
+        let devices = UsbguardBackend::default().parse_list_devices(sample);
+        assert_eq!(devices[0].id, "4");
+        assert_eq!(devices[0].interfaces, vec!["09:00:00"]);
+        assert_eq!(
+            devices[1].interfaces,
+            vec!["ff:ff:00", "02:06:00", "0a:00:00"]
+        );
+        assert!(devices[2].interfaces.is_empty());
+    }
+
+    #[test]
+    fn watch_output_becomes_device_events() {
+        let output = r#"[IPC] Connected
+[device] PresenceChanged: id=16
+ event=Insert
+ target=block
+ device_rule=block id 0781:5581 serial "4C53" name "Ultra" with-interface 08:06:50
+[device] PolicyChanged: id=16
+ target_old=block
+ target_new=allow
+ device_rule=allow id 0781:5581 serial "4C53" name "Ultra" with-interface 08:06:50
+ rule_id=4294967294
+[device] PresenceChanged: id=16
+ event=Remove
+ target=allow
+ device_rule=allow id 0781:5581 serial "4C53" name "Ultra" with-interface 08:06:50
+"#;
+        let mut parser = WatchParser::default();
+        let events: Vec<DeviceEvent> = output.lines().filter_map(|l| parser.feed(l)).collect();
+        assert_eq!(events.len(), 3);
+        let DeviceEvent::Inserted(inserted) = &events[0] else {
+            panic!("expected an insert, got {:?}", events[0]);
+        };
+        assert_eq!(inserted.id, "16");
+        assert_eq!(inserted.serial, "4C53");
+        assert!(!inserted.allowed);
+        assert!(matches!(&events[1], DeviceEvent::PolicyChanged(d) if d.id == "16" && d.allowed));
+        assert_eq!(
+            events[2],
+            DeviceEvent::Removed {
+                device_id: "16".into()
+            }
+        );
+    }
+
+    #[test]
+    fn watch_is_restarted_when_it_exits() {
+        // Each fake `watch` reports one removal and exits, as when usbguard restarts
+        let removal = |id: u32| {
+            Command::new("sh")
+                .arg("-c")
+                .arg(format!(
+                    "printf '[device] PresenceChanged: id={id}\\n event=Remove\\n device_rule=block id 1234:5678\\n'"
+                ))
+                .stdout(Stdio::piped())
+                .spawn()
+                .ok()
+        };
+        let mut next = 1;
+        let events = watch_stream(UsbguardBackend::default(), removal(0).unwrap(), move || {
+            next += 1;
+            removal(next - 1)
+        });
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let ids: Vec<String> = runtime.block_on(
+            events
+                .take(2)
+                .map(|event| match event {
+                    DeviceEvent::Removed { device_id } => device_id,
+                    other => panic!("expected a removal, got {:?}", other),
+                })
+                .collect(),
+        );
+        assert_eq!(ids, ["0", "1"]);
+    }
+
+    #[test]
+    fn fingerprints_come_from_sysfs_descriptors() {
+        let sysfs = tempfile::tempdir().unwrap();
+        let dir = sysfs.path().join("2-1");
+        fs::create_dir(&dir).unwrap();
+        fs::write(dir.join("idVendor"), "046d\n").unwrap();
+        fs::write(dir.join("idProduct"), "c534\n").unwrap();
+        fs::write(dir.join("descriptors"), [18u8, 1, 0, 2]).unwrap();
+        let backend = UsbguardBackend::with_sysfs_devices(sysfs.path());
+        let sample = r#"
+            1: block id 046d:c534 serial "" name "USB Receiver" via-port "2-1" with-interface 03:01:01
+            2: block id 046d:c534 serial "" name "USB Receiver" via-port "../2-1"
+            3: block id 046d:c534 serial "" name "USB Receiver"
+        "#;
+        let devices = backend.parse_list_devices(sample);
+        let source = FingerprintSource::from_sysfs(&dir).unwrap();
+        assert_eq!(devices[0].fingerprint, source.fingerprint());
+        assert_eq!(devices[0].legacy_fingerprint, source.legacy_fingerprint());
+        assert!(devices[1].fingerprint.is_empty());
+        assert!(devices[2].fingerprint.is_empty());
+    }
+
     #[test]
     fn parse_allows_escaped_quotes() {
         let sample = r#"
         10: allow id 1234:5678 serial "AB\"C" name "Test"
         "#;
-        let devices = UsbguardBackend::parse_list_devices(sample);
+        let devices = UsbguardBackend::default().parse_list_devices(sample);
         let dev = devices.first().unwrap();
         assert_eq!(dev.serial, "AB\"C");
     }
@@ -52,15 +216,261 @@ mod tests {
         #[test]
         fn parser_does_not_crash(s in "\\PC*") {
             // Parser should never panic on random strings
-            let _ = UsbguardBackend::parse_list_devices(&s);
+            let _ = UsbguardBackend::default().parse_list_devices(&s);
         }
     }
+
+    #[test]
+    fn test_generate_rules_safeguard() {
+        let b = Baseline {
+            version: 1,
+            created_by: "test".into(),
+            created_at: chrono::Utc::now(),
+            devices: vec![DeviceEntry {
+                vendor_id: "0x1234".into(),
+                product_id: "0x5678".into(),
+                serial: Some("AB\nC".into()), // malicious newline
+                bus_path: None,
+                descriptors_hash: "none".into(),
+                device_type: "hid".into(),
+                comment: None,
+            }],
+            not_before: None,
+            not_after: None,
+            hosts: Vec::new(),
+            sequence: None,
+            key_id: None,
+            signature: None,
+            signatures: Vec::new(),
+        };
+        let rules = generate_rules_from_baseline(&b);
+        // We expect exactly one line because there is one device.
+        let lines: Vec<&str> = rules.lines().collect();
+        assert_eq!(
+            lines.len(),
+            1,
+            "Should produce exactly one line, found: {:?}",
+            lines
+        );
+        assert!(lines[0].contains("serial \"AB?C\""));
+    }
+
+    #[test]
+    fn block_rules_from_revocation_list() {
+        let list = RevocationList {
+            version: 1,
+            created_by: "test".into(),
+            created_at: chrono::Utc::now(),
+            sequence: 1,
+            fingerprints: vec![],
+            devices: vec![
+                RevokedDevice::parse("0x0781:0x5581").unwrap(),
+                RevokedDevice::parse("1234:abcd:X\"Y").unwrap(),
+            ],
+            revoked_keys: vec![],
+            signatures: vec![],
+        };
+        assert_eq!(
+            generate_block_rules(&list),
+            "block id 0781:5581\nblock id 1234:abcd serial \"X\\\"Y\"\n"
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn rule_generation_safety(
+            vid in "[0-9a-fA-F]{1,10}",
+            pid in "[0-9a-fA-F]{1,10}",
+            serial in proptest::option::of("\\PC*")
+        ) {
+             let b = Baseline {
+                version: 1,
+                created_by: "prop".into(),
+                created_at: chrono::Utc::now(),
+                devices: vec![DeviceEntry {
+                    vendor_id: vid,
+                    product_id: pid,
+                    serial,
+                    bus_path: None,
+                    descriptors_hash: "none".into(),
+                    device_type: "hid".into(),
+                    comment: None,
+                }],
+                not_before: None,
+                not_after: None,
+                hosts: Vec::new(),
+                sequence: None,
+                key_id: None,
+                signature: None,
+                signatures: Vec::new(),
+            };
+            let rules = generate_rules_from_baseline(&b);
+            let lines: Vec<&str> = rules.lines().collect();
+
+            // Safety assertion: The rules string must NOT contain more lines than devices (1).
+            // This ensures no newline injection was successfully performed to create extra rules.
+            // Note: The generator always adds a newline at the end of each rule, so we expect exactly 1 line
+            // if we filter out empty strings or simply count valid rules.
+            // Our generator produces "allow ...\n", so `lines()` (which splits on \n) will see 1 item.
+
+            prop_assert_eq!(lines.len(), 1, "Should produce exactly 1 rule line, found {}", lines.len());
+
+            // Further assertion: The rule should verify basic syntax
+            let rule = lines[0];
+            prop_assert!(rule.starts_with("allow id "));
+
+            // Check for no control characters (except maybe the ones we sanitized to '?' which is safe)
+            // The output should be safe 7-bit ASCII or similar?
+            // Actually, we replaced controls with '?'.
+            // Let's ensure no raw newlines or CRs remain (already checked by lines.len()=1 mostly, but checking chars is good)
+            prop_assert!(!rule.contains('\r'));
+            prop_assert!(!rule.contains('\n'));
+        }
+    }
+}
+
+/// Incremental parser for `usbguard watch` output, where each event is a header line
+/// followed by indented `key=value` lines ending with `device_rule`:
+///
+/// ```text
+/// [device] PresenceChanged: id=16
+///  event=Insert
+///  target=block
+///  device_rule=block id 0781:5581 serial "4C53" name "Ultra" with-interface 08:06:50
+/// ```
+#[derive(Default)]
+struct WatchParser {
+    backend: UsbguardBackend,
+    /// "PresenceChanged" or "PolicyChanged" while inside such an event.
+    kind: Option<String>,
+    id: String,
+    event: String,
+    target_new: String,
+}
+
+impl WatchParser {
+    fn feed(&mut self, line: &str) -> Option<DeviceEvent> {
+        let line = line.trim();
+        if let Some(header) = line.strip_prefix("[device] ") {
+            self.kind = None;
+            let (kind, id) = header.split_once(": id=")?;
+            self.kind = Some(kind.to_string());
+            self.id = id.trim().to_string();
+            self.event.clear();
+            self.target_new.clear();
+            return None;
+        }
+        let (key, value) = line.split_once('=')?;
+        match key {
+            "event" => self.event = value.to_string(),
+            "target_new" => self.target_new = value.to_string(),
+            "device_rule" => {
+                return match (self.kind.take()?.as_str(), self.event.as_str()) {
+                    ("PresenceChanged", "Insert") => self
+                        .backend
+                        .parse_device_rule(&self.id, value)
+                        .map(DeviceEvent::Inserted),
+                    ("PresenceChanged", "Remove") => Some(DeviceEvent::Removed {
+                        device_id: self.id.clone(),
+                    }),
+                    ("PolicyChanged", _) => {
+                        self.backend
+                            .parse_device_rule(&self.id, value)
+                            .map(|device| {
+                                DeviceEvent::PolicyChanged(DeviceInfo {
+                                    allowed: self.target_new == "allow",
+                                    ..device
+                                })
+                            })
+                    }
+                    _ => None,
+                };
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+/// Delay before restarting `usbguard watch`, doubled up to `WATCH_RESTART_MAX` while it keeps
+/// exiting without output.
+const WATCH_RESTART_MIN: Duration = Duration::from_millis(500);
+const WATCH_RESTART_MAX: Duration = Duration::from_secs(30);
+
+/// Device events parsed from `watch` children. A child exits whenever the usbguard daemon
+/// stops, restarts or is upgraded, so another is spawned with backoff; the stream ends only
+/// when it is dropped.
+fn watch_stream(
+    backend: UsbguardBackend,
+    first: Child,
+    mut respawn: impl FnMut() -> Option<Child> + Send + 'static,
+) -> DeviceEventStream {
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        let mut child = Some(first);
+        let mut delay = WATCH_RESTART_MIN;
+        loop {
+            if let Some(mut running) = child.take() {
+                let mut parser = WatchParser {
+                    backend: backend.clone(),
+                    ..WatchParser::default()
+                };
+                let lines = running.stdout.take().map(|out| BufReader::new(out).lines());
+                for line in lines.into_iter().flatten().map_while(Result::ok) {
+                    delay = WATCH_RESTART_MIN;
+                    if let Some(event) = parser.feed(&line) {
+                        if tx.send(event).is_err() {
+                            break;
+                        }
+                    }
+                }
+                let _ = running.kill();
+                let _ = running.wait();
+            }
+            if tx.is_closed() {
+                return;
+            }
+            std::thread::sleep(delay);
+            delay = (delay * 2).min(WATCH_RESTART_MAX);
+            child = respawn();
+        }
+    });
+    Box::pin(futures_util::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|e| (e, rx))
+    }))
+}
+
+/// Where the devices named by usbguard's `via-port` live in sysfs.
+pub const SYSFS_USB_DEVICES: &str = "/sys/bus/usb/devices";
+
+#[derive(Clone)]
+pub struct UsbguardBackend {
+    sysfs_devices: PathBuf,
 }
 
-#[derive(Clone, Default)]
-pub struct UsbguardBackend;
+impl Default for UsbguardBackend {
+    fn default() -> Self {
+        Self::with_sysfs_devices(SYSFS_USB_DEVICES)
+    }
+}
 
 impl UsbguardBackend {
+    /// Read descriptors for fingerprints from `dir` instead of `SYSFS_USB_DEVICES`.
+    pub fn with_sysfs_devices(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            sysfs_devices: dir.into(),
+        }
+    }
+
+    fn spawn_watch() -> Option<Child> {
+        Command::new("usbguard")
+            .arg("watch")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()
+    }
+
     fn run_usbguard(args: &[&str]) -> Result<String, BackendError> {
         let out = Command::new("usbguard")
             .args(args)
@@ -75,76 +485,118 @@ impl UsbguardBackend {
         }
     }
 
-    fn parse_list_devices(output: &str) -> Vec<DeviceInfo> {
+    fn parse_list_devices(&self, output: &str) -> Vec<DeviceInfo> {
         // Very basic parser for `usbguard list-devices` textual output.
         // Example lines (format can vary):
         // 3: allow id 1d6b:0002 serial "" name "xHCI Host Controller" hash "..." parent-hash "..." via-port "..." with-interface ...
         // serial "AB\"C"
-        let mut devices = Vec::new();
-        for line in output.lines() {
-            let line = line.trim();
-            if line.is_empty() {
-                continue;
-            }
-            let mut id = String::new();
-            let mut vendor = String::new();
-            let mut product = String::new();
-            let mut serial = String::new();
-            // Extract id vid:pid
-            if let Some(idx) = line.find(" id ") {
-                let rest = &line[idx + 4..];
-                if let Some(space) = rest.find(' ') {
-                    let pair = &rest[..space];
-                    if let Some(colon) = pair.find(':') {
-                        vendor = format!("0x{}", &pair[..colon]);
-                        product = format!("0x{}", &pair[colon + 1..]);
-                        id = pair.to_string();
-                    }
-                }
+        output
+            .lines()
+            .filter_map(|line| {
+                let (id, rule) = line.trim().split_once(": ")?;
+                self.parse_device_rule(id.trim(), rule)
+            })
+            .collect()
+    }
+
+    /// A device from its usbguard rule, with usbguard's numeric device `id`. The fingerprint
+    /// is computed from the sysfs directory of its `via-port`, as every backend does.
+    fn parse_device_rule(&self, id: &str, rule: &str) -> Option<DeviceInfo> {
+        let rule = rule.trim();
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let mut vendor = String::new();
+        let mut product = String::new();
+        let mut serial = String::new();
+        // Extract id vid:pid
+        if let Some(idx) = rule.find(" id ") {
+            let rest = &rule[idx + 4..];
+            let pair = rest.split(' ').next().unwrap_or(rest);
+            if let Some((v, p)) = pair.split_once(':') {
+                vendor = format!("0x{}", v);
+                product = format!("0x{}", p);
             }
-            // Extract serial "..."
-            if let Some(sidx) = line.find(" serial \"") {
-                let rest = &line[sidx + 9..];
-                let mut s_acc = String::new();
-                let mut escaped = false;
-                for c in rest.chars() {
-                    if escaped {
-                        s_acc.push(c);
-                        escaped = false;
-                    } else if c == '\\' {
-                        escaped = true;
-                    } else if c == '"' {
-                        break;
-                    } else {
-                        s_acc.push(c);
-                    }
+        }
+        // Extract serial "..."
+        if let Some(sidx) = rule.find(" serial \"") {
+            let rest = &rule[sidx + 9..];
+            let mut escaped = false;
+            for c in rest.chars() {
+                if escaped {
+                    serial.push(c);
+                    escaped = false;
+                } else if c == '\\' {
+                    escaped = true;
+                } else if c == '"' {
+                    break;
+                } else {
+                    serial.push(c);
                 }
-                serial = s_acc;
-            }
-            // Guess type by presence of with-interface strings
-            let dtype = if line.contains("with-interface +hid") {
-                "hid"
-            } else if line.contains("with-interface +mass-storage") {
-                "storage"
-            } else {
-                ""
-            };
-
-            if !vendor.is_empty() && !product.is_empty() {
-                // fingerprint unknown here; leave empty; daemon can compute if needed
-                devices.push(DeviceInfo {
-                    id,
-                    vendor_id: vendor,
-                    product_id: product,
-                    serial,
-                    fingerprint: String::new(),
-                    device_type: dtype.to_string(),
-                    allowed: line.starts_with("allow"),
-                    persistent: line.contains("allow "),
-                });
             }
         }
-        devices
+        // Guess type by presence of with-interface strings
+        let dtype = if rule.contains("with-interface +hid") {
+            "hid"
+        } else if rule.contains("with-interface +mass-storage") {
+            "storage"
+        } else {
+            ""
+        };
+        if vendor.is_empty() || product.is_empty() {
+            return None;
+        }
+        let source = Self::via_port(rule)
+            .and_then(|port| FingerprintSource::from_sysfs(&self.sysfs_devices.join(port)));
+        Some(DeviceInfo {
+            id: id.to_string(),
+            vendor_id: vendor,
+            product_id: product,
+            serial,
+            fingerprint: source.as_ref().map(|s| s.fingerprint()).unwrap_or_default(),
+            device_type: dtype.to_string(),
+            allowed: rule.starts_with("allow"),
+            persistent: rule.contains("allow "),
+            interfaces: Self::parse_interfaces(rule),
+            legacy_fingerprint: source.map(|s| s.legacy_fingerprint()).unwrap_or_default(),
+        })
+    }
+
+    /// The kernel name of the device's port, `via-port "1-2"`.
+    fn via_port(rule: &str) -> Option<&str> {
+        let rest = &rule[rule.find(" via-port \"")? + 11..];
+        let port = &rest[..rest.find('"')?];
+        let plain = !port.is_empty() && !port.contains('/') && !port.starts_with('.');
+        plain.then_some(port)
+    }
+
+    /// Interface class triples of a rule: `with-interface 09:00:00` or
+    /// `with-interface { 03:01:01 03:01:02 }`. Named classes like `+hid` are skipped.
+    fn parse_interfaces(line: &str) -> Vec<String> {
+        let Some(idx) = line.find("with-interface ") else {
+            return Vec::new();
+        };
+        let rest = &line[idx + "with-interface ".len()..];
+        let tokens: Vec<&str> = match rest.strip_prefix('{') {
+            Some(set) => set
+                .split('}')
+                .next()
+                .unwrap_or("")
+                .split_whitespace()
+                .collect(),
+            None => rest.split_whitespace().take(1).collect(),
+        };
+        tokens
+            .into_iter()
+            .filter(|t| {
+                let parts: Vec<&str> = t.split(':').collect();
+                parts.len() == 3
+                    && parts
+                        .iter()
+                        .all(|p| p.len() == 2 && p.chars().all(|c| c.is_ascii_hexdigit()))
+            })
+            .map(|t| t.to_ascii_lowercase())
+            .collect()
     }
 
     /// Atomically write new rules content to /etc/usbguard/rules.conf and reload usbguard.
@@ -199,7 +651,7 @@ impl UsbBackend for UsbguardBackend {
             .ok()
             .and_then(|r| r.ok())
         {
-            Some(out) => Self::parse_list_devices(&out),
+            Some(out) => self.parse_list_devices(&out),
             None => Vec::new(),
         }
     }
@@ -233,4 +685,36 @@ impl UsbBackend for UsbguardBackend {
         .map(|r| r.is_ok())
         .unwrap_or(false)
     }
+
+    fn apply_rules(&self, rules: &str) -> Result<(), String> {
+        Self::apply_rules_atomically(rules).map_err(|e| e.to_string())
+    }
+
+    async fn block(&self, device_id: &str) -> bool {
+        let device_id = device_id.to_string();
+        tokio::task::spawn_blocking(move || {
+            let args = ["block-device", &device_id];
+            Self::run_usbguard(&args)
+        })
+        .await
+        .ok()
+        .map(|r| r.is_ok())
+        .unwrap_or(false)
+    }
+
+    fn events(&self) -> Option<DeviceEventStream> {
+        // Without usbguard installed there is nothing to restart; callers fall back to udev
+        let first = Self::spawn_watch()?;
+        Some(watch_stream(self.clone(), first, Self::spawn_watch))
+    }
+
+    async fn healthy(&self) -> bool {
+        // Any IPC query fails while the usbguard daemon is down
+        tokio::task::spawn_blocking(|| {
+            Self::run_usbguard(&["get-parameter", "ImplicitPolicyTarget"])
+        })
+        .await
+        .ok()
+        .is_some_and(|r| r.is_ok())
+    }
 }
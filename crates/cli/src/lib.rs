@@ -12,6 +12,8 @@ pub enum Commands {
     List,
     Info {
         device: String,
+        #[arg(long)]
+        descriptors: bool,
     },
     Status,
     Allow(AllowArgs),
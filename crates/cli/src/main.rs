@@ -1,20 +1,49 @@
 use anyhow::Result;
 use base64::{engine::general_purpose::STANDARD as B64, Engine};
-use chrono::Utc;
-use clap::{Args, Parser, Subcommand};
+use chrono::{DateTime, Utc};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use ed25519_dalek::{SigningKey, VerifyingKey};
+use lusby_backend_usbguard::{generate_rules_from_baseline, SYSFS_USB_DEVICES};
+use lusby_client::DaemonProxy;
 use lusby_common::audit::{verify_chain, AuditEntry};
-use lusby_common::baseline::{Baseline, DeviceEntry};
+use lusby_common::baseline::{
+    diff_baselines, lint_baseline, Baseline, BaselineDiff, DeviceEntry, KeyScope, SignatureStatus,
+};
+use lusby_common::crypto::{canonical_json_vec, key_id, KeySigner, SignatureFormat};
+use lusby_common::descriptors::{
+    parse_descriptors, parse_hid_report, render_tree, DescriptorError,
+};
+use lusby_common::error::Error as DaemonError;
+use lusby_common::fingerprint::{sysfs_fingerprint, FingerprintSource};
+use lusby_common::keyfile::{
+    from_openssh, is_openssh_private_key, openssh_is_encrypted, to_openssh, KeyFile,
+};
+#[cfg(feature = "pkcs11")]
+use lusby_common::pkcs11::{Pkcs11Signer, Pkcs11Uri};
+use lusby_common::revocation::{RevocationList, RevokedDevice};
+use lusby_common::schema::{signed_content, upgrade, BASELINE_VERSION};
+use lusby_common::ssh_agent::{default_socket, openssh_fingerprint, AgentSigner};
+use lusby_common::trust::{
+    parse_ssh_key_list, validate_key_name, SshKeyList, TrustedKey, TrustedKeyMeta,
+};
 use lusby_common::types::DeviceInfo;
 use rand::rngs::OsRng;
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::PathBuf;
-use zbus::Connection;
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
 
 mod tui;
 
 #[derive(Parser)]
-#[command(name = "lusbyctl", version, about = "Lusby CLI")]
+#[command(
+    name = "lusbyctl",
+    version,
+    about = "Lusby CLI",
+    after_help = "Exit status: 0 success, 1 other errors, 2 usage, 3 invalid input, 4 not found, \
+                  5 signature invalid, 6 denied by policy, 7 not authorized, 8 backend failed, \
+                  9 daemon unreachable"
+)]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
@@ -25,7 +54,12 @@ enum Commands {
     /// List devices
     List,
     /// Show info on a device
-    Info { device: String },
+    Info {
+        device: String,
+        /// Print the USB descriptors as a tree (like `lsusb -v`) instead of JSON
+        #[arg(long)]
+        descriptors: bool,
+    },
     /// Show policy status
     Status,
     /// Baseline operations
@@ -43,27 +77,61 @@ enum Commands {
         #[command(subcommand)]
         cmd: KeysCmd,
     },
+    /// Signed device and key revocation lists
+    Revocation {
+        #[command(subcommand)]
+        cmd: RevocationCmd,
+    },
     /// Ephemeral authorization (no root)
     Allow(AllowArgs),
     /// Revoke a device immediately
     Revoke { device: String },
+    /// Compute a device's fingerprint in the current and the legacy scheme
+    Fingerprint(FingerprintArgs),
     /// Launch TUI for baseline editing
     Tui,
 }
 
 #[derive(Subcommand)]
 enum BaselineCmd {
-    /// Generate an Ed25519 keypair and print base64 values
-    Keygen,
-    /// Sign a baseline JSON (canonical JSON) with a base64 secret key, writing signature into the file
-    Sign {
+    /// Generate an Ed25519 keypair; with --out, write a passphrase-encrypted key file
+    Keygen {
+        /// Encrypted key file to write (the raw public key goes to <out>.pub)
         #[arg(long)]
-        secret_b64: String,
+        out: Option<PathBuf>,
+        /// Name stored in the key file metadata
+        #[arg(long)]
+        name: Option<String>,
+        #[arg(long)]
+        passphrase_file: Option<PathBuf>,
+    },
+    /// Add a co-signature (canonical JSON); earlier signatures stay valid
+    Sign {
+        #[command(flatten)]
+        signer: SignerArgs,
         #[arg(long)]
         input: PathBuf,
         #[arg(long)]
         output: PathBuf,
     },
+    /// Convert an OpenSSH Ed25519 private key into an encrypted lusby key file
+    ImportSsh {
+        ssh_key: PathBuf,
+        #[arg(long)]
+        out: PathBuf,
+        #[arg(long)]
+        name: Option<String>,
+    },
+    /// Export a lusby key file as an OpenSSH Ed25519 private key
+    ExportSsh {
+        #[arg(long)]
+        key: PathBuf,
+        #[arg(long)]
+        out: PathBuf,
+        /// Write the OpenSSH key without a passphrase
+        #[arg(long)]
+        no_passphrase: bool,
+    },
     /// Initialize an unsigned baseline from a live device id
     Init {
         device: String,
@@ -71,6 +139,18 @@ enum BaselineCmd {
         serial: Option<String>,
         #[arg(long)]
         comment: Option<String>,
+        /// Not valid before this RFC 3339 timestamp
+        #[arg(long)]
+        not_before: Option<DateTime<Utc>>,
+        /// Not valid after this RFC 3339 timestamp
+        #[arg(long)]
+        not_after: Option<DateTime<Utc>>,
+        /// Bind to a machine-id or hostname pattern (repeatable, `*` and `?` wildcards)
+        #[arg(long = "host")]
+        hosts: Vec<String>,
+        /// Monotonic sequence number; the daemon rejects anything older than it last applied
+        #[arg(long)]
+        sequence: Option<u64>,
         #[arg(long)]
         output: PathBuf,
     },
@@ -78,17 +158,63 @@ enum BaselineCmd {
     Apply {
         #[arg(long)]
         file: PathBuf,
+        /// Trusted key name (as in `keys list`) or key id the baseline must be signed with
         #[arg(long)]
         signer: String,
+        /// Allow re-applying a baseline older than the last applied one (separate polkit action)
+        #[arg(long)]
+        rollback: bool,
     },
-    /// Verify a signed baseline JSON using an ed25519 public key
+    /// Verify a signed baseline JSON and report which of the given ed25519 public keys signed it
     Verify {
+        #[arg(long, required = true)]
+        pubkey: Vec<PathBuf>,
+        file: PathBuf,
+    },
+    /// Show added, removed and modified devices between two baselines
+    Diff {
+        /// Old baseline (omit when using --active)
+        old: Option<PathBuf>,
+        /// New baseline
+        new: Option<PathBuf>,
+        /// Diff this baseline against the set currently active in the daemon
+        #[arg(long, value_name = "NEW", conflicts_with_all = ["old", "new"])]
+        active: Option<PathBuf>,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Print the exact bytes that get signed (RFC 8785 canonical JSON), for signing or
+    /// verifying with other tools
+    Canonicalize {
+        file: PathBuf,
+        /// Print the co-signature payload for this key id instead of the baseline content
         #[arg(long)]
-        pubkey: PathBuf,
+        key_id: Option<String>,
+        /// Signing time for the payload (default: that key's existing co-signature)
+        #[arg(long, requires = "key_id")]
+        signed_at: Option<DateTime<Utc>>,
+    },
+    /// Check a baseline against its schema and report every problem with its JSON path
+    Lint {
         file: PathBuf,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Upgrade a baseline to the current schema version as an unsigned draft for re-signing
+    Migrate {
+        #[arg(long)]
+        input: PathBuf,
+        #[arg(long)]
+        output: PathBuf,
     },
 }
 
+#[derive(Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
 #[derive(Subcommand)]
 enum AuditCmd {
     /// Verify a JSONL audit log chain
@@ -97,16 +223,178 @@ enum AuditCmd {
 
 #[derive(Subcommand)]
 enum KeysCmd {
-    /// Add a trusted public key (raw 32 bytes) from base64
+    /// Add a trusted public key with optional metadata
     Add {
         name: String,
+        #[command(flatten)]
+        key: PubKeySource,
+        /// Human-readable name shown in `keys list`
         #[arg(long)]
-        pub_b64: String,
+        display_name: Option<String>,
+        /// Person or team responsible for the key
+        #[arg(long)]
+        owner: Option<String>,
+        /// Device types the key may sign (repeatable; default: any)
+        #[arg(long = "device-type")]
+        device_types: Vec<String>,
+        /// Stop trusting the key after this RFC 3339 timestamp
+        #[arg(long)]
+        not_after: Option<DateTime<Utc>>,
+    },
+    /// Trust the Ed25519 keys in an OpenSSH authorized_keys or allowed_signers file;
+    /// names come from the principal or comment
+    Import {
+        #[command(flatten)]
+        source: SshKeyListSource,
+        /// Person or team responsible for the keys
+        #[arg(long)]
+        owner: Option<String>,
+        /// Device types the keys may sign (repeatable; default: any)
+        #[arg(long = "device-type")]
+        device_types: Vec<String>,
+    },
+    /// List trusted public keys with their fingerprints and metadata
+    List {
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
     },
-    /// List trusted public keys
-    List,
     /// Remove a trusted public key by name (with or without .pub)
     Remove { name: String },
+    /// Restrict the device types a trusted key may sign (no types lifts the restriction)
+    Scope {
+        name: String,
+        #[arg(long = "device-type")]
+        device_types: Vec<String>,
+    },
+    /// Trust a replacement key with the old key's scope and owner; re-sign baselines with
+    /// the new key, then `keys retire` the old one
+    Rotate {
+        old: String,
+        new: String,
+        #[command(flatten)]
+        key: PubKeySource,
+    },
+    /// Stop trusting a key after rotation (refused while active baselines still need it)
+    Retire {
+        name: String,
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Args)]
+#[group(required = true, multiple = false)]
+struct PubKeySource {
+    /// Raw 32-byte public key in base64
+    #[arg(long)]
+    pub_b64: Option<String>,
+    /// Raw 32-byte public key file, e.g. the `.pub` written by `baseline keygen --out`
+    #[arg(long)]
+    pub_file: Option<PathBuf>,
+}
+
+impl PubKeySource {
+    fn to_b64(&self) -> Result<String> {
+        let raw = match (&self.pub_b64, &self.pub_file) {
+            (Some(b64), _) => B64.decode(b64)?,
+            (None, Some(path)) => fs::read(path)?,
+            (None, None) => anyhow::bail!("a public key is required"),
+        };
+        let raw: [u8; 32] = raw
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("public key must be 32 raw bytes"))?;
+        VerifyingKey::from_bytes(&raw)?;
+        Ok(B64.encode(raw))
+    }
+}
+
+#[derive(Args)]
+#[group(required = true, multiple = false)]
+struct SshKeyListSource {
+    #[arg(long)]
+    authorized_keys: Option<PathBuf>,
+    #[arg(long)]
+    allowed_signers: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum RevocationCmd {
+    /// Write an unsigned revocation list
+    Create {
+        /// Device fingerprint to ban (repeatable)
+        #[arg(long = "fingerprint")]
+        fingerprints: Vec<String>,
+        /// VID:PID or VID:PID:SERIAL to ban (repeatable)
+        #[arg(long = "device")]
+        devices: Vec<String>,
+        /// Signer key to distrust: a key id or a raw public key file (repeatable)
+        #[arg(long = "key")]
+        keys: Vec<String>,
+        /// Monotonic sequence number; the daemon rejects lists older than the applied one
+        #[arg(long)]
+        sequence: u64,
+        #[arg(long)]
+        output: PathBuf,
+    },
+    /// Add a co-signature to a revocation list
+    Sign {
+        #[command(flatten)]
+        signer: SignerArgs,
+        #[arg(long)]
+        input: PathBuf,
+        #[arg(long)]
+        output: PathBuf,
+    },
+    /// Apply a signed revocation list over D-Bus (polkit-gated)
+    Apply {
+        #[arg(long)]
+        file: PathBuf,
+    },
+    /// Print a revocation list file, or the list applied in the daemon
+    Show { file: Option<PathBuf> },
+}
+
+#[derive(Args)]
+#[group(required = true, multiple = false)]
+struct KeySource {
+    /// Encrypted lusby key file or OpenSSH Ed25519 private key; prompts for the passphrase
+    #[arg(long)]
+    key: Option<PathBuf>,
+    /// Key on a PKCS#11 token (RFC 7512 URI with ?module-path=); prompts for the PIN
+    #[cfg(feature = "pkcs11")]
+    #[arg(long)]
+    pkcs11_uri: Option<String>,
+    /// Key held by the running ssh-agent ($SSH_AUTH_SOCK); implies --sshsig
+    #[arg(long)]
+    ssh_agent: bool,
+    /// Raw base64 secret (deprecated: leaks into shell history and process listings)
+    #[arg(long)]
+    secret_b64: Option<String>,
+}
+
+#[derive(Args)]
+struct SignerArgs {
+    #[command(flatten)]
+    source: KeySource,
+    /// Read the passphrase or PIN from this file instead of prompting
+    #[arg(long)]
+    passphrase_file: Option<PathBuf>,
+    /// Agent key to use: SHA256 fingerprint, lusby key id or comment (default: the only one)
+    #[arg(long, requires = "ssh_agent")]
+    ssh_agent_key: Option<String>,
+    /// Write an OpenSSH sshsig signature (namespace "lusby") instead of a raw Ed25519 one
+    #[arg(long)]
+    sshsig: bool,
+}
+
+impl SignerArgs {
+    fn format(&self) -> SignatureFormat {
+        if self.sshsig || self.source.ssh_agent {
+            SignatureFormat::Sshsig
+        } else {
+            SignatureFormat::Ed25519
+        }
+    }
 }
 
 #[derive(Args)]
@@ -118,130 +406,586 @@ struct AllowArgs {
     ttl: u32,
 }
 
+#[derive(Args)]
+struct FingerprintArgs {
+    /// Connected device: sysfs name (e.g., 1-2) or device directory
+    #[arg(
+        required_unless_present = "descriptors",
+        conflicts_with = "descriptors"
+    )]
+    device: Option<String>,
+    /// Raw descriptor file, as copied from sysfs `descriptors`
+    #[arg(long)]
+    descriptors: Option<PathBuf>,
+    /// Strings the descriptor file does not carry
+    #[arg(long, requires = "descriptors")]
+    serial: Option<String>,
+    #[arg(long, requires = "descriptors")]
+    manufacturer: Option<String>,
+    #[arg(long, requires = "descriptors")]
+    product: Option<String>,
+    /// Check a recorded fingerprint (lfp2: or sha256:) instead of printing
+    #[arg(long)]
+    verify: Option<String>,
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+}
+
+impl FingerprintArgs {
+    fn source(&self) -> Result<FingerprintSource> {
+        if let Some(path) = &self.descriptors {
+            let mut source =
+                FingerprintSource::from_descriptors(fs::read(path)?).ok_or_else(|| {
+                    DaemonError::InvalidInput(format!("{}: no device descriptor", path.display()))
+                })?;
+            source.serial = self.serial.clone();
+            source.manufacturer = self.manufacturer.clone();
+            source.product = self.product.clone();
+            return Ok(source);
+        }
+        let device = self.device.as_deref().unwrap_or_default();
+        let dir = if device.contains('/') {
+            PathBuf::from(device)
+        } else {
+            Path::new(SYSFS_USB_DEVICES).join(device)
+        };
+        FingerprintSource::from_sysfs(&dir)
+            .ok_or_else(|| DaemonError::NotFound(format!("USB device {}", dir.display())).into())
+    }
+}
+
+fn signing_key_from_b64(secret_b64: &str) -> Result<SigningKey> {
+    let secret: Zeroizing<[u8; 32]> = Zeroizing::new(
+        B64.decode(secret_b64)?
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("secret must be 32 raw bytes in base64"))?,
+    );
+    Ok(SigningKey::from_bytes(&secret))
+}
+
+/// Read a passphrase from `file` (first line) or prompt on the terminal.
+fn read_passphrase(file: Option<&Path>, prompt: &str, confirm: bool) -> Result<Zeroizing<String>> {
+    if let Some(file) = file {
+        let text = Zeroizing::new(fs::read_to_string(file)?);
+        return Ok(Zeroizing::new(
+            text.lines().next().unwrap_or_default().to_string(),
+        ));
+    }
+    let pass = Zeroizing::new(rpassword::prompt_password(prompt)?);
+    if confirm {
+        let again = Zeroizing::new(rpassword::prompt_password("Confirm passphrase: ")?);
+        if *pass != *again {
+            anyhow::bail!("passphrases do not match");
+        }
+    }
+    Ok(pass)
+}
+
+/// Load a signing key from an encrypted key file or an OpenSSH private key file.
+fn load_key_file(path: &Path, passphrase_file: Option<&Path>) -> Result<SigningKey> {
+    let text = Zeroizing::new(fs::read_to_string(path)?);
+    if is_openssh_private_key(&text) {
+        let pass = if openssh_is_encrypted(&text)? {
+            Some(read_passphrase(
+                passphrase_file,
+                &format!("Passphrase for {}: ", path.display()),
+                false,
+            )?)
+        } else {
+            None
+        };
+        return Ok(from_openssh(&text, pass.as_deref().map(String::as_str))?.0);
+    }
+    let file: KeyFile = serde_json::from_str(&text)?;
+    let pass = read_passphrase(
+        passphrase_file,
+        &format!("Passphrase for {} ({}): ", file.name, path.display()),
+        false,
+    )?;
+    Ok(file.decrypt(&pass)?)
+}
+
+fn load_signing_key(args: &SignerArgs) -> Result<Box<dyn KeySigner>> {
+    let source = &args.source;
+    if let Some(path) = &source.key {
+        return Ok(Box::new(load_key_file(
+            path,
+            args.passphrase_file.as_deref(),
+        )?));
+    }
+    #[cfg(feature = "pkcs11")]
+    if let Some(uri) = &source.pkcs11_uri {
+        let uri = Pkcs11Uri::parse(uri)?;
+        let pin = match (uri.pin()?, &args.passphrase_file) {
+            (Some(_), _) => None,
+            (None, file) => Some(read_passphrase(
+                file.as_deref(),
+                &format!("PIN for {}: ", uri.token.as_deref().unwrap_or("token")),
+                false,
+            )?),
+        };
+        return Ok(Box::new(Pkcs11Signer::open(
+            &uri,
+            pin.as_deref().map(String::as_str),
+        )?));
+    }
+    if source.ssh_agent {
+        let signer = AgentSigner::connect(default_socket()?, args.ssh_agent_key.as_deref())?;
+        eprintln!(
+            "Using agent key {} {}",
+            openssh_fingerprint(&signer.identity().key),
+            signer.identity().comment
+        );
+        return Ok(Box::new(signer));
+    }
+    match &source.secret_b64 {
+        Some(secret_b64) => {
+            eprintln!(
+                "warning: --secret-b64 exposes the key; prefer --key with an encrypted key file"
+            );
+            Ok(Box::new(signing_key_from_b64(secret_b64)?))
+        }
+        None => anyhow::bail!("a signing key is required"),
+    }
+}
+
+/// Write `contents` to a new file readable only by the owner.
+fn write_private(path: &Path, contents: &[u8]) -> Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+    let mut f = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)?;
+    f.write_all(contents)?;
+    Ok(())
+}
+
+/// Signing a baseline that expires within this many days prints a warning.
+const EXPIRY_WARN_DAYS: i64 = 7;
+
+fn warn_if_expiring(baseline: &Baseline) {
+    if let Some(not_after) = baseline.not_after {
+        let now = Utc::now();
+        if not_after <= now {
+            eprintln!(
+                "warning: baseline already expired at {}",
+                not_after.to_rfc3339()
+            );
+        } else if not_after - now < chrono::Duration::days(EXPIRY_WARN_DAYS) {
+            eprintln!(
+                "warning: baseline expires soon ({}, in {} hours)",
+                not_after.to_rfc3339(),
+                (not_after - now).num_hours()
+            );
+        }
+    }
+}
+
+/// The sysfs directory of a device the daemon reported. Every backend fingerprints devices
+/// from sysfs, so the fingerprint finds it whatever ids the backend uses.
+fn sysfs_device_dir(info: &DeviceInfo) -> Result<PathBuf> {
+    let root = Path::new(SYSFS_USB_DEVICES);
+    if info.fingerprint.is_empty() {
+        let dir = root.join(&info.id);
+        if dir.join("descriptors").exists() {
+            return Ok(dir);
+        }
+    } else {
+        for entry in fs::read_dir(root)? {
+            let dir = entry?.path();
+            if sysfs_fingerprint(&dir).as_deref() == Some(info.fingerprint.as_str()) {
+                return Ok(dir);
+            }
+        }
+    }
+    Err(DaemonError::NotFound(format!("no sysfs descriptors for device {}", info.id)).into())
+}
+
+/// The descriptor tree of a sysfs device, with the strings and HID report descriptors sysfs
+/// exposes for its active configuration.
+fn sysfs_descriptor_tree(dir: &Path) -> Result<String> {
+    let invalid = |e: DescriptorError| DaemonError::InvalidInput(e.to_string());
+    let mut descriptors =
+        parse_descriptors(&fs::read(dir.join("descriptors"))?).map_err(invalid)?;
+    let attr = |dir: &Path, name: &str| {
+        fs::read_to_string(dir.join(name))
+            .ok()
+            .map(|s| s.trim().to_string())
+    };
+    let hex_attr =
+        |dir: &Path, name: &str| attr(dir, name).and_then(|s| u8::from_str_radix(&s, 16).ok());
+
+    let mut strings = BTreeMap::new();
+    let device = &descriptors.device;
+    for (index, name) in [
+        (device.manufacturer_index, "manufacturer"),
+        (device.product_index, "product"),
+        (device.serial_index, "serial"),
+    ] {
+        if let Some(text) = attr(dir, name).filter(|_| index != 0) {
+            strings.insert(index, text);
+        }
+    }
+    // Interfaces are <device>:<config>.<interface> directories; HID ones hold the report
+    // descriptor in their hid device directory
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let (Some(number), Some(alternate)) = (
+            hex_attr(&path, "bInterfaceNumber"),
+            hex_attr(&path, "bAlternateSetting"),
+        ) else {
+            continue;
+        };
+        let Some(interface) = descriptors
+            .interfaces_mut()
+            .find(|i| i.number == number && i.alternate == alternate)
+        else {
+            continue;
+        };
+        if let Some(text) = attr(&path, "interface").filter(|_| interface.string_index != 0) {
+            strings.insert(interface.string_index, text);
+        }
+        let Some(hid) = interface.hid.as_mut() else {
+            continue;
+        };
+        for child in fs::read_dir(&path)?.flatten() {
+            if let Ok(report) = fs::read(child.path().join("report_descriptor")) {
+                hid.report = parse_hid_report(&report).map_err(invalid)?;
+            }
+        }
+    }
+    Ok(render_tree(&descriptors, &strings))
+}
+
+/// Connect to the daemon on the system bus; offline commands such as keygen and sign skip it.
+async fn daemon_proxy() -> zbus::Result<DaemonProxy<'static>> {
+    lusby_client::connect().await
+}
+
 #[tokio::main]
-async fn main() -> Result<()> {
-    let cli = Cli::parse();
-    let conn = Connection::system().await?;
-    let proxy = zbus::Proxy::new(
-        &conn,
-        "org.lusby.Daemon",
-        "/org/lusby/Daemon",
-        "org.lusby.Daemon",
-    )
-    .await?;
+async fn main() {
+    if let Err(e) = run(Cli::parse()).await {
+        // Failed daemon calls carry an org.lusby.Error name that picks the exit status
+        let (message, code) = match e.downcast::<zbus::Error>() {
+            Ok(e) => {
+                let e = DaemonError::from(e);
+                (e.to_string(), e.exit_code())
+            }
+            Err(e) => match e.downcast::<DaemonError>() {
+                Ok(e) => (e.to_string(), e.exit_code()),
+                Err(e) => (format!("{:#}", e), 1),
+            },
+        };
+        eprintln!("Error: {}", message);
+        std::process::exit(code);
+    }
+}
+
+async fn run(cli: Cli) -> Result<()> {
     match cli.command {
         Commands::List => {
-            let devices: Vec<DeviceInfo> = proxy.call("list_devices", &()).await?;
+            let devices: Vec<DeviceInfo> = daemon_proxy().await?.list_devices().await?;
             println!("{}", serde_json::to_string_pretty(&devices)?);
         }
-        Commands::Info { device } => {
-            let info: DeviceInfo = proxy.call("get_device_info", &(device)).await?;
-            println!("{}", serde_json::to_string_pretty(&info)?);
+        Commands::Info {
+            device,
+            descriptors,
+        } => {
+            let info: DeviceInfo = daemon_proxy().await?.get_device_info(&device).await?;
+            if descriptors {
+                print!("{}", sysfs_descriptor_tree(&sysfs_device_dir(&info)?)?);
+            } else {
+                println!("{}", serde_json::to_string_pretty(&info)?);
+            }
         }
         Commands::Status => {
             let status: lusby_common::types::PolicyStatus =
-                proxy.call("get_policy_status", &()).await?;
+                daemon_proxy().await?.get_policy_status().await?;
             println!("{}", serde_json::to_string_pretty(&status)?);
         }
-        Commands::Baseline { cmd } => {
-            match cmd {
-                BaselineCmd::Keygen => {
-                    let sk = SigningKey::generate(&mut OsRng);
-                    let pk = sk.verifying_key();
-                    println!("SECRET_B64={}", B64.encode(sk.to_bytes()));
-                    println!("PUB_RAW32_B64={}", B64.encode(pk.to_bytes()));
-                }
-                BaselineCmd::Sign {
-                    secret_b64,
-                    input,
-                    output,
-                } => {
-                    let data = fs::read(&input)?;
-                    let mut baseline: Baseline = serde_json::from_slice(&data)?;
-                    // decode 32-byte secret
-                    let secret = B64.decode(secret_b64)?;
-                    if secret.len() != 32 {
-                        anyhow::bail!("secret must be 32 raw bytes in base64");
+        Commands::Baseline { cmd } => match cmd {
+            BaselineCmd::Keygen {
+                out,
+                name,
+                passphrase_file,
+            } => {
+                let sk = SigningKey::generate(&mut OsRng);
+                let pk = sk.verifying_key();
+                match out {
+                    Some(out) => {
+                        let pass =
+                            read_passphrase(passphrase_file.as_deref(), "New passphrase: ", true)?;
+                        if pass.is_empty() {
+                            anyhow::bail!("refusing to write a key file with an empty passphrase");
+                        }
+                        let name = name.unwrap_or_else(whoami::username);
+                        let file = KeyFile::encrypt(&sk, &name, &pass)?;
+                        write_private(&out, serde_json::to_string_pretty(&file)?.as_bytes())?;
+                        let mut pub_path = out.clone().into_os_string();
+                        pub_path.push(".pub");
+                        fs::write(&pub_path, pk.to_bytes())?;
+                        println!("Key file written: {}", out.display());
+                        println!("Public key written: {}", PathBuf::from(pub_path).display());
                     }
-                    let sk = SigningKey::from_bytes(&secret.clone().try_into().unwrap());
-                    baseline.sign_attach(&sk).map_err(|e| anyhow::anyhow!(e))?;
-                    fs::write(&output, serde_json::to_string_pretty(&baseline)?)?;
-                    println!("Signed baseline written: {}", output.display());
-                }
-                BaselineCmd::Init {
-                    device,
-                    serial,
-                    comment,
-                    output,
-                } => {
-                    let info: DeviceInfo = proxy.call("get_device_info", &(device)).await?;
-                    if info.id.is_empty() {
-                        anyhow::bail!("device not found");
+                    None => {
+                        eprintln!("warning: printing the secret key; prefer --out <key file>");
+                        println!("SECRET_B64={}", B64.encode(sk.to_bytes()));
                     }
-                    let dev = DeviceEntry {
-                        vendor_id: info.vendor_id,
-                        product_id: info.product_id,
-                        serial: serial.or({
-                            if info.serial.is_empty() {
-                                None
-                            } else {
-                                Some(info.serial)
-                            }
-                        }),
-                        bus_path: None,
-                        descriptors_hash: String::new(),
-                        device_type: if info.device_type.is_empty() {
-                            String::from("")
-                        } else {
-                            info.device_type
-                        },
-                        comment,
-                    };
-                    let baseline = Baseline {
-                        version: 1,
-                        created_by: whoami::username(),
-                        created_at: Utc::now(),
-                        devices: vec![dev],
-                        signature: None,
-                    };
-                    fs::write(&output, serde_json::to_string_pretty(&baseline)?)?;
-                    println!("Baseline draft written: {}", output.display());
                 }
-                BaselineCmd::Apply { file, signer } => {
-                    let path = file.canonicalize()?;
-                    let ok: bool = proxy
-                        .call(
-                            "apply_persistent_allow",
-                            &(path.to_string_lossy().to_string(), signer),
-                        )
-                        .await?;
-                    if ok {
-                        println!("OK");
-                    } else {
-                        anyhow::bail!("apply failed (see daemon logs)");
+                println!("PUB_RAW32_B64={}", B64.encode(pk.to_bytes()));
+                println!("KEY_ID={}", key_id(&pk));
+            }
+            BaselineCmd::ImportSsh { ssh_key, out, name } => {
+                let text = Zeroizing::new(fs::read_to_string(&ssh_key)?);
+                let pass = if openssh_is_encrypted(&text)? {
+                    Some(read_passphrase(None, "OpenSSH key passphrase: ", false)?)
+                } else {
+                    None
+                };
+                let (sk, comment) = from_openssh(&text, pass.as_deref().map(String::as_str))?;
+                let new_pass = read_passphrase(None, "New key file passphrase: ", true)?;
+                if new_pass.is_empty() {
+                    anyhow::bail!("refusing to write a key file with an empty passphrase");
+                }
+                let name = name
+                    .or(Some(comment).filter(|c| !c.is_empty()))
+                    .unwrap_or_else(whoami::username);
+                let file = KeyFile::encrypt(&sk, &name, &new_pass)?;
+                write_private(&out, serde_json::to_string_pretty(&file)?.as_bytes())?;
+                println!("Key file written: {}", out.display());
+                println!("KEY_ID={}", file.key_id);
+            }
+            BaselineCmd::ExportSsh {
+                key,
+                out,
+                no_passphrase,
+            } => {
+                let file: KeyFile = serde_json::from_slice(&fs::read(&key)?)?;
+                let pass =
+                    read_passphrase(None, &format!("Passphrase for {}: ", file.name), false)?;
+                let sk = file.decrypt(&pass)?;
+                let ssh_pass = if no_passphrase {
+                    None
+                } else {
+                    Some(read_passphrase(None, "OpenSSH key passphrase: ", true)?)
+                };
+                let text = to_openssh(&sk, &file.name, ssh_pass.as_deref().map(String::as_str))?;
+                write_private(&out, text.as_bytes())?;
+                println!("OpenSSH key written: {}", out.display());
+            }
+            BaselineCmd::Sign {
+                signer,
+                input,
+                output,
+            } => {
+                let data = fs::read(&input)?;
+                let mut baseline = match lint_baseline(&data) {
+                    Ok(b) => b,
+                    Err(issues) => {
+                        for issue in &issues {
+                            eprintln!("{}: {}", input.display(), issue);
+                        }
+                        anyhow::bail!("refusing to sign an invalid baseline");
                     }
+                };
+                let sk = load_signing_key(&signer)?;
+                warn_if_expiring(&baseline);
+                if baseline.version < BASELINE_VERSION {
+                    eprintln!(
+                        "warning: baseline uses schema version {}; `lusbyctl baseline migrate` \
+                         upgrades it to {}",
+                        baseline.version, BASELINE_VERSION
+                    );
+                }
+                if baseline.sequence.is_none() {
+                    eprintln!(
+                        "warning: baseline has no sequence number and cannot supersede older ones"
+                    );
                 }
-                BaselineCmd::Verify { pubkey, file } => {
-                    let data = fs::read(&file)?;
-                    let baseline: Baseline = serde_json::from_slice(&data)?;
-                    let pk_bytes = fs::read(&pubkey)?;
-                    let vk = VerifyingKey::from_bytes(
+                baseline
+                    .sign_attach_as(sk.as_ref(), signer.format())
+                    .map_err(|e| anyhow::anyhow!(e))?;
+                fs::write(&output, serde_json::to_string_pretty(&baseline)?)?;
+                println!("Signed baseline written: {}", output.display());
+                println!("Signer key id: {}", key_id(&sk.public_key()?));
+                println!("Signatures: {}", baseline.signatures.len());
+            }
+            BaselineCmd::Init {
+                device,
+                serial,
+                comment,
+                not_before,
+                not_after,
+                hosts,
+                sequence,
+                output,
+            } => {
+                let info: DeviceInfo = daemon_proxy().await?.get_device_info(&device).await?;
+                let mut dev = DeviceEntry::from_device_info(&info);
+                if serial.is_some() {
+                    dev.serial = serial;
+                }
+                dev.comment = comment;
+                let baseline = Baseline {
+                    version: BASELINE_VERSION,
+                    created_by: whoami::username(),
+                    created_at: Utc::now(),
+                    devices: vec![dev],
+                    not_before,
+                    not_after,
+                    hosts,
+                    sequence,
+                    key_id: None,
+                    signature: None,
+                    signatures: Vec::new(),
+                };
+                fs::write(&output, serde_json::to_string_pretty(&baseline)?)?;
+                println!("Baseline draft written: {}", output.display());
+            }
+            BaselineCmd::Apply {
+                file,
+                signer,
+                rollback,
+            } => {
+                let path = file.canonicalize()?.to_string_lossy().to_string();
+                let proxy = daemon_proxy().await?;
+                if rollback {
+                    proxy.rollback_persistent_allow(&path, &signer).await?;
+                } else {
+                    proxy.apply_persistent_allow(&path, &signer).await?;
+                }
+                println!("OK");
+            }
+            BaselineCmd::Verify { pubkey, file } => {
+                let data = fs::read(&file)?;
+                let baseline = Baseline::from_json(&data)?;
+                let mut keys = Vec::new();
+                for p in &pubkey {
+                    let pk_bytes = fs::read(p)?;
+                    keys.push(VerifyingKey::from_bytes(
                         &pk_bytes
                             .try_into()
                             .map_err(|_| anyhow::anyhow!("invalid pubkey length"))?,
-                    )?;
-                    let ok = baseline
-                        .verify_signature(&vk)
-                        .map_err(|e| anyhow::anyhow!(e))?;
-                    if ok {
-                        println!("OK");
-                    } else {
-                        eprintln!("FAIL");
-                        std::process::exit(1);
+                    )?);
+                }
+                for check in baseline.signature_report(&keys) {
+                    let status = match check.status {
+                        SignatureStatus::Valid => "VALID",
+                        SignatureStatus::Invalid => "INVALID",
+                        SignatureStatus::UnknownKey => "UNKNOWN KEY",
+                    };
+                    println!(
+                        "{} {} {}",
+                        check.key_id.as_deref().unwrap_or("<unknown key>"),
+                        check
+                            .signed_at
+                            .map(|t| t.to_rfc3339())
+                            .unwrap_or_else(|| "legacy".into()),
+                        status
+                    );
+                }
+                let signers = baseline.verified_key_ids(&keys);
+                if signers.is_empty() {
+                    eprintln!("FAIL");
+                    std::process::exit(1);
+                }
+                println!("OK ({} of {} keys signed)", signers.len(), keys.len());
+            }
+            BaselineCmd::Diff {
+                old,
+                new,
+                active,
+                format,
+            } => {
+                let diff: BaselineDiff = match (old, new, active) {
+                    (_, _, Some(new)) => {
+                        let text = fs::read_to_string(&new)?;
+                        let json: String =
+                            daemon_proxy().await?.diff_active_baseline(&text).await?;
+                        serde_json::from_str(&json)?
+                    }
+                    (Some(old), Some(new), None) => {
+                        let old = Baseline::from_json(&fs::read(&old)?)?;
+                        let new = Baseline::from_json(&fs::read(&new)?)?;
+                        diff_baselines(&old, &new, generate_rules_from_baseline)
+                    }
+                    _ => anyhow::bail!("expected <old> <new> or --active <new>"),
+                };
+                match format {
+                    OutputFormat::Text => print!("{}", diff),
+                    OutputFormat::Json => {
+                        println!("{}", serde_json::to_string_pretty(&diff)?)
                     }
                 }
             }
-        }
+            BaselineCmd::Canonicalize {
+                file,
+                key_id,
+                signed_at,
+            } => {
+                use std::io::Write;
+                let baseline = Baseline::from_json(&fs::read(&file)?)?;
+                let bytes = match key_id {
+                    None => canonical_json_vec(
+                        &signed_content(&baseline).map_err(|e| anyhow::anyhow!(e))?,
+                    )?,
+                    Some(id) => {
+                        let signed_at = match signed_at {
+                            Some(t) => t,
+                            None => baseline
+                                .signatures
+                                .iter()
+                                .find(|s| s.key_id == id)
+                                .map(|s| s.signed_at)
+                                .ok_or_else(|| {
+                                    anyhow::anyhow!("no co-signature by {}; pass --signed-at", id)
+                                })?,
+                        };
+                        baseline
+                            .cosigned_bytes(&id, &signed_at)
+                            .map_err(|e| anyhow::anyhow!(e))?
+                    }
+                };
+                std::io::stdout().write_all(&bytes)?;
+            }
+            BaselineCmd::Lint { file, format } => {
+                let issues = lint_baseline(&fs::read(&file)?).err().unwrap_or_default();
+                match format {
+                    OutputFormat::Text => {
+                        for issue in &issues {
+                            println!("{}: {}", file.display(), issue);
+                        }
+                    }
+                    OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&issues)?),
+                }
+                if !issues.is_empty() {
+                    anyhow::bail!("{} problem(s) found", issues.len());
+                }
+                if let OutputFormat::Text = format {
+                    println!("OK");
+                }
+            }
+            BaselineCmd::Migrate { input, output } => {
+                let baseline = Baseline::from_json(&fs::read(&input)?)?;
+                if baseline.version == BASELINE_VERSION {
+                    println!("Already at schema version {}", BASELINE_VERSION);
+                    return Ok(());
+                }
+                let draft = upgrade(&baseline)?;
+                fs::write(&output, serde_json::to_string_pretty(&draft)?)?;
+                println!(
+                    "Migrated schema version {} -> {}: {}",
+                    baseline.version,
+                    draft.version,
+                    output.display()
+                );
+                if baseline.signature.is_some() || !baseline.signatures.is_empty() {
+                    println!("Signatures were removed; sign the draft again before applying");
+                }
+            }
+        },
         Commands::Audit { cmd } => match cmd {
             AuditCmd::Verify { file } => {
                 let text = fs::read_to_string(&file)?;
@@ -262,52 +1006,258 @@ async fn main() -> Result<()> {
             }
         },
         Commands::Keys { cmd } => match cmd {
-            KeysCmd::Add { name, pub_b64 } => {
-                let ok: bool = proxy.call("add_trusted_pubkey", &(name, pub_b64)).await?;
-                if ok {
-                    println!("OK");
-                } else {
-                    anyhow::bail!("add key failed");
+            KeysCmd::Add {
+                name,
+                key,
+                display_name,
+                owner,
+                device_types,
+                not_after,
+            } => {
+                validate_key_name(&name).map_err(|e| anyhow::anyhow!(e))?;
+                let meta = TrustedKeyMeta {
+                    display_name: display_name.unwrap_or_default(),
+                    owner: owner.unwrap_or_default(),
+                    scope: KeyScope { device_types },
+                    not_after,
+                    ..Default::default()
+                };
+                daemon_proxy()
+                    .await?
+                    .add_trusted_key(&name, &key.to_b64()?, &serde_json::to_string(&meta)?)
+                    .await?;
+                println!("OK");
+            }
+            KeysCmd::Import {
+                source,
+                owner,
+                device_types,
+            } => {
+                let (path, list) = match (source.authorized_keys, source.allowed_signers) {
+                    (Some(p), _) => (p, SshKeyList::AuthorizedKeys),
+                    (None, Some(p)) => (p, SshKeyList::AllowedSigners),
+                    (None, None) => anyhow::bail!("a key list is required"),
+                };
+                let proxy = daemon_proxy().await?;
+                let mut failed = 0;
+                for entry in parse_ssh_key_list(&fs::read_to_string(&path)?, list) {
+                    let entry = match entry {
+                        Ok(entry) => entry,
+                        Err(e) => {
+                            eprintln!("skipped {}", e);
+                            continue;
+                        }
+                    };
+                    let meta = TrustedKeyMeta {
+                        display_name: entry.comment.clone(),
+                        owner: owner.clone().unwrap_or_default(),
+                        scope: KeyScope {
+                            device_types: device_types.clone(),
+                        },
+                        not_after: entry.not_after,
+                        ..Default::default()
+                    };
+                    let b64 = B64.encode(entry.verifying_key.as_bytes());
+                    let added = proxy
+                        .add_trusted_key(&entry.name, &b64, &serde_json::to_string(&meta)?)
+                        .await;
+                    match added {
+                        Ok(()) => println!("{}  {}", entry.name, key_id(&entry.verifying_key)),
+                        // Only refusals of this one key are worth carrying on after
+                        Err(e @ (DaemonError::InvalidInput(_) | DaemonError::PolicyDenied(_))) => {
+                            eprintln!("failed to add {}: {}", entry.name, e);
+                            failed += 1;
+                        }
+                        Err(e) => return Err(e.into()),
+                    }
+                }
+                if failed > 0 {
+                    anyhow::bail!("{} key(s) were not added", failed);
                 }
             }
-            KeysCmd::List => {
-                let names: Vec<String> = proxy.call("list_trusted_pubkeys", &()).await?;
-                for n in names {
-                    println!("{}", n);
+            KeysCmd::List { format } => {
+                let json: String = daemon_proxy().await?.list_trusted_keys().await?;
+                let keys: Vec<TrustedKey> = serde_json::from_str(&json)?;
+                match format {
+                    OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&keys)?),
+                    OutputFormat::Text => {
+                        let now = Utc::now();
+                        for k in keys {
+                            println!("{}  {}", k.name, k.key_id);
+                            if !k.meta.display_name.is_empty() {
+                                println!("    name:      {}", k.meta.display_name);
+                            }
+                            if !k.meta.owner.is_empty() {
+                                println!("    owner:     {}", k.meta.owner);
+                            }
+                            if !k.meta.scope.device_types.is_empty() {
+                                println!("    scope:     {}", k.meta.scope.device_types.join(", "));
+                            }
+                            if let Some(t) = k.meta.not_after {
+                                println!(
+                                    "    not after: {}{}",
+                                    t.to_rfc3339(),
+                                    if k.is_expired(now) { " (expired)" } else { "" }
+                                );
+                            }
+                            if let Some(next) = &k.meta.rotating_to {
+                                println!("    rotating:  to {}", next);
+                            }
+                        }
+                    }
                 }
             }
             KeysCmd::Remove { name } => {
-                let ok: bool = proxy.call("remove_trusted_pubkey", &(name)).await?;
-                if ok {
-                    println!("OK");
-                } else {
-                    anyhow::bail!("remove key failed");
+                daemon_proxy().await?.remove_trusted_pubkey(&name).await?;
+                println!("OK");
+            }
+            KeysCmd::Scope { name, device_types } => {
+                daemon_proxy()
+                    .await?
+                    .set_trusted_key_scope(&name, device_types)
+                    .await?;
+                println!("OK");
+            }
+            KeysCmd::Rotate { old, new, key } => {
+                validate_key_name(&new).map_err(|e| anyhow::anyhow!(e))?;
+                daemon_proxy()
+                    .await?
+                    .rotate_trusted_key(&old, &new, &key.to_b64()?)
+                    .await?;
+                println!("OK: {} now trusted alongside {}", new, old);
+                println!("Next: re-sign and apply active baselines with the new key,");
+                println!("then run `lusbyctl keys retire {}`", old);
+            }
+            KeysCmd::Retire { name, force } => {
+                match daemon_proxy().await?.retire_trusted_key(&name, force).await {
+                    Ok(()) => println!("OK"),
+                    Err(e @ DaemonError::PolicyDenied(_)) => {
+                        eprintln!("hint: re-sign those baselines with the new key, or use --force");
+                        return Err(e.into());
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+            }
+        },
+        Commands::Revocation { cmd } => match cmd {
+            RevocationCmd::Create {
+                fingerprints,
+                devices,
+                keys,
+                sequence,
+                output,
+            } => {
+                let devices = devices
+                    .iter()
+                    .map(|d| {
+                        RevokedDevice::parse(d).ok_or_else(|| {
+                            anyhow::anyhow!("invalid device {d}, expected VID:PID[:SERIAL]")
+                        })
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                let mut revoked_keys = Vec::new();
+                for k in keys {
+                    if k.starts_with("sha256:") {
+                        revoked_keys.push(k);
+                    } else {
+                        let pk_bytes = fs::read(&k)?;
+                        let vk = VerifyingKey::from_bytes(
+                            &pk_bytes
+                                .try_into()
+                                .map_err(|_| anyhow::anyhow!("invalid pubkey length in {k}"))?,
+                        )?;
+                        revoked_keys.push(key_id(&vk));
+                    }
                 }
+                let list = RevocationList {
+                    version: 1,
+                    created_by: whoami::username(),
+                    created_at: Utc::now(),
+                    sequence,
+                    fingerprints,
+                    devices,
+                    revoked_keys,
+                    signatures: Vec::new(),
+                };
+                fs::write(&output, serde_json::to_string_pretty(&list)?)?;
+                println!("Revocation list draft written: {}", output.display());
+            }
+            RevocationCmd::Sign {
+                signer,
+                input,
+                output,
+            } => {
+                let mut list: RevocationList = serde_json::from_slice(&fs::read(&input)?)?;
+                let sk = load_signing_key(&signer)?;
+                list.sign_attach_as(sk.as_ref(), signer.format())
+                    .map_err(|e| anyhow::anyhow!(e))?;
+                fs::write(&output, serde_json::to_string_pretty(&list)?)?;
+                println!("Signed revocation list written: {}", output.display());
+                println!("Signer key id: {}", key_id(&sk.public_key()?));
+            }
+            RevocationCmd::Apply { file } => {
+                let path = file.canonicalize()?;
+                daemon_proxy()
+                    .await?
+                    .apply_revocation_list(&path.to_string_lossy())
+                    .await?;
+                println!("OK");
+            }
+            RevocationCmd::Show { file } => {
+                let list: RevocationList = match file {
+                    Some(f) => serde_json::from_slice(&fs::read(&f)?)?,
+                    None => {
+                        let json: String = daemon_proxy().await?.get_revocation_list().await?;
+                        if json.is_empty() {
+                            println!("No revocation list applied");
+                            return Ok(());
+                        }
+                        serde_json::from_str(&json)?
+                    }
+                };
+                println!("{}", serde_json::to_string_pretty(&list)?);
             }
         },
         Commands::Allow(args) => {
             let uid = unsafe { libc::geteuid() } as u32;
-            let ok: bool = proxy
-                .call("request_ephemeral_allow", &(args.device, args.ttl, uid))
+            daemon_proxy()
+                .await?
+                .request_ephemeral_allow(&args.device, args.ttl, uid)
                 .await?;
-            if ok {
-                println!("OK");
-            } else {
-                eprintln!("FAIL");
-                std::process::exit(1);
-            }
+            println!("OK");
         }
         Commands::Revoke { device } => {
-            let ok: bool = proxy.call("revoke_device", &(device)).await?;
-            if ok {
-                println!("OK");
-            } else {
-                eprintln!("FAIL");
-                std::process::exit(1);
+            daemon_proxy().await?.revoke_device(&device).await?;
+            println!("OK");
+        }
+        Commands::Fingerprint(args) => {
+            let source = args.source()?;
+            if let Some(stored) = &args.verify {
+                if source.verify(stored) {
+                    println!("OK");
+                } else {
+                    eprintln!("FAIL");
+                    std::process::exit(1);
+                }
+                return Ok(());
+            }
+            let (current, legacy) = (source.fingerprint(), source.legacy_fingerprint());
+            match args.format {
+                OutputFormat::Text => {
+                    println!("fingerprint: {}", current);
+                    println!("legacy:      {}", legacy);
+                }
+                OutputFormat::Json => println!(
+                    "{}",
+                    serde_json::to_string_pretty(&serde_json::json!({
+                        "fingerprint": current,
+                        "legacy_fingerprint": legacy,
+                    }))?
+                ),
             }
         }
         Commands::Tui => {
-            let devices: Vec<DeviceInfo> = proxy.call("list_devices", &()).await?;
+            let devices: Vec<DeviceInfo> = daemon_proxy().await?.list_devices().await?;
             match tui::run_baseline_editor(devices) {
                 Ok(Some(baseline)) => {
                     let path: String = format!(
@@ -1,6 +1,7 @@
 use crossterm::event::{self, Event, KeyCode};
 use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
 use lusby_common::baseline::{Baseline, DeviceEntry};
+use lusby_common::schema::BASELINE_VERSION;
 use lusby_common::types::DeviceInfo;
 use std::io::{self, Stdout};
 use tui::layout::{Constraint, Direction, Layout};
@@ -50,11 +51,11 @@ pub fn run_baseline_editor(devices: Vec<DeviceInfo>) -> io::Result<Option<Baseli
                         d.serial,
                         d.device_type
                     );
-                    if baseline_devices.iter().any(|bd| {
-                        bd.vendor_id == d.vendor_id
-                            && bd.product_id == d.product_id
-                            && bd.serial.as_deref() == Some(&d.serial)
-                    }) {
+                    let classes = d.interface_classes();
+                    if !classes.is_empty() {
+                        line.push_str(&format!(" ({})", classes.join(", ")));
+                    }
+                    if baseline_devices.iter().any(|bd| bd.is_for(d)) {
                         line.push_str(" [selected]");
                     }
                     ListItem::new(line)
@@ -95,26 +96,10 @@ pub fn run_baseline_editor(devices: Vec<DeviceInfo>) -> io::Result<Option<Baseli
                     }
                     KeyCode::Enter => {
                         let d = &devices[selected];
-                        if let Some(idx) = baseline_devices.iter().position(|bd| {
-                            bd.vendor_id == d.vendor_id
-                                && bd.product_id == d.product_id
-                                && bd.serial.as_deref() == Some(&d.serial)
-                        }) {
+                        if let Some(idx) = baseline_devices.iter().position(|bd| bd.is_for(d)) {
                             baseline_devices.remove(idx);
                         } else {
-                            baseline_devices.push(DeviceEntry {
-                                vendor_id: d.vendor_id.clone(),
-                                product_id: d.product_id.clone(),
-                                serial: if d.serial.is_empty() {
-                                    None
-                                } else {
-                                    Some(d.serial.clone())
-                                },
-                                bus_path: None,
-                                descriptors_hash: String::new(),
-                                device_type: d.device_type.clone(),
-                                comment: None,
-                            });
+                            baseline_devices.push(DeviceEntry::from_device_info(d));
                         }
                     }
                     KeyCode::Char('c') => {
@@ -141,11 +126,17 @@ pub fn run_baseline_editor(devices: Vec<DeviceInfo>) -> io::Result<Option<Baseli
                             })
                             .collect();
                         let baseline: Baseline = Baseline {
-                            version: 1,
+                            version: BASELINE_VERSION,
                             created_by: whoami::username(),
                             created_at: chrono::Utc::now(),
                             devices: devices_with_comment,
+                            not_before: None,
+                            not_after: None,
+                            hosts: Vec::new(),
+                            sequence: None,
+                            key_id: None,
                             signature: None,
+                            signatures: Vec::new(),
                         };
                         return Ok(Some(baseline));
                     }
@@ -19,6 +19,18 @@ fn test_cli_parsing_status() {
     }
 }
 
+#[test]
+fn test_cli_parsing_info_descriptors() {
+    let cli = Cli::parse_from(["lusbyctl", "info", "1-2", "--descriptors"]);
+    match cli.command {
+        Commands::Info {
+            device,
+            descriptors,
+        } => assert!(device == "1-2" && descriptors),
+        _ => panic!("Info command not parsed correctly"),
+    }
+}
+
 #[test]
 fn test_cli_command_help() {
     // CommandFactory erzeugt die Hilfe, sollte nicht paniken
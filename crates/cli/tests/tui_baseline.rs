@@ -12,6 +12,8 @@ fn make_device(vendor_id: &str, product_id: &str, serial: &str, device_type: &st
         device_type: device_type.to_string(),
         allowed: false,
         persistent: false,
+        interfaces: Vec::new(),
+        legacy_fingerprint: String::new(),
     }
 }
 
@@ -77,7 +79,13 @@ fn test_baseline_serialization_roundtrip() {
         created_by: "tester".to_string(),
         created_at: Utc::now(),
         devices: vec![entry],
+        not_before: None,
+        not_after: None,
+        hosts: Vec::new(),
+        sequence: None,
+        key_id: None,
         signature: None,
+        signatures: Vec::new(),
     };
     let json = serde_json::to_string_pretty(&baseline).unwrap();
     let baseline2: Baseline = serde_json::from_str(&json).unwrap();
@@ -0,0 +1,150 @@
+//! Typed client for `org.lusby.Daemon`, shared by `lusbyctl`, the tray and the tests.
+//!
+//! Methods that change state fail with [`Error`], whose variants mirror the daemon's
+//! `org.lusby.Error.*` replies. `tests/introspection.rs` checks this trait against the
+//! interface the daemon actually exports, so keep both in step. Properties other than `Mode`,
+//! `DenyUnknown` and `Version` announce changes, so `receive_*_changed` streams replace polling.
+
+use lusby_common::types::{DeviceInfo, EphemeralGrant, PolicyStatus};
+use zbus::proxy;
+
+pub use lusby_common::error::{Error, Result};
+
+pub const SERVICE: &str = "org.lusby.Daemon";
+pub const PATH: &str = "/org/lusby/Daemon";
+/// Parent of the per-device objects; enumerate them with `ObjectManagerProxy` at [`PATH`].
+pub const DEVICES_PATH: &str = "/org/lusby/Daemon/devices";
+
+#[proxy(
+    interface = "org.lusby.Daemon",
+    default_service = "org.lusby.Daemon",
+    default_path = "/org/lusby/Daemon",
+    gen_blocking = false
+)]
+pub trait Daemon {
+    fn get_policy_status(&self) -> zbus::Result<PolicyStatus>;
+
+    fn get_policy_status_string(&self) -> zbus::Result<String>;
+
+    fn list_devices(&self) -> zbus::Result<Vec<DeviceInfo>>;
+
+    fn get_device_info(&self, device_id: &str) -> Result<DeviceInfo>;
+
+    fn request_ephemeral_allow(&self, device_id: &str, ttl: u32, requester_uid: u32) -> Result<()>;
+
+    fn revoke_device(&self, device_id: &str) -> Result<()>;
+
+    /// Verify and activate the baseline at `baseline_path`, signed by `signer_id`.
+    fn apply_persistent_allow(&self, baseline_path: &str, signer_id: &str) -> Result<()>;
+
+    /// Like `apply_persistent_allow`, but accepts an older sequence number.
+    fn rollback_persistent_allow(&self, baseline_path: &str, signer_id: &str) -> Result<()>;
+
+    /// Diff a baseline (JSON text) against the active set; returns `BaselineDiff` JSON.
+    fn diff_active_baseline(&self, baseline_json: &str) -> Result<String>;
+
+    fn apply_revocation_list(&self, list_path: &str) -> Result<()>;
+
+    /// The applied `RevocationList` as JSON, or an empty string.
+    fn get_revocation_list(&self) -> zbus::Result<String>;
+
+    fn list_trusted_pubkeys(&self) -> Result<Vec<String>>;
+
+    /// Trusted keys as a JSON array of `TrustedKey`.
+    fn list_trusted_keys(&self) -> Result<String>;
+
+    fn add_trusted_pubkey(&self, name: &str, key_bytes_b64: &str) -> Result<()>;
+
+    /// `meta_json` is a `TrustedKeyMeta`.
+    fn add_trusted_key(&self, name: &str, key_bytes_b64: &str, meta_json: &str) -> Result<()>;
+
+    fn remove_trusted_pubkey(&self, name: &str) -> Result<()>;
+
+    fn rotate_trusted_key(&self, old_name: &str, new_name: &str, new_key_b64: &str) -> Result<()>;
+
+    fn retire_trusted_key(&self, name: &str, force: bool) -> Result<()>;
+
+    fn set_trusted_key_scope(&self, name: &str, device_types: Vec<String>) -> Result<()>;
+
+    /// "enforcing" or "permissive".
+    #[zbus(property)]
+    fn mode(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn deny_unknown(&self) -> zbus::Result<bool>;
+
+    #[zbus(property)]
+    fn active_ephemeral_grants(&self) -> zbus::Result<Vec<EphemeralGrant>>;
+
+    #[zbus(property)]
+    fn baseline_count(&self) -> zbus::Result<u32>;
+
+    #[zbus(property)]
+    fn backend_healthy(&self) -> zbus::Result<bool>;
+
+    #[zbus(property)]
+    fn version(&self) -> zbus::Result<String>;
+
+    #[zbus(signal)]
+    fn unknown_device_inserted(&self, device: DeviceInfo) -> zbus::Result<()>;
+
+    /// `entry` is the matching baseline entry as JSON.
+    #[zbus(signal)]
+    fn known_device_inserted(&self, device: DeviceInfo, entry: String) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn device_removed(&self, device_id: String) -> zbus::Result<()>;
+}
+
+/// One connected device, exported at a child of [`DEVICES_PATH`].
+#[proxy(
+    interface = "org.lusby.Device",
+    default_service = "org.lusby.Daemon",
+    gen_blocking = false
+)]
+pub trait Device {
+    /// Allow the device for `ttl` seconds; the grant is recorded for the calling user.
+    fn allow(&self, ttl: u32) -> Result<()>;
+
+    fn block(&self) -> Result<()>;
+
+    fn reject(&self) -> Result<()>;
+
+    /// Returns the path of the daemon's unsigned draft baseline.
+    fn add_to_baseline_draft(&self) -> Result<String>;
+
+    #[zbus(property)]
+    fn id(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn vendor_id(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn product_id(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn serial(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn fingerprint(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn device_type(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn interfaces(&self) -> zbus::Result<Vec<String>>;
+
+    /// "allowed" or "blocked".
+    #[zbus(property)]
+    fn state(&self) -> zbus::Result<String>;
+
+    /// Unix seconds when the ephemeral grant ends, 0 without one.
+    #[zbus(property)]
+    fn grant_expiry(&self) -> zbus::Result<u64>;
+}
+
+/// Connect to the daemon on the system bus.
+pub async fn connect() -> zbus::Result<DaemonProxy<'static>> {
+    let conn = zbus::Connection::system().await?;
+    DaemonProxy::new(&conn).await
+}
@@ -0,0 +1,351 @@
+//! Checks the typed proxy against the interface the daemon really exports. The daemon is
+//! served over a peer-to-peer connection, so no bus or polkit is needed: every method of the
+//! introspection XML must be reachable through `DaemonProxy` with matching arguments, and
+//! every signal must arrive on the proxy's stream. Properties must be readable, and those that
+//! change must announce it with `PropertiesChanged`.
+
+use std::collections::BTreeSet;
+
+use futures_util::StreamExt;
+use lusby_backend_mock::MockBackend;
+use lusby_client::{DaemonProxy, DeviceProxy, Error, DEVICES_PATH, PATH, SERVICE};
+use lusby_common::types::DeviceInfo;
+use lusby_daemon::config::DaemonConfig;
+use lusby_daemon::DaemonState;
+use tokio::net::UnixStream;
+use zbus::{connection, proxy::CacheProperties, Connection, SignalContext};
+
+async fn serve(dir: &std::path::Path) -> (Connection, Connection, DaemonState) {
+    let mut config = DaemonConfig::default();
+    config.paths.audit_log = dir.join("audit.log");
+    config.paths.state_dir = dir.join("state");
+    config.paths.baselines_system = dir.join("baselines");
+    config.paths.trusted_pubkeys = dir.join("keys");
+    let state = DaemonState::from_config(MockBackend::new_with_sample(), &config);
+    let served = state.clone();
+
+    let (a, b) = UnixStream::pair().unwrap();
+    let server = connection::Builder::unix_stream(a)
+        .server(zbus::Guid::generate())
+        .unwrap()
+        .p2p()
+        .serve_at(PATH, served)
+        .unwrap()
+        .serve_at(PATH, zbus::fdo::ObjectManager)
+        .unwrap()
+        .build();
+    let client = connection::Builder::unix_stream(b).p2p().build();
+    let (server, client) = tokio::try_join!(server, client).unwrap();
+    (server, client, state)
+}
+
+/// The call reached the method: anything but an unknown method or mismatched arguments.
+fn dispatched(name: &str, result: Result<(), Error>) {
+    if let Err(Error::ZBus(e)) = result {
+        panic!("{} did not reach the daemon: {}", name, e);
+    }
+}
+
+#[tokio::test]
+async fn proxy_matches_exported_interface() {
+    let dir = tempfile::tempdir().unwrap();
+    let (server, client, _state) = serve(dir.path()).await;
+    let proxy = DaemonProxy::builder(&client)
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await
+        .unwrap();
+
+    let xml = zbus::fdo::IntrospectableProxy::builder(&client)
+        .destination(SERVICE)
+        .unwrap()
+        .path(PATH)
+        .unwrap()
+        .build()
+        .await
+        .unwrap()
+        .introspect()
+        .await
+        .unwrap();
+    let node = zbus_xml::Node::from_reader(xml.as_bytes()).unwrap();
+    let iface = node
+        .interfaces()
+        .iter()
+        .find(|i| i.name() == SERVICE)
+        .expect("org.lusby.Daemon is exported");
+    let exported = |names: Vec<String>| names.into_iter().collect::<BTreeSet<_>>();
+    let methods = exported(
+        iface
+            .methods()
+            .iter()
+            .map(|m| m.name().to_string())
+            .collect(),
+    );
+    let signals = exported(
+        iface
+            .signals()
+            .iter()
+            .map(|s| s.name().to_string())
+            .collect(),
+    );
+    let properties = exported(
+        iface
+            .properties()
+            .iter()
+            .map(|p| p.name().to_string())
+            .collect(),
+    );
+
+    let mut called = BTreeSet::new();
+    let mut call = |name: &str, result: Result<(), Error>| {
+        dispatched(name, result);
+        called.insert(name.to_string());
+    };
+
+    // Unprivileged methods answer with data the proxy has to decode
+    let status = proxy.get_policy_status().await.unwrap();
+    call("GetPolicyStatus", Ok(()));
+    assert_eq!(
+        proxy.get_policy_status_string().await.unwrap(),
+        format!("deny_unknown={}", status.deny_unknown)
+    );
+    call("GetPolicyStatusString", Ok(()));
+    assert_eq!(proxy.list_devices().await.unwrap()[0].id, "dev1");
+    call("ListDevices", Ok(()));
+    assert_eq!(proxy.get_device_info("dev1").await.unwrap().serial, "ABC");
+    call("GetDeviceInfo", Ok(()));
+    assert!(matches!(
+        proxy.get_device_info("dev9").await,
+        Err(Error::NotFound(_))
+    ));
+    assert_eq!(proxy.get_revocation_list().await.unwrap(), "");
+    call("GetRevocationList", Ok(()));
+    call(
+        "RequestEphemeralAllow",
+        proxy.request_ephemeral_allow("dev1", 60, 1000).await,
+    );
+    assert!(matches!(
+        proxy.request_ephemeral_allow("dev1", 0, 1000).await,
+        Err(Error::InvalidInput(_))
+    ));
+    call("RevokeDevice", proxy.revoke_device("dev1").await);
+    assert!(matches!(
+        proxy.diff_active_baseline("{}").await,
+        Err(Error::InvalidInput(_))
+    ));
+    call("DiffActiveBaseline", Ok(()));
+
+    // Privileged methods: without a bus sender polkit cannot authorize anyone
+    let denied = |name: &str, result: Result<(), Error>| {
+        assert!(
+            matches!(result, Err(Error::NotAuthorized(_))),
+            "{}: {:?}",
+            name,
+            result
+        );
+        result
+    };
+    let path = "/nonexistent.json";
+    for (name, result) in [
+        (
+            "ApplyPersistentAllow",
+            proxy.apply_persistent_allow(path, "ops").await,
+        ),
+        (
+            "RollbackPersistentAllow",
+            proxy.rollback_persistent_allow(path, "ops").await,
+        ),
+        (
+            "ApplyRevocationList",
+            proxy.apply_revocation_list(path).await,
+        ),
+        (
+            "ListTrustedPubkeys",
+            proxy.list_trusted_pubkeys().await.map(drop),
+        ),
+        ("ListTrustedKeys", proxy.list_trusted_keys().await.map(drop)),
+        (
+            "AddTrustedPubkey",
+            proxy.add_trusted_pubkey("ops", "").await,
+        ),
+        (
+            "AddTrustedKey",
+            proxy.add_trusted_key("ops", "", "{}").await,
+        ),
+        (
+            "RemoveTrustedPubkey",
+            proxy.remove_trusted_pubkey("ops").await,
+        ),
+        (
+            "RotateTrustedKey",
+            proxy.rotate_trusted_key("ops", "ops-2", "").await,
+        ),
+        (
+            "RetireTrustedKey",
+            proxy.retire_trusted_key("ops", false).await,
+        ),
+        (
+            "SetTrustedKeyScope",
+            proxy.set_trusted_key_scope("ops", vec!["hid".into()]).await,
+        ),
+    ] {
+        call(name, denied(name, result));
+    }
+    assert_eq!(
+        called, methods,
+        "proxy methods differ from the exported ones"
+    );
+
+    // Signals: emitted through the interface, received through the proxy's streams
+    let mut inserted = proxy.receive_unknown_device_inserted().await.unwrap();
+    let mut known = proxy.receive_known_device_inserted().await.unwrap();
+    let mut removed = proxy.receive_device_removed().await.unwrap();
+    let ctxt = SignalContext::new(&server, PATH).unwrap();
+    let device: DeviceInfo = proxy.get_device_info("dev1").await.unwrap();
+    DaemonState::unknown_device_inserted(&ctxt, &device)
+        .await
+        .unwrap();
+    DaemonState::known_device_inserted(&ctxt, &device, "{}")
+        .await
+        .unwrap();
+    DaemonState::device_removed(&ctxt, "dev1").await.unwrap();
+    let got = inserted.next().await.unwrap();
+    assert_eq!(got.args().unwrap().device, device);
+    let got = known.next().await.unwrap();
+    assert_eq!(got.args().unwrap().entry, "{}");
+    let got = removed.next().await.unwrap();
+    assert_eq!(got.args().unwrap().device_id, "dev1");
+    assert_eq!(
+        signals,
+        exported(vec![
+            "DeviceRemoved".into(),
+            "KnownDeviceInserted".into(),
+            "UnknownDeviceInserted".into()
+        ])
+    );
+
+    // Properties: read back through the proxy's getters
+    assert_eq!(
+        proxy.mode().await.unwrap(),
+        if status.deny_unknown {
+            "enforcing"
+        } else {
+            "permissive"
+        }
+    );
+    assert_eq!(proxy.deny_unknown().await.unwrap(), status.deny_unknown);
+    assert!(proxy.active_ephemeral_grants().await.unwrap().is_empty());
+    assert_eq!(proxy.baseline_count().await.unwrap(), 0);
+    assert!(proxy.backend_healthy().await.unwrap());
+    assert_eq!(proxy.version().await.unwrap(), env!("CARGO_PKG_VERSION"));
+    assert_eq!(
+        properties,
+        exported(
+            [
+                "ActiveEphemeralGrants",
+                "BackendHealthy",
+                "BaselineCount",
+                "DenyUnknown",
+                "Mode",
+                "Version",
+            ]
+            .map(String::from)
+            .to_vec()
+        )
+    );
+}
+
+#[tokio::test]
+async fn grants_announce_property_changes() {
+    let dir = tempfile::tempdir().unwrap();
+    let (_server, client, _state) = serve(dir.path()).await;
+    // Change streams are fed by the property cache
+    let proxy = DaemonProxy::builder(&client).build().await.unwrap();
+    let mut grants = proxy.receive_active_ephemeral_grants_changed().await;
+    // The first item is the current value
+    assert!(grants.next().await.unwrap().get().await.unwrap().is_empty());
+
+    proxy
+        .request_ephemeral_allow("dev1", 60, 1000)
+        .await
+        .unwrap();
+    let granted = grants.next().await.unwrap().get().await.unwrap();
+    assert_eq!(granted.len(), 1);
+    assert_eq!(granted[0].device_id, "dev1");
+    assert_eq!(granted[0].requester_uid, 1000);
+
+    proxy.revoke_device("dev1").await.unwrap();
+    assert!(grants.next().await.unwrap().get().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn devices_are_exported_as_objects() {
+    let dir = tempfile::tempdir().unwrap();
+    let (server, client, state) = serve(dir.path()).await;
+    state.sync_device_objects(&server).await.unwrap();
+
+    let managed = zbus::fdo::ObjectManagerProxy::builder(&client)
+        .destination(SERVICE)
+        .unwrap()
+        .path(PATH)
+        .unwrap()
+        .build()
+        .await
+        .unwrap()
+        .get_managed_objects()
+        .await
+        .unwrap();
+    let devices: Vec<_> = managed
+        .into_iter()
+        .filter(|(_, interfaces)| interfaces.keys().any(|i| i.as_str() == "org.lusby.Device"))
+        .map(|(path, _)| path)
+        .collect();
+    assert_eq!(devices.len(), 1);
+    let path = devices.into_iter().next().unwrap();
+    assert!(path.as_str().starts_with(DEVICES_PATH));
+
+    let device = DeviceProxy::builder(&client)
+        .path(path)
+        .unwrap()
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await
+        .unwrap();
+    assert_eq!(device.id().await.unwrap(), "dev1");
+    assert_eq!(device.serial().await.unwrap(), "ABC");
+    assert_eq!(device.interfaces().await.unwrap(), vec!["03:01:02"]);
+    assert_eq!(device.state().await.unwrap(), "blocked");
+    assert_eq!(device.grant_expiry().await.unwrap(), 0);
+
+    // A peer-to-peer caller has no bus name to resolve a uid from, so nobody to grant to
+    assert!(matches!(
+        device.allow(60).await,
+        Err(Error::NotAuthorized(_))
+    ));
+    assert_eq!(device.state().await.unwrap(), "blocked");
+    state.grant_ephemeral("dev1", 60, 1000).await.unwrap();
+    state.sync_device_objects(&server).await.unwrap();
+    assert_eq!(device.state().await.unwrap(), "allowed");
+    assert!(device.grant_expiry().await.unwrap() > 0);
+    let daemon = DaemonProxy::builder(&client)
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await
+        .unwrap();
+    assert_eq!(
+        daemon.active_ephemeral_grants().await.unwrap()[0].device_id,
+        "dev1"
+    );
+
+    device.block().await.unwrap();
+    assert_eq!(device.state().await.unwrap(), "blocked");
+    assert_eq!(device.grant_expiry().await.unwrap(), 0);
+    assert!(daemon.active_ephemeral_grants().await.unwrap().is_empty());
+    device.reject().await.unwrap();
+
+    // Writing the draft baseline needs polkit
+    assert!(matches!(
+        device.add_to_baseline_draft().await,
+        Err(Error::NotAuthorized(_))
+    ));
+}
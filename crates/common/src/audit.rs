@@ -9,6 +9,9 @@ pub struct AuditEntryPayload {
     pub device_fingerprint: Option<String>,
     pub action: String,
     pub requester_uid: Option<u32>,
+    /// Key id of the signer for baseline operations.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signer_key_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
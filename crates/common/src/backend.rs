@@ -1,5 +1,22 @@
+use std::pin::Pin;
+
 use crate::types::DeviceInfo;
 use async_trait::async_trait;
+use futures_core::Stream;
+
+/// A change reported by a backend. Device ids are the backend's own, the same ones its
+/// `allow_ephemeral`, `revoke` and `block` accept.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceEvent {
+    Inserted(DeviceInfo),
+    Removed {
+        device_id: String,
+    },
+    /// The device's authorization changed; `allowed` holds the new state.
+    PolicyChanged(DeviceInfo),
+}
+
+pub type DeviceEventStream = Pin<Box<dyn Stream<Item = DeviceEvent> + Send>>;
 
 #[async_trait]
 pub trait UsbBackend: Send + Sync {
@@ -7,4 +24,18 @@ pub trait UsbBackend: Send + Sync {
     async fn get_device(&self, device_id: &str) -> Option<DeviceInfo>;
     async fn allow_ephemeral(&self, device_id: &str, ttl_secs: u32) -> bool;
     async fn revoke(&self, device_id: &str) -> bool;
+    /// Keep the device connected but unauthorized, unlike `revoke` which rejects it.
+    async fn block(&self, device_id: &str) -> bool;
+    /// Replace the persistent rule set (usbguard rule syntax) generated from the active
+    /// baselines and revocation list.
+    fn apply_rules(&self, rules: &str) -> Result<(), String>;
+    /// Whether the backend can currently enforce policy (e.g. its service is reachable).
+    async fn healthy(&self) -> bool {
+        true
+    }
+    /// Start watching for device events. `None` if the backend cannot report them; the
+    /// daemon then relies on its periodic device sync.
+    fn events(&self) -> Option<DeviceEventStream> {
+        None
+    }
 }
@@ -1,10 +1,17 @@
 use chrono::{DateTime, Utc};
-use ed25519_dalek::{SigningKey, VerifyingKey};
+use ed25519_dalek::VerifyingKey;
 use serde::{Deserialize, Serialize};
 
-use crate::crypto::{sign_canonical, verify_canonical};
+use crate::crypto::{
+    cosign_as, cosigned_bytes, key_id, verify_canonical, verify_cosign, CoSignature, KeySigner,
+    SignatureFormat,
+};
+use crate::schema::{self, SchemaError};
+use crate::types::DeviceInfo;
 
+/// A device entry of the current schema version (see `schema`).
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
 pub struct DeviceEntry {
     pub vendor_id: String,
     pub product_id: String,
@@ -18,38 +25,707 @@ pub struct DeviceEntry {
     pub comment: Option<String>,
 }
 
+/// A baseline in the current schema version. Parse files with `Baseline::from_json`, which
+/// also accepts older versions; deserializing directly only accepts the current layout.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
 pub struct Baseline {
+    /// Schema version, see `schema::BASELINE_VERSION`.
     pub version: u32,
     pub created_by: String,
     pub created_at: DateTime<Utc>,
     pub devices: Vec<DeviceEntry>,
+    /// Not valid before this instant.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub not_before: Option<DateTime<Utc>>,
+    /// Not valid after this instant; the daemon deactivates expired baselines.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub not_after: Option<DateTime<Utc>>,
+    /// Machine-id or hostname patterns (`*` and `?` wildcards) this baseline is bound to.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hosts: Vec<String>,
+    /// Monotonic per-signer sequence; older baselines are rejected as replays.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sequence: Option<u64>,
+    /// Key id of a legacy single signer (see `crypto::key_id`); covered by `signature`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_id: Option<String>,
     #[serde(default)]
     pub signature: Option<String>, // base64(ed25519)
+    /// Independent co-signatures; adding one never invalidates the others.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub signatures: Vec<BaselineSignature>,
+}
+
+/// One co-signature over the baseline content, its key id and timestamp.
+pub type BaselineSignature = CoSignature;
+
+/// Field name the baseline content is nested under in co-signed payloads.
+const COSIGN_KIND: &str = "baseline";
+
+/// Outcome of checking one signature against a set of known keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureStatus {
+    Valid,
+    Invalid,
+    UnknownKey,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureCheck {
+    /// `None` for a legacy signature without key id that matched no known key.
+    pub key_id: Option<String>,
+    pub signed_at: Option<DateTime<Utc>>,
+    pub status: SignatureStatus,
+}
+
+/// Restrictions on what a trusted key may sign. Empty lists mean "no restriction".
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct KeyScope {
+    #[serde(default)]
+    pub device_types: Vec<String>,
+}
+
+impl KeyScope {
+    /// Returns the first device entry the scope does not cover, if any.
+    pub fn violation<'a>(&self, baseline: &'a Baseline) -> Option<&'a DeviceEntry> {
+        if self.device_types.is_empty() {
+            return None;
+        }
+        baseline
+            .devices
+            .iter()
+            .find(|d| !self.device_types.contains(&d.device_type))
+    }
+}
+
+/// Identity of the machine a baseline is applied on.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HostIdentity {
+    pub machine_id: String,
+    pub hostname: String,
+}
+
+impl HostIdentity {
+    /// Read `/etc/machine-id` and the kernel hostname.
+    pub fn detect() -> Self {
+        let read = |p: &str| {
+            std::fs::read_to_string(p)
+                .map(|s| s.trim().to_string())
+                .unwrap_or_default()
+        };
+        let mut hostname = read("/proc/sys/kernel/hostname");
+        if hostname.is_empty() {
+            hostname = read("/etc/hostname");
+        }
+        Self {
+            machine_id: read("/etc/machine-id"),
+            hostname,
+        }
+    }
+
+    pub fn matches(&self, pattern: &str) -> bool {
+        (!self.machine_id.is_empty() && glob_match(pattern, &self.machine_id))
+            || (!self.hostname.is_empty() && glob_match(pattern, &self.hostname))
+    }
+}
+
+/// Minimal glob: `*` matches any run of characters, `?` exactly one.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let t: Vec<char> = text.chars().collect();
+    let (mut pi, mut ti) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while ti < t.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == t[ti]) {
+            pi += 1;
+            ti += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ti));
+            pi += 1;
+        } else if let Some((sp, st)) = star {
+            pi = sp + 1;
+            ti = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|c| *c == '*')
 }
 
 impl Baseline {
+    /// Parse a baseline file of any supported schema version.
+    pub fn from_json(data: &[u8]) -> Result<Self, SchemaError> {
+        schema::parse(data)
+    }
+
+    /// Why the baseline may not be used at `now` on `host`, or `None` if it may.
+    pub fn validity_error(&self, now: DateTime<Utc>, host: &HostIdentity) -> Option<String> {
+        if let Some(nb) = self.not_before {
+            if now < nb {
+                return Some(format!("not valid before {}", nb.to_rfc3339()));
+            }
+        }
+        if self.is_expired(now) {
+            return Some(format!(
+                "expired at {}",
+                self.not_after.unwrap_or(now).to_rfc3339()
+            ));
+        }
+        if !self.hosts.is_empty() && !self.hosts.iter().any(|h| host.matches(h)) {
+            return Some("not bound to this host".into());
+        }
+        None
+    }
+
+    /// The first entry that admits `info`, see `DeviceEntry::matches`.
+    pub fn entry_for(&self, info: &DeviceInfo) -> Option<&DeviceEntry> {
+        self.devices.iter().find(|d| d.matches(info))
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.not_after.is_some_and(|na| now > na)
+    }
+
+    /// Content covered by signatures: everything except `signature` and `signatures`.
     pub fn without_signature(&self) -> Self {
         let mut b = self.clone();
         b.signature = None;
+        b.signatures.clear();
         b
     }
 
-    pub fn sign_attach(&mut self, signing_key: &SigningKey) -> Result<(), String> {
-        // Sign canonical form without signature field.
-        let unsigned = self.without_signature();
-        let sig_b64 = sign_canonical(signing_key, &unsigned).map_err(|e| e.to_string())?;
-        self.signature = Some(sig_b64);
+    /// Bytes a co-signature by `key_id` made at `signed_at` covers, for external signers.
+    pub fn cosigned_bytes(
+        &self,
+        key_id: &str,
+        signed_at: &DateTime<Utc>,
+    ) -> Result<Vec<u8>, String> {
+        let content = schema::signed_content(self)?;
+        cosigned_bytes(COSIGN_KIND, &content, key_id, signed_at).map_err(|e| e.to_string())
+    }
+
+    /// Add (or replace) this key's co-signature, keeping all other signatures valid.
+    pub fn sign_attach<S: KeySigner + ?Sized>(&mut self, signer: &S) -> Result<(), String> {
+        self.sign_attach_as(signer, SignatureFormat::Ed25519)
+    }
+
+    /// Like `sign_attach`, choosing between a raw Ed25519 and an OpenSSH `sshsig` signature.
+    pub fn sign_attach_as<S: KeySigner + ?Sized>(
+        &mut self,
+        signer: &S,
+        format: SignatureFormat,
+    ) -> Result<(), String> {
+        let content = schema::signed_content(self)?;
+        let sig = cosign_as(signer, COSIGN_KIND, &content, format).map_err(|e| e.to_string())?;
+        self.signatures.retain(|s| s.key_id != sig.key_id);
+        self.signatures.push(sig);
         Ok(())
     }
 
-    pub fn verify_signature(&self, verifying_key: &VerifyingKey) -> Result<bool, String> {
+    fn verify_legacy(&self, verifying_key: &VerifyingKey) -> Result<bool, String> {
         match &self.signature {
+            Some(_)
+                if self
+                    .key_id
+                    .as_deref()
+                    .is_some_and(|id| id != key_id(verifying_key)) =>
+            {
+                Ok(false)
+            }
             Some(sig) => {
-                let unsigned = self.without_signature();
+                let unsigned = schema::signed_content(self)?;
                 verify_canonical(verifying_key, &unsigned, sig).map_err(|e| e.to_string())
             }
             None => Ok(false),
         }
     }
+
+    fn verify_cosignature(
+        &self,
+        sig: &BaselineSignature,
+        verifying_key: &VerifyingKey,
+    ) -> Result<bool, String> {
+        let content = schema::signed_content(self)?;
+        verify_cosign(verifying_key, COSIGN_KIND, &content, sig).map_err(|e| e.to_string())
+    }
+
+    /// True if `verifying_key` produced a valid signature (legacy or co-signature).
+    pub fn verify_signature(&self, verifying_key: &VerifyingKey) -> Result<bool, String> {
+        if self.verify_legacy(verifying_key)? {
+            return Ok(true);
+        }
+        for sig in &self.signatures {
+            if self.verify_cosignature(sig, verifying_key)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Check every signature on the baseline (legacy first, then co-signatures).
+    pub fn signature_report(&self, keys: &[VerifyingKey]) -> Vec<SignatureCheck> {
+        let mut out = Vec::new();
+        if self.signature.is_some() {
+            let candidates: Vec<&VerifyingKey> = keys
+                .iter()
+                .filter(|vk| self.key_id.as_deref().is_none_or(|id| id == key_id(vk)))
+                .collect();
+            let valid = candidates
+                .iter()
+                .find(|vk| matches!(self.verify_legacy(vk), Ok(true)));
+            let (id, status) = match (valid, candidates.is_empty(), &self.key_id) {
+                (Some(vk), _, _) => (Some(key_id(vk)), SignatureStatus::Valid),
+                (None, true, id) => (id.clone(), SignatureStatus::UnknownKey),
+                (None, false, Some(id)) => (Some(id.clone()), SignatureStatus::Invalid),
+                (None, false, None) => (None, SignatureStatus::UnknownKey),
+            };
+            out.push(SignatureCheck {
+                key_id: id,
+                signed_at: None,
+                status,
+            });
+        }
+        for sig in &self.signatures {
+            let status = match keys.iter().find(|vk| key_id(vk) == sig.key_id) {
+                None => SignatureStatus::UnknownKey,
+                Some(vk) => match self.verify_cosignature(sig, vk) {
+                    Ok(true) => SignatureStatus::Valid,
+                    _ => SignatureStatus::Invalid,
+                },
+            };
+            out.push(SignatureCheck {
+                key_id: Some(sig.key_id.clone()),
+                signed_at: Some(sig.signed_at),
+                status,
+            });
+        }
+        out
+    }
+
+    /// Key ids of the distinct `keys` that validly signed this baseline.
+    pub fn verified_key_ids(&self, keys: &[VerifyingKey]) -> Vec<String> {
+        let mut out: Vec<String> = Vec::new();
+        for vk in keys {
+            let id = key_id(vk);
+            if !out.contains(&id) && matches!(self.verify_signature(vk), Ok(true)) {
+                out.push(id);
+            }
+        }
+        out
+    }
+}
+
+/// Values accepted in `DeviceEntry::device_type`.
+pub const DEVICE_TYPES: &[&str] = &[
+    "hid",
+    "storage",
+    "net",
+    "audio",
+    "video",
+    "printer",
+    "hub",
+    "smartcard",
+    "wireless",
+    "serial",
+    "other",
+];
+
+/// One problem in a baseline, located by a JSONPath such as `$.devices[2].vendor_id`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ValidationIssue {
+    pub path: String,
+    pub message: String,
+}
+
+impl ValidationIssue {
+    fn new(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+impl From<SchemaError> for ValidationIssue {
+    fn from(e: SchemaError) -> Self {
+        match e {
+            SchemaError::Invalid { path, message, .. } => Self::new(path, message),
+            SchemaError::MissingVersion | SchemaError::UnsupportedVersion { .. } => {
+                Self::new("$.version", e.to_string())
+            }
+            SchemaError::Json(e) => Self::new("$", e.to_string()),
+        }
+    }
+}
+
+/// Join issues into one line, e.g. for the audit log.
+pub fn describe_issues(issues: &[ValidationIssue]) -> String {
+    issues
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+fn is_hex(s: &str, len: usize) -> bool {
+    s.len() == len && s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+impl DeviceEntry {
+    /// A draft entry for a connected device: normalized ids, no empty serial or type.
+    pub fn from_device_info(info: &DeviceInfo) -> Self {
+        Self {
+            vendor_id: normalize_usb_id(&info.vendor_id),
+            product_id: normalize_usb_id(&info.product_id),
+            serial: Some(info.serial.clone()).filter(|s| !s.is_empty()),
+            bus_path: None,
+            descriptors_hash: String::new(),
+            device_type: if DEVICE_TYPES.contains(&info.device_type.as_str()) {
+                info.device_type.clone()
+            } else {
+                "other".into()
+            },
+            comment: None,
+        }
+    }
+
+    /// True if this entry was made for `info` (same ids and serial).
+    pub fn is_for(&self, info: &DeviceInfo) -> bool {
+        normalize_usb_id(&self.vendor_id) == normalize_usb_id(&info.vendor_id)
+            && normalize_usb_id(&self.product_id) == normalize_usb_id(&info.product_id)
+            && self.serial.as_deref().unwrap_or_default() == info.serial
+    }
+
+    /// True if the allow rule generated for this entry admits `info`: same ids and, when
+    /// the entry pins one, the same serial.
+    pub fn matches(&self, info: &DeviceInfo) -> bool {
+        normalize_usb_id(&self.vendor_id) == normalize_usb_id(&info.vendor_id)
+            && normalize_usb_id(&self.product_id) == normalize_usb_id(&info.product_id)
+            && self.serial.as_deref().is_none_or(|s| s == info.serial)
+    }
+
+    fn validate(&self, path: &str, version: u32, issues: &mut Vec<ValidationIssue>) {
+        let legacy = version < 2;
+        for (field, id) in [
+            ("vendor_id", &self.vendor_id),
+            ("product_id", &self.product_id),
+        ] {
+            let ok = if legacy {
+                is_hex(&normalize_usb_id(id), 4)
+            } else {
+                is_hex(id, 4)
+            };
+            if !ok {
+                issues.push(ValidationIssue::new(
+                    format!("{}.{}", path, field),
+                    format!("{:?} is not four lowercase hex digits", id),
+                ));
+            }
+        }
+        if let Some(serial) = &self.serial {
+            if serial.is_empty() {
+                issues.push(ValidationIssue::new(
+                    format!("{}.serial", path),
+                    "empty serial; omit the field to match any serial",
+                ));
+            } else if serial.chars().any(char::is_control) {
+                issues.push(ValidationIssue::new(
+                    format!("{}.serial", path),
+                    "contains control characters",
+                ));
+            }
+        }
+        if let Some(hash) = self
+            .descriptors_hash
+            .strip_prefix("lfp2:")
+            .or_else(|| self.descriptors_hash.strip_prefix("sha256:"))
+        {
+            if !is_hex(hash, 64) {
+                issues.push(ValidationIssue::new(
+                    format!("{}.descriptors_hash", path),
+                    "expected \"lfp2:\" or \"sha256:\" and 64 lowercase hex digits",
+                ));
+            }
+        } else if !self.descriptors_hash.is_empty() {
+            issues.push(ValidationIssue::new(
+                format!("{}.descriptors_hash", path),
+                "expected \"lfp2:<hex>\", \"sha256:<hex>\" or an empty string",
+            ));
+        }
+        let empty_ok = legacy && self.device_type.is_empty();
+        if !empty_ok && !DEVICE_TYPES.contains(&self.device_type.as_str()) {
+            issues.push(ValidationIssue::new(
+                format!("{}.device_type", path),
+                format!(
+                    "unknown device type {:?}; expected one of {}",
+                    self.device_type,
+                    DEVICE_TYPES.join(", ")
+                ),
+            ));
+        }
+    }
+}
+
+impl Baseline {
+    /// Check field values beyond what parsing enforces. Returns every problem found.
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+        if self.created_by.trim().is_empty() {
+            issues.push(ValidationIssue::new("$.created_by", "must not be empty"));
+        }
+        if let (Some(nb), Some(na)) = (self.not_before, self.not_after) {
+            if na <= nb {
+                issues.push(ValidationIssue::new(
+                    "$.not_after",
+                    "must be later than not_before",
+                ));
+            }
+        }
+        for (i, h) in self.hosts.iter().enumerate() {
+            if h.trim().is_empty() {
+                issues.push(ValidationIssue::new(
+                    format!("$.hosts[{}]", i),
+                    "empty host pattern",
+                ));
+            }
+        }
+        if self.sequence.is_some_and(|s| s > 1 << 53) {
+            issues.push(ValidationIssue::new(
+                "$.sequence",
+                "larger than 2^53, which canonical JSON cannot represent",
+            ));
+        }
+        for (i, d) in self.devices.iter().enumerate() {
+            let path = format!("$.devices[{}]", i);
+            d.validate(&path, self.version, &mut issues);
+            if let Some(j) = self.devices[..i]
+                .iter()
+                .position(|o| same_device(o, d) && o.serial == d.serial)
+            {
+                issues.push(ValidationIssue::new(
+                    path,
+                    format!("duplicate of $.devices[{}]", j),
+                ));
+            }
+        }
+        issues
+    }
+}
+
+/// Parse and validate a baseline file. A document that does not parse yields one issue at
+/// the failing location; otherwise every value problem is reported.
+pub fn lint_baseline(data: &[u8]) -> Result<Baseline, Vec<ValidationIssue>> {
+    let baseline = Baseline::from_json(data).map_err(|e| vec![e.into()])?;
+    let issues = baseline.validate();
+    if issues.is_empty() {
+        Ok(baseline)
+    } else {
+        Err(issues)
+    }
+}
+
+/// A single field that differs between two matched device entries.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FieldChange {
+    pub field: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// A device entry present on both sides whose non-identifying fields changed.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ModifiedEntry {
+    pub old: DeviceEntry,
+    pub new: DeviceEntry,
+    pub changes: Vec<FieldChange>,
+}
+
+/// Difference between two baselines, including the resulting usbguard rule change.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BaselineDiff {
+    pub added: Vec<DeviceEntry>,
+    pub removed: Vec<DeviceEntry>,
+    pub modified: Vec<ModifiedEntry>,
+    pub rules_added: Vec<String>,
+    pub rules_removed: Vec<String>,
+}
+
+impl BaselineDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.modified.is_empty()
+            && self.rules_added.is_empty()
+            && self.rules_removed.is_empty()
+    }
+}
+
+/// Normalize a USB vendor or product id (`0x046D`, `46d`) to four lowercase hex digits.
+pub fn normalize_usb_id(id: &str) -> String {
+    let hex = id.trim();
+    let hex = hex
+        .strip_prefix("0x")
+        .or_else(|| hex.strip_prefix("0X"))
+        .unwrap_or(hex);
+    format!("{:0>4}", hex.to_ascii_lowercase())
+}
+
+fn same_device(a: &DeviceEntry, b: &DeviceEntry) -> bool {
+    normalize_usb_id(&a.vendor_id) == normalize_usb_id(&b.vendor_id)
+        && normalize_usb_id(&a.product_id) == normalize_usb_id(&b.product_id)
+}
+
+fn entry_changes(old: &DeviceEntry, new: &DeviceEntry) -> Vec<FieldChange> {
+    let fields: [(&str, Option<&str>, Option<&str>); 5] = [
+        ("serial", old.serial.as_deref(), new.serial.as_deref()),
+        ("bus_path", old.bus_path.as_deref(), new.bus_path.as_deref()),
+        (
+            "descriptors_hash",
+            Some(old.descriptors_hash.as_str()),
+            Some(new.descriptors_hash.as_str()),
+        ),
+        (
+            "device_type",
+            Some(old.device_type.as_str()),
+            Some(new.device_type.as_str()),
+        ),
+        ("comment", old.comment.as_deref(), new.comment.as_deref()),
+    ];
+    fields
+        .iter()
+        .filter(|(_, o, n)| o != n)
+        .map(|(field, o, n)| FieldChange {
+            field: field.to_string(),
+            old: o.map(str::to_string),
+            new: n.map(str::to_string),
+        })
+        .collect()
+}
+
+/// Lines present in `a` but not in `b`, respecting duplicates and keeping order.
+fn line_difference(a: &str, b: &str) -> Vec<String> {
+    let mut remaining: Vec<&str> = b.lines().collect();
+    let mut out = Vec::new();
+    for line in a.lines().filter(|l| !l.trim().is_empty()) {
+        if let Some(pos) = remaining.iter().position(|r| *r == line) {
+            remaining.remove(pos);
+        } else {
+            out.push(line.to_string());
+        }
+    }
+    out
+}
+
+/// Compare two baselines entry by entry.
+///
+/// Identical entries are ignored. Remaining entries are paired by VID:PID and serial
+/// first, then by VID:PID alone, so a changed serial shows up as a modification.
+/// `rules` renders a baseline into backend rules for the rule diff.
+pub fn diff_baselines<F>(old: &Baseline, new: &Baseline, rules: F) -> BaselineDiff
+where
+    F: Fn(&Baseline) -> String,
+{
+    let mut old_left: Vec<&DeviceEntry> = old.devices.iter().collect();
+    let mut new_left: Vec<&DeviceEntry> = Vec::new();
+    for n in &new.devices {
+        if let Some(pos) = old_left.iter().position(|o| *o == n) {
+            old_left.remove(pos);
+        } else {
+            new_left.push(n);
+        }
+    }
+
+    let mut modified = Vec::new();
+    for same_serial in [true, false] {
+        let mut i = 0;
+        while i < old_left.len() {
+            let o = old_left[i];
+            let found = new_left
+                .iter()
+                .position(|n| same_device(o, n) && (!same_serial || o.serial == n.serial));
+            match found {
+                Some(pos) => {
+                    let n = new_left.remove(pos);
+                    old_left.remove(i);
+                    modified.push(ModifiedEntry {
+                        old: o.clone(),
+                        new: n.clone(),
+                        changes: entry_changes(o, n),
+                    });
+                }
+                None => i += 1,
+            }
+        }
+    }
+
+    let old_rules = rules(old);
+    let new_rules = rules(new);
+    BaselineDiff {
+        added: new_left.into_iter().cloned().collect(),
+        removed: old_left.into_iter().cloned().collect(),
+        modified,
+        rules_added: line_difference(&new_rules, &old_rules),
+        rules_removed: line_difference(&old_rules, &new_rules),
+    }
+}
+
+fn describe_entry(d: &DeviceEntry) -> String {
+    let mut s = format!("{}:{}", d.vendor_id, d.product_id);
+    if let Some(serial) = &d.serial {
+        s.push_str(&format!(" serial={:?}", serial));
+    }
+    if !d.device_type.is_empty() {
+        s.push_str(&format!(" type={}", d.device_type));
+    }
+    if let Some(comment) = &d.comment {
+        s.push_str(&format!(" comment={:?}", comment));
+    }
+    s
+}
+
+impl std::fmt::Display for BaselineDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "No changes");
+        }
+        writeln!(f, "Devices:")?;
+        for d in &self.added {
+            writeln!(f, "+ {}", describe_entry(d))?;
+        }
+        for d in &self.removed {
+            writeln!(f, "- {}", describe_entry(d))?;
+        }
+        for m in &self.modified {
+            writeln!(f, "~ {}", describe_entry(&m.old))?;
+            for c in &m.changes {
+                writeln!(
+                    f,
+                    "    {}: {} -> {}",
+                    c.field,
+                    c.old
+                        .as_deref()
+                        .map_or("<none>".into(), |v| format!("{:?}", v)),
+                    c.new
+                        .as_deref()
+                        .map_or("<none>".into(), |v| format!("{:?}", v)),
+                )?;
+            }
+        }
+        writeln!(f, "Rules:")?;
+        for r in &self.rules_added {
+            writeln!(f, "+ {}", r)?;
+        }
+        for r in &self.rules_removed {
+            writeln!(f, "- {}", r)?;
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,115 @@
+//! JSON Canonicalization Scheme (RFC 8785), the byte form every lusby signature covers.
+//!
+//! - No whitespace; object members sorted by the UTF-16 code units of their names.
+//! - Strings are UTF-8. Only `"`, `\` and control characters below U+0020 are escaped:
+//!   `\b \t \n \f \r` in short form, the rest as `\u00xx` with lowercase hex.
+//! - Numbers are IEEE 754 doubles printed like ECMAScript `Number.prototype.toString`
+//!   (`1e+30`, `0.002`, `-0` becomes `0`). NaN and infinities are rejected, as are integers
+//!   beyond ±2^53 that a double cannot hold exactly.
+//!
+//! Any conforming JCS implementation produces the same bytes, so baselines can be signed from
+//! other languages; `tests/jcs` holds the shared test vectors.
+
+use serde_json::{Map, Number, Value};
+
+/// Largest integer a double represents exactly (2^53).
+const MAX_SAFE_INTEGER: u64 = 1 << 53;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum CanonicalError {
+    #[error("number {0} cannot be canonicalized (NaN, infinite or beyond 2^53)")]
+    Number(String),
+}
+
+/// Canonicalize a JSON value.
+pub fn to_jcs_string(value: &Value) -> Result<String, CanonicalError> {
+    let mut out = String::new();
+    write_value(value, &mut out)?;
+    Ok(out)
+}
+
+fn write_value(value: &Value, out: &mut String) -> Result<(), CanonicalError> {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) => out.push_str(&format_number(n)?),
+        Value::String(s) => write_string(s, out),
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(item, out)?;
+            }
+            out.push(']');
+        }
+        Value::Object(map) => write_object(map, out)?,
+    }
+    Ok(())
+}
+
+fn write_object(map: &Map<String, Value>, out: &mut String) -> Result<(), CanonicalError> {
+    let mut members: Vec<(&String, &Value)> = map.iter().collect();
+    members.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+    out.push('{');
+    for (i, (name, value)) in members.into_iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        write_string(name, out);
+        out.push(':');
+        write_value(value, out)?;
+    }
+    out.push('}');
+    Ok(())
+}
+
+fn write_string(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\u{8}' => out.push_str("\\b"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\u{c}' => out.push_str("\\f"),
+            '\r' => out.push_str("\\r"),
+            c if c < '\u{20}' => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn format_number(n: &Number) -> Result<String, CanonicalError> {
+    let unrepresentable = || CanonicalError::Number(n.to_string());
+    if let Some(u) = n.as_u64() {
+        return if u <= MAX_SAFE_INTEGER {
+            Ok(u.to_string())
+        } else {
+            Err(unrepresentable())
+        };
+    }
+    if let Some(i) = n.as_i64() {
+        return if i.unsigned_abs() <= MAX_SAFE_INTEGER {
+            Ok(i.to_string())
+        } else {
+            Err(unrepresentable())
+        };
+    }
+    match n.as_f64() {
+        Some(f) if f.is_finite() => Ok(format_double(f)),
+        _ => Err(unrepresentable()),
+    }
+}
+
+/// ECMAScript `Number.prototype.toString` for a finite double, including its
+/// round-half-even choice between equally short digit strings.
+pub fn format_double(f: f64) -> String {
+    if f == 0.0 {
+        return "0".into();
+    }
+    ryu_js::Buffer::new().format_finite(f).to_string()
+}
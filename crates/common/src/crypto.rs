@@ -1,33 +1,80 @@
 use base64::engine::general_purpose::STANDARD as B64;
 use base64::Engine;
 use canonical_json::to_string as canonical_to_string;
+use chrono::{DateTime, Utc};
 use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use sha2::{Digest, Sha256};
+use ssh_encoding::{Decode, Encode};
+use ssh_key::public::{Ed25519PublicKey, KeyData};
+use ssh_key::{HashAlg, SshSig};
 use thiserror::Error;
 
+use crate::canonical::to_jcs_string;
+
 #[derive(Debug, Error)]
 pub enum CryptoError {
     #[error("serialization error: {0}")]
     Ser(String),
     #[error("signature error: {0}")]
     Sig(String),
+    #[error("token error: {0}")]
+    Token(String),
+}
+
+/// Produces Ed25519 signatures without exposing where the secret lives: an in-memory key,
+/// a decrypted key file (`keyfile::KeyFile::decrypt`) or a PKCS#11 token.
+pub trait KeySigner {
+    fn public_key(&self) -> Result<VerifyingKey, CryptoError>;
+    fn sign_message(&self, msg: &[u8]) -> Result<Signature, CryptoError>;
+}
+
+impl KeySigner for SigningKey {
+    fn public_key(&self) -> Result<VerifyingKey, CryptoError> {
+        Ok(self.verifying_key())
+    }
+
+    fn sign_message(&self, msg: &[u8]) -> Result<Signature, CryptoError> {
+        self.try_sign(msg)
+            .map_err(|e| CryptoError::Sig(e.to_string()))
+    }
 }
 
+/// The bytes lusby signs: `value` in RFC 8785 (JCS) canonical form, see `canonical`.
 pub fn canonical_json_vec<T: Serialize>(value: &T) -> Result<Vec<u8>, CryptoError> {
+    let v: Value = serde_json::to_value(value).map_err(|e| CryptoError::Ser(e.to_string()))?;
+    let s = to_jcs_string(&v).map_err(|e| CryptoError::Ser(e.to_string()))?;
+    Ok(s.into_bytes())
+}
+
+/// The canonical form used before JCS. It differs only for non-ASCII and DEL characters
+/// (escaped as `\uXXXX`), floats and some non-ASCII key orders, and is accepted when
+/// verifying so signatures made by older releases stay valid.
+pub fn legacy_canonical_json_vec<T: Serialize>(value: &T) -> Result<Vec<u8>, CryptoError> {
     let v: Value = serde_json::to_value(value).map_err(|e| CryptoError::Ser(e.to_string()))?;
     let s = canonical_to_string(&v).map_err(|e| CryptoError::Ser(e.to_string()))?;
     Ok(s.into_bytes())
 }
 
-pub fn sign_canonical<T: Serialize>(
-    signing_key: &SigningKey,
+/// Messages a signature over `value` may cover: the JCS form, then the legacy form if it
+/// differs.
+fn signed_messages<T: Serialize>(value: &T) -> Result<Vec<Vec<u8>>, CryptoError> {
+    let jcs = canonical_json_vec(value)?;
+    let legacy = legacy_canonical_json_vec(value)?;
+    Ok(if legacy == jcs {
+        vec![jcs]
+    } else {
+        vec![jcs, legacy]
+    })
+}
+
+pub fn sign_canonical<T: Serialize, S: KeySigner + ?Sized>(
+    signer: &S,
     value: &T,
 ) -> Result<String, CryptoError> {
     let msg = canonical_json_vec(value)?;
-    let sig: Signature = signing_key
-        .try_sign(&msg)
-        .map_err(|e| CryptoError::Sig(e.to_string()))?;
+    let sig = signer.sign_message(&msg)?;
     Ok(B64.encode(sig.to_bytes()))
 }
 
@@ -36,7 +83,6 @@ pub fn verify_canonical<T: Serialize>(
     value: &T,
     b64sig: &str,
 ) -> Result<bool, CryptoError> {
-    let msg = canonical_json_vec(value)?;
     let sig_bytes = B64
         .decode(b64sig)
         .map_err(|e| CryptoError::Sig(e.to_string()))?;
@@ -44,5 +90,164 @@ pub fn verify_canonical<T: Serialize>(
         .try_into()
         .map_err(|_| CryptoError::Sig("sig size".into()))?;
     let sig = Signature::from_bytes(&arr);
-    Ok(verifying_key.verify_strict(&msg, &sig).is_ok())
+    Ok(signed_messages(value)?
+        .iter()
+        .any(|msg| verifying_key.verify_strict(msg, &sig).is_ok()))
+}
+
+/// Stable identifier for a trusted key: `sha256:<hex>` over the raw 32-byte public key.
+pub fn key_id(verifying_key: &VerifyingKey) -> String {
+    let digest = Sha256::digest(verifying_key.as_bytes());
+    format!("sha256:{}", hex::encode(digest))
+}
+
+/// Namespace for lusby `sshsig` signatures, as passed to `ssh-keygen -Y sign -n`.
+pub const SSHSIG_NAMESPACE: &str = "lusby";
+
+/// Encoding of a co-signature's `signature` field.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SignatureFormat {
+    /// Raw Ed25519 signature over the canonical payload.
+    #[default]
+    Ed25519,
+    /// OpenSSH `sshsig` blob (SHA-512, namespace [`SSHSIG_NAMESPACE`]) over the canonical payload.
+    Sshsig,
+}
+
+impl SignatureFormat {
+    fn is_default(&self) -> bool {
+        *self == SignatureFormat::Ed25519
+    }
+}
+
+/// One co-signature over a document's content, the signer's key id and a timestamp.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CoSignature {
+    pub key_id: String,
+    pub signed_at: DateTime<Utc>,
+    pub signature: String, // base64(ed25519) or base64(sshsig)
+    #[serde(default, skip_serializing_if = "SignatureFormat::is_default")]
+    pub format: SignatureFormat,
+}
+
+/// Sign `msg` as an OpenSSH `sshsig` and return the base64 of the binary blob.
+pub fn sshsig_sign<S: KeySigner + ?Sized>(signer: &S, msg: &[u8]) -> Result<String, CryptoError> {
+    let signed_data = SshSig::signed_data(SSHSIG_NAMESPACE, HashAlg::Sha512, msg)
+        .map_err(|e| CryptoError::Sig(e.to_string()))?;
+    let sig = signer.sign_message(&signed_data)?;
+    let public = KeyData::Ed25519(Ed25519PublicKey(signer.public_key()?.to_bytes()));
+    let sshsig = SshSig::new(
+        public,
+        SSHSIG_NAMESPACE,
+        HashAlg::Sha512,
+        ssh_key::Signature::new(ssh_key::Algorithm::Ed25519, sig.to_bytes().to_vec())
+            .map_err(|e| CryptoError::Sig(e.to_string()))?,
+    )
+    .map_err(|e| CryptoError::Sig(e.to_string()))?;
+    let mut blob = Vec::new();
+    sshsig
+        .encode(&mut blob)
+        .map_err(|e| CryptoError::Sig(e.to_string()))?;
+    Ok(B64.encode(blob))
+}
+
+/// Verify a base64 `sshsig` blob over `msg`. The blob must carry `verifying_key` and the
+/// lusby namespace.
+pub fn sshsig_verify(
+    verifying_key: &VerifyingKey,
+    msg: &[u8],
+    b64sig: &str,
+) -> Result<bool, CryptoError> {
+    let blob = B64
+        .decode(b64sig)
+        .map_err(|e| CryptoError::Sig(e.to_string()))?;
+    let sshsig =
+        SshSig::decode(&mut blob.as_slice()).map_err(|e| CryptoError::Sig(e.to_string()))?;
+    let public =
+        ssh_key::PublicKey::from(KeyData::Ed25519(Ed25519PublicKey(verifying_key.to_bytes())));
+    // Checks the embedded key and namespace before the signature itself
+    Ok(public.verify(SSHSIG_NAMESPACE, msg, &sshsig).is_ok())
+}
+
+/// The signed message: `{ <kind>: content, "key_id": .., "signed_at": .. }` in canonical form.
+fn cosigned_payload<T: Serialize>(
+    kind: &str,
+    content: &T,
+    key_id: &str,
+    signed_at: &DateTime<Utc>,
+) -> Result<Value, CryptoError> {
+    let mut map = serde_json::Map::new();
+    map.insert(
+        kind.to_string(),
+        serde_json::to_value(content).map_err(|e| CryptoError::Ser(e.to_string()))?,
+    );
+    map.insert("key_id".into(), Value::String(key_id.to_string()));
+    map.insert(
+        "signed_at".into(),
+        serde_json::to_value(signed_at).map_err(|e| CryptoError::Ser(e.to_string()))?,
+    );
+    Ok(Value::Object(map))
+}
+
+/// The exact bytes a co-signature by `key_id` at `signed_at` covers.
+pub fn cosigned_bytes<T: Serialize>(
+    kind: &str,
+    content: &T,
+    key_id: &str,
+    signed_at: &DateTime<Utc>,
+) -> Result<Vec<u8>, CryptoError> {
+    canonical_json_vec(&cosigned_payload(kind, content, key_id, signed_at)?)
+}
+
+pub fn cosign<T: Serialize, S: KeySigner + ?Sized>(
+    signer: &S,
+    kind: &str,
+    content: &T,
+) -> Result<CoSignature, CryptoError> {
+    cosign_as(signer, kind, content, SignatureFormat::Ed25519)
+}
+
+pub fn cosign_as<T: Serialize, S: KeySigner + ?Sized>(
+    signer: &S,
+    kind: &str,
+    content: &T,
+    format: SignatureFormat,
+) -> Result<CoSignature, CryptoError> {
+    let id = key_id(&signer.public_key()?);
+    let signed_at = Utc::now();
+    let payload = cosigned_payload(kind, content, &id, &signed_at)?;
+    let signature = match format {
+        SignatureFormat::Ed25519 => sign_canonical(signer, &payload)?,
+        SignatureFormat::Sshsig => sshsig_sign(signer, &canonical_json_vec(&payload)?)?,
+    };
+    Ok(CoSignature {
+        signature,
+        key_id: id,
+        signed_at,
+        format,
+    })
+}
+
+pub fn verify_cosign<T: Serialize>(
+    verifying_key: &VerifyingKey,
+    kind: &str,
+    content: &T,
+    sig: &CoSignature,
+) -> Result<bool, CryptoError> {
+    if sig.key_id != key_id(verifying_key) {
+        return Ok(false);
+    }
+    let payload = cosigned_payload(kind, content, &sig.key_id, &sig.signed_at)?;
+    match sig.format {
+        SignatureFormat::Ed25519 => verify_canonical(verifying_key, &payload, &sig.signature),
+        SignatureFormat::Sshsig => {
+            for msg in signed_messages(&payload)? {
+                if sshsig_verify(verifying_key, &msg, &sig.signature)? {
+                    return Ok(true);
+                }
+            }
+            Ok(false)
+        }
+    }
 }
@@ -0,0 +1,658 @@
+//! USB descriptor parsing and `lsusb -v` style rendering.
+//!
+//! `parse_descriptors` reads the blob sysfs exposes as `descriptors`: the device descriptor
+//! followed by every configuration with its interface, class-specific and endpoint
+//! descriptors. HID report descriptors and string descriptors come from elsewhere (sysfs
+//! `report_descriptor`, GET_DESCRIPTOR) and have their own parsers. Input is untrusted (a
+//! device writes it), so every length is checked and nothing here panics on malformed data.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+
+use thiserror::Error;
+
+const DEVICE: u8 = 0x01;
+const CONFIGURATION: u8 = 0x02;
+const STRING: u8 = 0x03;
+const INTERFACE: u8 = 0x04;
+const ENDPOINT: u8 = 0x05;
+const HID: u8 = 0x21;
+const HID_REPORT: u8 = 0x22;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum DescriptorError {
+    #[error("descriptor at offset {offset} is truncated")]
+    Truncated { offset: usize },
+    #[error("descriptor at offset {offset}: {reason}")]
+    Malformed { offset: usize, reason: String },
+}
+
+fn malformed(offset: usize, reason: impl Into<String>) -> DescriptorError {
+    DescriptorError::Malformed {
+        offset,
+        reason: reason.into(),
+    }
+}
+
+fn le16(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceDescriptor {
+    /// BCD, e.g. 0x0200 for USB 2.0.
+    pub usb_version: u16,
+    pub class: u8,
+    pub subclass: u8,
+    pub protocol: u8,
+    pub max_packet_size0: u8,
+    pub vendor_id: u16,
+    pub product_id: u16,
+    pub device_version: u16,
+    pub manufacturer_index: u8,
+    pub product_index: u8,
+    pub serial_index: u8,
+    pub num_configurations: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigDescriptor {
+    pub value: u8,
+    pub string_index: u8,
+    pub attributes: u8,
+    /// bMaxPower in mA (the descriptor counts 2 mA units).
+    pub max_power_ma: u16,
+    pub interfaces: Vec<InterfaceDescriptor>,
+    /// Descriptors between the configuration and its first interface, e.g. association.
+    pub extra: Vec<RawDescriptor>,
+}
+
+impl ConfigDescriptor {
+    pub fn self_powered(&self) -> bool {
+        self.attributes & 0x40 != 0
+    }
+
+    pub fn remote_wakeup(&self) -> bool {
+        self.attributes & 0x20 != 0
+    }
+}
+
+/// One alternate setting of an interface.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InterfaceDescriptor {
+    pub number: u8,
+    pub alternate: u8,
+    pub class: u8,
+    pub subclass: u8,
+    pub protocol: u8,
+    pub string_index: u8,
+    pub hid: Option<HidDescriptor>,
+    pub endpoints: Vec<EndpointDescriptor>,
+    /// Class-specific descriptors other than HID, e.g. CDC functional descriptors.
+    pub extra: Vec<RawDescriptor>,
+}
+
+impl InterfaceDescriptor {
+    pub fn class_name(&self) -> &'static str {
+        class_name(self.class, self.subclass, self.protocol)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EndpointDescriptor {
+    pub address: u8,
+    pub attributes: u8,
+    pub max_packet_size: u16,
+    pub interval: u8,
+}
+
+impl EndpointDescriptor {
+    pub fn number(&self) -> u8 {
+        self.address & 0x0f
+    }
+
+    pub fn is_in(&self) -> bool {
+        self.address & 0x80 != 0
+    }
+
+    pub fn transfer_type(&self) -> &'static str {
+        match self.attributes & 0x03 {
+            0 => "Control",
+            1 => "Isochronous",
+            2 => "Bulk",
+            _ => "Interrupt",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HidDescriptor {
+    pub hid_version: u16,
+    pub country_code: u8,
+    /// Length of the report descriptor the device announces.
+    pub report_length: u16,
+    /// The parsed report descriptor, when one was read separately (see `parse_hid_report`).
+    pub report: Vec<HidItem>,
+}
+
+/// A descriptor kept as bytes (everything after bLength and bDescriptorType).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawDescriptor {
+    pub descriptor_type: u8,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Descriptors {
+    pub device: DeviceDescriptor,
+    pub configurations: Vec<ConfigDescriptor>,
+}
+
+impl Descriptors {
+    pub fn interfaces(&self) -> impl Iterator<Item = &InterfaceDescriptor> {
+        self.configurations.iter().flat_map(|c| c.interfaces.iter())
+    }
+
+    pub fn interfaces_mut(&mut self) -> impl Iterator<Item = &mut InterfaceDescriptor> {
+        self.configurations
+            .iter_mut()
+            .flat_map(|c| c.interfaces.iter_mut())
+    }
+}
+
+/// Parse a device descriptor followed by its configurations, as in sysfs `descriptors`.
+pub fn parse_descriptors(bytes: &[u8]) -> Result<Descriptors, DescriptorError> {
+    let mut chunks = split(bytes);
+    let (offset, first) = chunks
+        .next()
+        .ok_or(DescriptorError::Truncated { offset: 0 })??;
+    if first[1] != DEVICE {
+        return Err(malformed(offset, "expected a device descriptor"));
+    }
+    if first.len() < 18 {
+        return Err(malformed(offset, "device descriptor shorter than 18 bytes"));
+    }
+    let device = DeviceDescriptor {
+        usb_version: le16(first, 2),
+        class: first[4],
+        subclass: first[5],
+        protocol: first[6],
+        max_packet_size0: first[7],
+        vendor_id: le16(first, 8),
+        product_id: le16(first, 10),
+        device_version: le16(first, 12),
+        manufacturer_index: first[14],
+        product_index: first[15],
+        serial_index: first[16],
+        num_configurations: first[17],
+    };
+
+    let mut configurations: Vec<ConfigDescriptor> = Vec::new();
+    for chunk in chunks {
+        let (offset, d) = chunk?;
+        let min_len = match d[1] {
+            CONFIGURATION | INTERFACE => 9,
+            ENDPOINT => 7,
+            HID => 6,
+            _ => 2,
+        };
+        if d.len() < min_len {
+            return Err(malformed(
+                offset,
+                format!("descriptor type {:#04x} shorter than {}", d[1], min_len),
+            ));
+        }
+        if d[1] == CONFIGURATION {
+            configurations.push(ConfigDescriptor {
+                value: d[5],
+                string_index: d[6],
+                attributes: d[7],
+                max_power_ma: u16::from(d[8]) * 2,
+                interfaces: Vec::new(),
+                extra: Vec::new(),
+            });
+            continue;
+        }
+        let config = configurations
+            .last_mut()
+            .ok_or_else(|| malformed(offset, "descriptor outside a configuration"))?;
+        match d[1] {
+            DEVICE => return Err(malformed(offset, "second device descriptor")),
+            INTERFACE => config.interfaces.push(InterfaceDescriptor {
+                number: d[2],
+                alternate: d[3],
+                class: d[5],
+                subclass: d[6],
+                protocol: d[7],
+                string_index: d[8],
+                hid: None,
+                endpoints: Vec::new(),
+                extra: Vec::new(),
+            }),
+            ENDPOINT => config
+                .interfaces
+                .last_mut()
+                .ok_or_else(|| malformed(offset, "endpoint outside an interface"))?
+                .endpoints
+                .push(EndpointDescriptor {
+                    address: d[2],
+                    attributes: d[3],
+                    max_packet_size: le16(d, 4),
+                    interval: d[6],
+                }),
+            descriptor_type => {
+                let raw = RawDescriptor {
+                    descriptor_type,
+                    data: d[2..].to_vec(),
+                };
+                match config.interfaces.last_mut() {
+                    Some(interface) if descriptor_type == HID && interface.class == 0x03 => {
+                        interface.hid = Some(parse_hid_descriptor(offset, d)?)
+                    }
+                    Some(interface) => interface.extra.push(raw),
+                    None => config.extra.push(raw),
+                }
+            }
+        }
+    }
+    Ok(Descriptors {
+        device,
+        configurations,
+    })
+}
+
+/// The descriptors in `bytes` with their offsets; each slice starts with bLength.
+fn split(bytes: &[u8]) -> impl Iterator<Item = Result<(usize, &[u8]), DescriptorError>> {
+    let mut offset = 0;
+    std::iter::from_fn(move || {
+        let rest = bytes.get(offset..).filter(|r| !r.is_empty())?;
+        let at = offset;
+        let len = usize::from(rest[0]);
+        // Stop after an error, the following lengths are meaningless
+        offset = bytes.len();
+        if len < 2 {
+            return Some(Err(malformed(at, format!("bLength {} below 2", len))));
+        }
+        if len > rest.len() {
+            return Some(Err(DescriptorError::Truncated { offset: at }));
+        }
+        offset = at + len;
+        Some(Ok((at, &rest[..len])))
+    })
+}
+
+fn parse_hid_descriptor(offset: usize, d: &[u8]) -> Result<HidDescriptor, DescriptorError> {
+    // bcdHID, bCountryCode, bNumDescriptors, then (bDescriptorType, wDescriptorLength) pairs
+    let count = usize::from(d[5]);
+    if d.len() < 6 + 3 * count {
+        return Err(malformed(
+            offset,
+            "HID descriptor lists more class descriptors than it holds",
+        ));
+    }
+    let report_length = d[6..6 + 3 * count]
+        .chunks_exact(3)
+        .find(|entry| entry[0] == HID_REPORT)
+        .map_or(0, |entry| le16(entry, 1));
+    Ok(HidDescriptor {
+        hid_version: le16(d, 2),
+        country_code: d[4],
+        report_length,
+        report: Vec::new(),
+    })
+}
+
+/// The text of a string descriptor (UTF-16LE). Unpaired surrogates become U+FFFD.
+pub fn parse_string_descriptor(bytes: &[u8]) -> Result<String, DescriptorError> {
+    let d = match split(bytes).next() {
+        Some(d) => d?.1,
+        None => return Err(DescriptorError::Truncated { offset: 0 }),
+    };
+    if d[1] != STRING {
+        return Err(malformed(0, "expected a string descriptor"));
+    }
+    let units: Vec<u16> = d[2..]
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .collect();
+    Ok(String::from_utf16_lossy(&units))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HidItemType {
+    Main,
+    Global,
+    Local,
+    /// Reserved short items and long items.
+    Reserved,
+}
+
+/// One item of a HID report descriptor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HidItem {
+    pub item_type: HidItemType,
+    pub tag: u8,
+    pub data: Vec<u8>,
+}
+
+impl HidItem {
+    /// The data as an unsigned little-endian value (at most 4 bytes for short items).
+    pub fn value(&self) -> u32 {
+        self.data
+            .iter()
+            .take(4)
+            .rev()
+            .fold(0, |acc, b| (acc << 8) | u32::from(*b))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match (self.item_type, self.tag) {
+            (HidItemType::Main, 0x8) => "Input",
+            (HidItemType::Main, 0x9) => "Output",
+            (HidItemType::Main, 0xa) => "Collection",
+            (HidItemType::Main, 0xb) => "Feature",
+            (HidItemType::Main, 0xc) => "End Collection",
+            (HidItemType::Global, 0x0) => "Usage Page",
+            (HidItemType::Global, 0x1) => "Logical Minimum",
+            (HidItemType::Global, 0x2) => "Logical Maximum",
+            (HidItemType::Global, 0x3) => "Physical Minimum",
+            (HidItemType::Global, 0x4) => "Physical Maximum",
+            (HidItemType::Global, 0x5) => "Unit Exponent",
+            (HidItemType::Global, 0x6) => "Unit",
+            (HidItemType::Global, 0x7) => "Report Size",
+            (HidItemType::Global, 0x8) => "Report ID",
+            (HidItemType::Global, 0x9) => "Report Count",
+            (HidItemType::Global, 0xa) => "Push",
+            (HidItemType::Global, 0xb) => "Pop",
+            (HidItemType::Local, 0x0) => "Usage",
+            (HidItemType::Local, 0x1) => "Usage Minimum",
+            (HidItemType::Local, 0x2) => "Usage Maximum",
+            (HidItemType::Local, 0x3) => "Designator Index",
+            (HidItemType::Local, 0x4) => "Designator Minimum",
+            (HidItemType::Local, 0x5) => "Designator Maximum",
+            (HidItemType::Local, 0x7) => "String Index",
+            (HidItemType::Local, 0x8) => "String Minimum",
+            (HidItemType::Local, 0x9) => "String Maximum",
+            (HidItemType::Local, 0xa) => "Delimiter",
+            _ => "Unknown",
+        }
+    }
+}
+
+/// Parse a HID report descriptor (sysfs `report_descriptor`) into its items.
+pub fn parse_hid_report(bytes: &[u8]) -> Result<Vec<HidItem>, DescriptorError> {
+    let mut items = Vec::new();
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let prefix = bytes[offset];
+        let (item, len) = if prefix == 0xfe {
+            // Long item: bDataSize, bLongItemTag, data
+            let header = bytes
+                .get(offset + 1..offset + 3)
+                .ok_or(DescriptorError::Truncated { offset })?;
+            let size = usize::from(header[0]);
+            let data = bytes
+                .get(offset + 3..offset + 3 + size)
+                .ok_or(DescriptorError::Truncated { offset })?;
+            let item = HidItem {
+                item_type: HidItemType::Reserved,
+                tag: header[1],
+                data: data.to_vec(),
+            };
+            (item, 3 + size)
+        } else {
+            let size = match prefix & 0x03 {
+                3 => 4,
+                n => usize::from(n),
+            };
+            let data = bytes
+                .get(offset + 1..offset + 1 + size)
+                .ok_or(DescriptorError::Truncated { offset })?;
+            let item_type = match (prefix >> 2) & 0x03 {
+                0 => HidItemType::Main,
+                1 => HidItemType::Global,
+                2 => HidItemType::Local,
+                _ => HidItemType::Reserved,
+            };
+            let item = HidItem {
+                item_type,
+                tag: prefix >> 4,
+                data: data.to_vec(),
+            };
+            (item, 1 + size)
+        };
+        items.push(item);
+        offset += len;
+    }
+    Ok(items)
+}
+
+/// What a class/subclass/protocol triple is, in the terms a user would pick a device by.
+pub fn class_name(class: u8, subclass: u8, protocol: u8) -> &'static str {
+    match (class, subclass, protocol) {
+        (0x00, _, _) => "Defined at Interface level",
+        (0x01, _, _) => "Audio",
+        (0x02, 0x02, _) => "CDC ACM (serial)",
+        (0x02, 0x06, _) => "CDC Ethernet",
+        (0x02, 0x0a, _) => "CDC Mobile Direct Line",
+        (0x02, 0x0d, _) => "CDC NCM",
+        (0x02, 0x0e, _) => "CDC MBIM",
+        (0x02, _, _) => "Communications",
+        (0x03, 0x01, 0x01) => "Keyboard",
+        (0x03, 0x01, 0x02) => "Mouse",
+        (0x03, _, _) => "HID",
+        (0x05, _, _) => "Physical",
+        (0x06, _, _) => "Still Image",
+        (0x07, _, _) => "Printer",
+        (0x08, _, _) => "Mass Storage",
+        (0x09, _, _) => "Hub",
+        (0x0a, _, _) => "CDC Data",
+        (0x0b, _, _) => "Smart Card",
+        (0x0d, _, _) => "Content Security",
+        (0x0e, _, _) => "Video",
+        (0x0f, _, _) => "Personal Healthcare",
+        (0x10, _, _) => "Audio/Video",
+        (0x11, _, _) => "Billboard",
+        (0x12, _, _) => "USB Type-C Bridge",
+        (0xdc, _, _) => "Diagnostic",
+        (0xe0, 0x01, 0x01) => "Bluetooth",
+        (0xe0, 0x01, 0x03) => "RNDIS Ethernet",
+        (0xe0, _, _) => "Wireless Controller",
+        (0xef, _, _) => "Miscellaneous",
+        (0xfe, 0x01, _) => "Firmware Update (DFU)",
+        (0xfe, _, _) => "Application Specific",
+        (0xff, _, _) => "Vendor Specific",
+        _ => "Unknown",
+    }
+}
+
+/// `class_name` for an interface as reported in `DeviceInfo::interfaces` (`cc:ss:pp` hex).
+pub fn interface_class_name(triple: &str) -> Option<&'static str> {
+    let mut parts = triple.split(':').map(|p| u8::from_str_radix(p, 16));
+    match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(Ok(class)), Some(Ok(subclass)), Some(Ok(protocol)), None) => {
+            Some(class_name(class, subclass, protocol))
+        }
+        _ => None,
+    }
+}
+
+fn bcd(version: u16) -> String {
+    format!("{:x}.{:02x}", version >> 8, version & 0xff)
+}
+
+/// Render `descriptors` as an indented tree in the layout of `lsusb -v`. `strings` maps string
+/// indexes to their text where known (sysfs only has manufacturer, product and serial).
+pub fn render_tree(descriptors: &Descriptors, strings: &BTreeMap<u8, String>) -> String {
+    let mut out = String::new();
+    let string = |index: u8| strings.get(&index).map_or("", String::as_str);
+    let d = &descriptors.device;
+    let mut line = |indent: usize, field: &str, value: String, note: &str| {
+        let text = format!("{:indent$}{:<20}{:>7} {}", "", field, value, note);
+        // Writing to a String cannot fail
+        let _ = writeln!(out, "{}", text.trim_end());
+    };
+    line(0, "Device Descriptor:", String::new(), "");
+    line(2, "bcdUSB", bcd(d.usb_version), "");
+    let device_class = class_name(d.class, d.subclass, d.protocol);
+    line(2, "bDeviceClass", d.class.to_string(), device_class);
+    line(2, "bDeviceSubClass", d.subclass.to_string(), "");
+    line(2, "bDeviceProtocol", d.protocol.to_string(), "");
+    line(2, "bMaxPacketSize0", d.max_packet_size0.to_string(), "");
+    line(2, "idVendor", format!("{:#06x}", d.vendor_id), "");
+    line(2, "idProduct", format!("{:#06x}", d.product_id), "");
+    line(2, "bcdDevice", bcd(d.device_version), "");
+    line(
+        2,
+        "iManufacturer",
+        d.manufacturer_index.to_string(),
+        string(d.manufacturer_index),
+    );
+    line(
+        2,
+        "iProduct",
+        d.product_index.to_string(),
+        string(d.product_index),
+    );
+    line(
+        2,
+        "iSerial",
+        d.serial_index.to_string(),
+        string(d.serial_index),
+    );
+    line(
+        2,
+        "bNumConfigurations",
+        d.num_configurations.to_string(),
+        "",
+    );
+    for config in &descriptors.configurations {
+        line(2, "Configuration Descriptor:", String::new(), "");
+        line(
+            4,
+            "bNumInterfaces",
+            count_interfaces(config).to_string(),
+            "",
+        );
+        line(4, "bConfigurationValue", config.value.to_string(), "");
+        line(
+            4,
+            "iConfiguration",
+            config.string_index.to_string(),
+            string(config.string_index),
+        );
+        line(4, "bmAttributes", format!("{:#04x}", config.attributes), "");
+        if config.self_powered() {
+            line(6, "Self Powered", String::new(), "");
+        }
+        if config.remote_wakeup() {
+            line(6, "Remote Wakeup", String::new(), "");
+        }
+        line(4, "MaxPower", format!("{}mA", config.max_power_ma), "");
+        for extra in &config.extra {
+            line(
+                4,
+                "Descriptor",
+                format!("{:#04x}", extra.descriptor_type),
+                &hex_bytes(&extra.data),
+            );
+        }
+        for interface in &config.interfaces {
+            line(4, "Interface Descriptor:", String::new(), "");
+            line(6, "bInterfaceNumber", interface.number.to_string(), "");
+            line(6, "bAlternateSetting", interface.alternate.to_string(), "");
+            line(
+                6,
+                "bNumEndpoints",
+                interface.endpoints.len().to_string(),
+                "",
+            );
+            line(
+                6,
+                "bInterfaceClass",
+                interface.class.to_string(),
+                interface.class_name(),
+            );
+            line(6, "bInterfaceSubClass", interface.subclass.to_string(), "");
+            line(6, "bInterfaceProtocol", interface.protocol.to_string(), "");
+            line(
+                6,
+                "iInterface",
+                interface.string_index.to_string(),
+                string(interface.string_index),
+            );
+            if let Some(hid) = &interface.hid {
+                line(6, "HID Device Descriptor:", String::new(), "");
+                line(8, "bcdHID", bcd(hid.hid_version), "");
+                line(8, "bCountryCode", hid.country_code.to_string(), "");
+                line(8, "wDescriptorLength", hid.report_length.to_string(), "");
+                if !hid.report.is_empty() {
+                    line(8, "Report Descriptor:", String::new(), "");
+                    out_hid_report(&mut line, &hid.report);
+                }
+            }
+            for extra in &interface.extra {
+                line(
+                    6,
+                    "Descriptor",
+                    format!("{:#04x}", extra.descriptor_type),
+                    &hex_bytes(&extra.data),
+                );
+            }
+            for endpoint in &interface.endpoints {
+                line(6, "Endpoint Descriptor:", String::new(), "");
+                let direction = if endpoint.is_in() { "IN" } else { "OUT" };
+                let address = format!("EP {} {}", endpoint.number(), direction);
+                line(
+                    8,
+                    "bEndpointAddress",
+                    format!("{:#04x}", endpoint.address),
+                    &address,
+                );
+                line(8, "Transfer Type", String::new(), endpoint.transfer_type());
+                line(
+                    8,
+                    "wMaxPacketSize",
+                    format!("{:#06x}", endpoint.max_packet_size),
+                    "",
+                );
+                line(8, "bInterval", endpoint.interval.to_string(), "");
+            }
+        }
+    }
+    out
+}
+
+/// The distinct interface numbers; alternate settings repeat them.
+fn count_interfaces(config: &ConfigDescriptor) -> usize {
+    let mut numbers: Vec<u8> = config.interfaces.iter().map(|i| i.number).collect();
+    numbers.sort_unstable();
+    numbers.dedup();
+    numbers.len()
+}
+
+fn out_hid_report(line: &mut impl FnMut(usize, &str, String, &str), items: &[HidItem]) {
+    let mut depth = 0usize;
+    for item in items {
+        if item.item_type == HidItemType::Main && item.tag == 0xc {
+            depth = depth.saturating_sub(1);
+        }
+        // Nesting is capped so a hostile descriptor cannot blow up the output width
+        line(
+            10 + 2 * depth.min(16),
+            item.name(),
+            format!("{:#x}", item.value()),
+            "",
+        );
+        if item.item_type == HidItemType::Main && item.tag == 0xa {
+            depth += 1;
+        }
+    }
+}
+
+fn hex_bytes(data: &[u8]) -> String {
+    data.iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
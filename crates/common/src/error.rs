@@ -0,0 +1,66 @@
+//! Errors the daemon replies with over D-Bus, named `org.lusby.Error.<Variant>`.
+//!
+//! Clients get the same type back from a failed call (`Error::from(zbus::Error)` matches the
+//! error name), so the CLI and tray can tell a polkit refusal from a bad signature without
+//! reading the audit log.
+
+use zbus::DBusError;
+
+#[derive(Debug, DBusError)]
+#[zbus(prefix = "org.lusby.Error", impl_display = false)]
+pub enum Error {
+    /// Transport failure, or an error name this version does not know.
+    #[zbus(error)]
+    ZBus(zbus::Error),
+    /// polkit refused the caller.
+    NotAuthorized(String),
+    /// Malformed arguments: ids, TTLs, paths, keys or baseline documents.
+    InvalidInput(String),
+    /// No trusted key produced a valid signature.
+    SignatureInvalid(String),
+    /// usbguard, the key store or the state directory failed.
+    BackendFailed(String),
+    /// The named device, key or signer does not exist.
+    NotFound(String),
+    /// Well-formed and signed, but refused by policy (expiry, scope, replay, thresholds).
+    PolicyDenied(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// Exit status for command line tools; documented in `lusbyctl --help`.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::InvalidInput(_) => 3,
+            Error::NotFound(_) => 4,
+            Error::SignatureInvalid(_) => 5,
+            Error::PolicyDenied(_) => 6,
+            Error::NotAuthorized(_) => 7,
+            Error::BackendFailed(_) => 8,
+            Error::ZBus(_) => 9,
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::ZBus(zbus::Error::MethodError(name, desc, _)) => {
+                write!(
+                    f,
+                    "{}: {}",
+                    name,
+                    desc.as_deref().unwrap_or("no description")
+                )
+            }
+            Error::ZBus(e) => write!(f, "cannot reach the lusby daemon: {}", e),
+            Error::NotAuthorized(m) => write!(f, "not authorized: {}", m),
+            Error::InvalidInput(m) => write!(f, "invalid input: {}", m),
+            Error::SignatureInvalid(m) => write!(f, "signature invalid: {}", m),
+            Error::BackendFailed(m) => write!(f, "backend failed: {}", m),
+            Error::NotFound(m) => write!(f, "not found: {}", m),
+            Error::PolicyDenied(m) => write!(f, "denied by policy: {}", m),
+        }
+    }
+}
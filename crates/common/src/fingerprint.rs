@@ -1,5 +1,28 @@
+//! Device fingerprints.
+//!
+//! The current scheme is `lfp2:<sha256 hex>`: a domain tag followed by every field as a
+//! presence byte, a big-endian `u64` length and the bytes, so no two inputs share an
+//! encoding. The previous `sha256:` scheme joined fields with `|` and is ambiguous (a serial
+//! `a|b` looks like serial `a` and manufacturer `b`); it is still computed and accepted by
+//! `verify_fingerprint` so values recorded before the switch keep matching. Released lusby
+//! hashed udev's view of a device for it: `0x` ids, udev's sanitized strings and no
+//! descriptors, which `FingerprintSource::legacy_fingerprint` reproduces from sysfs.
+
+use std::fs;
+use std::path::Path;
+
 use sha2::{Digest, Sha256};
 
+use crate::baseline::normalize_usb_id;
+
+/// Prefix of current fingerprints.
+pub const FINGERPRINT_PREFIX: &str = "lfp2:";
+/// Prefix of fingerprints from before `lfp2`.
+pub const LEGACY_FINGERPRINT_PREFIX: &str = "sha256:";
+
+/// Hashed ahead of the fields, so `lfp2` digests never equal other SHA-256 uses.
+const DOMAIN_TAG: &[u8] = b"lusby-fingerprint-v2\0";
+
 pub struct FingerprintInput<'a> {
     pub vendor_id: &'a str,
     pub product_id: &'a str,
@@ -9,33 +32,210 @@ pub struct FingerprintInput<'a> {
     pub raw_descriptors: Option<&'a [u8]>,
 }
 
+impl FingerprintInput<'_> {
+    fn fields(&self) -> [Option<&[u8]>; 6] {
+        [
+            Some(self.vendor_id.as_bytes()),
+            Some(self.product_id.as_bytes()),
+            self.serial.map(str::as_bytes),
+            self.manufacturer.map(str::as_bytes),
+            self.product.map(str::as_bytes),
+            self.raw_descriptors,
+        ]
+    }
+}
+
+/// The current (`lfp2:`) fingerprint.
 pub fn compute_fingerprint(input: &FingerprintInput) -> String {
     let mut hasher = Sha256::new();
-    hasher.update(input.vendor_id.as_bytes());
-    hasher.update(b"|");
-    hasher.update(input.product_id.as_bytes());
-    hasher.update(b"|");
-    if let Some(s) = input.serial {
-        hasher.update(s.as_bytes());
+    hasher.update(DOMAIN_TAG);
+    for field in input.fields() {
+        match field {
+            Some(bytes) => {
+                hasher.update([1]);
+                hasher.update((bytes.len() as u64).to_be_bytes());
+                hasher.update(bytes);
+            }
+            None => hasher.update([0]),
+        }
+    }
+    format!("{}{}", FINGERPRINT_PREFIX, hex::encode(hasher.finalize()))
+}
+
+/// The `sha256:` fingerprint of lusby before `lfp2`, for checking values recorded with it.
+pub fn compute_legacy_fingerprint(input: &FingerprintInput) -> String {
+    let mut hasher = Sha256::new();
+    let fields = input.fields();
+    for (i, field) in fields.iter().enumerate() {
+        if let Some(bytes) = field {
+            hasher.update(bytes);
+        }
+        if i + 1 < fields.len() {
+            hasher.update(b"|");
+        }
+    }
+    format!(
+        "{}{}",
+        LEGACY_FINGERPRINT_PREFIX,
+        hex::encode(hasher.finalize())
+    )
+}
+
+/// True if `stored`, in either scheme, is the fingerprint of `input`.
+pub fn verify_fingerprint(stored: &str, input: &FingerprintInput) -> bool {
+    if stored.starts_with(FINGERPRINT_PREFIX) {
+        stored == compute_fingerprint(input)
+    } else if stored.starts_with(LEGACY_FINGERPRINT_PREFIX) {
+        stored == compute_legacy_fingerprint(input)
+    } else {
+        false
     }
-    hasher.update(b"|");
-    if let Some(m) = input.manufacturer {
-        hasher.update(m.as_bytes());
+}
+
+/// The fingerprinted attributes of one device, owned; `input` borrows them for hashing.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FingerprintSource {
+    pub vendor_id: String,
+    pub product_id: String,
+    pub serial: Option<String>,
+    pub manufacturer: Option<String>,
+    pub product: Option<String>,
+    pub descriptors: Vec<u8>,
+}
+
+impl FingerprintSource {
+    /// The attributes of a sysfs USB device directory such as `/sys/bus/usb/devices/1-2`.
+    ///
+    /// Besides ids, serial and strings this reads the raw `descriptors` attribute: the device
+    /// descriptor followed by each configuration with its interface and endpoint descriptors,
+    /// so a clone has to reproduce the whole layout. `None` if the ids or descriptors are
+    /// unreadable.
+    pub fn from_sysfs(dir: &Path) -> Option<Self> {
+        let attr = |name: &str| {
+            fs::read_to_string(dir.join(name))
+                .ok()
+                .map(|s| s.trim().to_string())
+        };
+        Some(Self {
+            vendor_id: normalize_usb_id(&attr("idVendor")?),
+            product_id: normalize_usb_id(&attr("idProduct")?),
+            descriptors: fs::read(dir.join("descriptors")).ok()?,
+            serial: attr("serial").filter(|s| !s.is_empty()),
+            manufacturer: attr("manufacturer"),
+            product: attr("product"),
+        })
+    }
+
+    /// A raw descriptor blob as in sysfs `descriptors`; the ids come from its device
+    /// descriptor. Strings are not part of the blob, set them separately if known.
+    pub fn from_descriptors(descriptors: Vec<u8>) -> Option<Self> {
+        // bLength, bDescriptorType = DEVICE, ..., idVendor and idProduct little-endian at 8
+        if descriptors.len() < 18 || descriptors[0] < 18 || descriptors[1] != 1 {
+            return None;
+        }
+        let id = |at: usize| {
+            format!(
+                "{:04x}",
+                u16::from_le_bytes([descriptors[at], descriptors[at + 1]])
+            )
+        };
+        Some(Self {
+            vendor_id: id(8),
+            product_id: id(10),
+            descriptors,
+            ..Self::default()
+        })
+    }
+
+    pub fn input(&self) -> FingerprintInput<'_> {
+        FingerprintInput {
+            vendor_id: &self.vendor_id,
+            product_id: &self.product_id,
+            serial: self.serial.as_deref(),
+            manufacturer: self.manufacturer.as_deref(),
+            product: self.product.as_deref(),
+            raw_descriptors: Some(&self.descriptors),
+        }
     }
-    hasher.update(b"|");
-    if let Some(p) = input.product {
-        hasher.update(p.as_bytes());
+
+    pub fn fingerprint(&self) -> String {
+        compute_fingerprint(&self.input())
+    }
+
+    /// The `sha256:` fingerprint released lusby recorded for this device, from the inputs it
+    /// used: udev's `ID_VENDOR_ID`/`ID_MODEL_ID` with `0x`, `ID_SERIAL_SHORT`, `ID_VENDOR` and
+    /// `ID_MODEL` (which fall back to the ids), and no descriptors.
+    pub fn legacy_fingerprint(&self) -> String {
+        let vendor_id = format!("0x{}", self.vendor_id);
+        let product_id = format!("0x{}", self.product_id);
+        // udev drops serials that Windows would not accept either
+        let serial = self
+            .serial
+            .as_deref()
+            .filter(|s| s.bytes().all(|b| (0x20..=0x7f).contains(&b) && b != b','))
+            .map(udev_string);
+        let manufacturer = udev_string(self.manufacturer.as_deref().unwrap_or(&self.vendor_id));
+        let product = udev_string(self.product.as_deref().unwrap_or(&self.product_id));
+        let non_empty = |s: &String| !s.is_empty();
+        compute_legacy_fingerprint(&FingerprintInput {
+            vendor_id: &vendor_id,
+            product_id: &product_id,
+            serial: serial.as_ref().filter(|s| non_empty(s)).map(String::as_str),
+            manufacturer: Some(&manufacturer)
+                .filter(|s| non_empty(s))
+                .map(String::as_str),
+            product: Some(&product).filter(|s| non_empty(s)).map(String::as_str),
+            raw_descriptors: None,
+        })
     }
-    hasher.update(b"|");
-    if let Some(desc) = input.raw_descriptors {
-        hasher.update(desc);
+
+    /// True if `stored`, in either scheme, is this device's fingerprint.
+    pub fn verify(&self, stored: &str) -> bool {
+        if stored.starts_with(FINGERPRINT_PREFIX) {
+            stored == self.fingerprint()
+        } else if stored.starts_with(LEGACY_FINGERPRINT_PREFIX) {
+            stored == self.legacy_fingerprint()
+        } else {
+            false
+        }
     }
-    let digest = hasher.finalize();
-    format!("sha256:{}", hex::encode(digest))
 }
 
+/// A USB string as udev stores it in `ID_VENDOR` and friends: whitespace trimmed, runs of it
+/// replaced by one `_`, and characters outside `[0-9A-Za-z#+-.:=@_]`, `\x` escapes and
+/// non-ASCII UTF-8 replaced by `_`.
+fn udev_string(value: &str) -> String {
+    let collapsed = value
+        .split([' ', '\t', '\n', '\x0b', '\x0c', '\r'])
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join("_");
+    collapsed
+        .char_indices()
+        .map(|(i, c)| {
+            let keep = c.is_ascii_alphanumeric()
+                || "#+-.:=@_".contains(c)
+                || !c.is_ascii()
+                || (c == '\\' && collapsed[i + 1..].starts_with('x'));
+            if keep {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Fingerprint of the device in a sysfs USB device directory, see
+/// `FingerprintSource::from_sysfs`. Every backend derives fingerprints this way, giving a
+/// device the same one everywhere.
+pub fn sysfs_fingerprint(dir: &Path) -> Option<String> {
+    Some(FingerprintSource::from_sysfs(dir)?.fingerprint())
+}
+
+/// The first 8 hex digits, for display.
 pub fn short_fingerprint(full: &str) -> String {
-    // Expect format sha256:<hex>
+    // Expect format <scheme>:<hex>
     if let Some(hexpart) = full.split(':').nth(1) {
         hexpart[0..8.min(hexpart.len())].to_string()
     } else {
@@ -1,8 +1,18 @@
 pub mod audit;
 pub mod backend;
 pub mod baseline;
+pub mod canonical;
 pub mod crypto;
+pub mod descriptors;
+pub mod error;
 pub mod fingerprint;
+pub mod keyfile;
+#[cfg(feature = "pkcs11")]
+pub mod pkcs11;
+pub mod revocation;
+pub mod schema;
+pub mod ssh_agent;
+pub mod trust;
 pub mod types;
 
 pub const APP_ID: &str = "lusby";
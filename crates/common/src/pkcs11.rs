@@ -0,0 +1,270 @@
+//! Ed25519 signing with keys held on a PKCS#11 token (`CKM_EDDSA`).
+//!
+//! Keys are addressed with RFC 7512 URIs, for example
+//! `pkcs11:token=lusby;object=baseline?module-path=/usr/lib/softhsm/libsofthsm2.so`.
+
+use cryptoki::context::{CInitializeArgs, Pkcs11};
+use cryptoki::mechanism::eddsa::{EddsaParams, EddsaSignatureScheme};
+use cryptoki::mechanism::Mechanism;
+use cryptoki::object::{Attribute, AttributeType, KeyType, ObjectClass, ObjectHandle};
+use cryptoki::session::{Session, UserType};
+use cryptoki::types::AuthPin;
+use ed25519_dalek::{Signature, VerifyingKey};
+
+use crate::crypto::{CryptoError, KeySigner};
+
+fn token_err(e: impl std::fmt::Display) -> CryptoError {
+    CryptoError::Token(e.to_string())
+}
+
+fn percent_decode(s: &str) -> Result<Vec<u8>, CryptoError> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = s
+                .get(i + 1..i + 3)
+                .ok_or_else(|| token_err("truncated percent escape in PKCS#11 URI"))?;
+            out.push(u8::from_str_radix(hex, 16).map_err(token_err)?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    Ok(out)
+}
+
+fn percent_decode_str(s: &str) -> Result<String, CryptoError> {
+    String::from_utf8(percent_decode(s)?).map_err(token_err)
+}
+
+/// The subset of RFC 7512 needed to find one signing key.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Pkcs11Uri {
+    pub token: Option<String>,
+    pub serial: Option<String>,
+    pub slot_id: Option<u64>,
+    pub object: Option<String>,
+    pub id: Option<Vec<u8>>,
+    pub module_path: Option<String>,
+    pub pin_value: Option<String>,
+    pub pin_source: Option<String>,
+}
+
+impl Pkcs11Uri {
+    pub fn parse(uri: &str) -> Result<Self, CryptoError> {
+        let rest = uri
+            .strip_prefix("pkcs11:")
+            .ok_or_else(|| token_err("PKCS#11 URI must start with pkcs11:"))?;
+        let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let mut parsed = Pkcs11Uri::default();
+        for attr in path.split(';').filter(|a| !a.is_empty()) {
+            let (k, v) = attr
+                .split_once('=')
+                .ok_or_else(|| token_err(format!("malformed PKCS#11 URI attribute {attr}")))?;
+            match k {
+                "token" => parsed.token = Some(percent_decode_str(v)?),
+                "serial" => parsed.serial = Some(percent_decode_str(v)?),
+                "slot-id" => parsed.slot_id = Some(v.parse().map_err(token_err)?),
+                "object" => parsed.object = Some(percent_decode_str(v)?),
+                "id" => parsed.id = Some(percent_decode(v)?),
+                "type" if v != "private" => {
+                    return Err(token_err("PKCS#11 URI must name a private key"))
+                }
+                // Other attributes (manufacturer, model, library-*) only narrow the match.
+                _ => {}
+            }
+        }
+        for attr in query.split('&').filter(|a| !a.is_empty()) {
+            let (k, v) = attr
+                .split_once('=')
+                .ok_or_else(|| token_err(format!("malformed PKCS#11 URI query {attr}")))?;
+            match k {
+                "module-path" => parsed.module_path = Some(percent_decode_str(v)?),
+                "pin-value" => parsed.pin_value = Some(percent_decode_str(v)?),
+                "pin-source" => parsed.pin_source = Some(percent_decode_str(v)?),
+                _ => {}
+            }
+        }
+        if parsed.object.is_none() && parsed.id.is_none() {
+            return Err(token_err("PKCS#11 URI needs object= or id="));
+        }
+        Ok(parsed)
+    }
+
+    /// PIN from `pin-value`, or the first line of the `pin-source` file.
+    pub fn pin(&self) -> Result<Option<String>, CryptoError> {
+        if let Some(pin) = &self.pin_value {
+            return Ok(Some(pin.clone()));
+        }
+        match &self.pin_source {
+            Some(source) => {
+                let path = source.strip_prefix("file:").unwrap_or(source);
+                let text = std::fs::read_to_string(path).map_err(token_err)?;
+                Ok(Some(text.lines().next().unwrap_or_default().to_string()))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn key_template(&self, class: ObjectClass) -> Vec<Attribute> {
+        let mut template = vec![
+            Attribute::Class(class),
+            Attribute::KeyType(KeyType::EC_EDWARDS),
+        ];
+        if let Some(label) = &self.object {
+            template.push(Attribute::Label(label.as_bytes().to_vec()));
+        }
+        if let Some(id) = &self.id {
+            template.push(Attribute::Id(id.clone()));
+        }
+        template
+    }
+}
+
+/// The 32-byte point from `CKA_EC_POINT`, which tokens return either raw or DER-wrapped.
+fn ed25519_point(ec_point: &[u8]) -> Result<VerifyingKey, CryptoError> {
+    let raw = match ec_point {
+        [0x04, 0x20, rest @ ..] if rest.len() == 32 => rest,
+        raw if raw.len() == 32 => raw,
+        _ => return Err(token_err("unexpected CKA_EC_POINT encoding")),
+    };
+    VerifyingKey::from_bytes(raw.try_into().expect("length checked")).map_err(token_err)
+}
+
+/// A logged-in session on the token holding one Ed25519 private key.
+pub struct Pkcs11Signer {
+    session: Session,
+    key: ObjectHandle,
+    public: VerifyingKey,
+}
+
+impl Pkcs11Signer {
+    /// Load the module, find the token and key named by `uri` and log in. `pin` overrides
+    /// any PIN in the URI.
+    pub fn open(uri: &Pkcs11Uri, pin: Option<&str>) -> Result<Self, CryptoError> {
+        let module = uri
+            .module_path
+            .as_deref()
+            .ok_or_else(|| token_err("PKCS#11 URI needs ?module-path="))?;
+        let ctx = Pkcs11::new(module).map_err(token_err)?;
+        ctx.initialize(CInitializeArgs::OsThreads)
+            .map_err(token_err)?;
+
+        let mut slot = None;
+        for s in ctx.get_slots_with_token().map_err(token_err)? {
+            let info = ctx.get_token_info(s).map_err(token_err)?;
+            if uri.slot_id.is_some_and(|id| id != s.id())
+                || uri.token.as_deref().is_some_and(|t| t != info.label())
+                || uri
+                    .serial
+                    .as_deref()
+                    .is_some_and(|n| n != info.serial_number())
+            {
+                continue;
+            }
+            slot = Some(s);
+            break;
+        }
+        let slot = slot.ok_or_else(|| token_err("no matching PKCS#11 token"))?;
+
+        let session = ctx.open_ro_session(slot).map_err(token_err)?;
+        let pin = match pin {
+            Some(p) => Some(p.to_string()),
+            None => uri.pin()?,
+        };
+        if let Some(pin) = pin {
+            session
+                .login(UserType::User, Some(&AuthPin::new(pin)))
+                .map_err(token_err)?;
+        }
+
+        let keys = session
+            .find_objects(&uri.key_template(ObjectClass::PRIVATE_KEY))
+            .map_err(token_err)?;
+        let key = match keys.as_slice() {
+            [key] => *key,
+            [] => return Err(token_err("no Ed25519 private key matches the URI")),
+            _ => return Err(token_err("URI matches more than one private key")),
+        };
+        let public = session
+            .find_objects(&uri.key_template(ObjectClass::PUBLIC_KEY))
+            .map_err(token_err)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| token_err("no public key object next to the private key"))?;
+        let point = session
+            .get_attributes(public, &[AttributeType::EcPoint])
+            .map_err(token_err)?
+            .into_iter()
+            .find_map(|a| match a {
+                Attribute::EcPoint(p) => Some(p),
+                _ => None,
+            })
+            .ok_or_else(|| token_err("public key has no CKA_EC_POINT"))?;
+
+        Ok(Self {
+            session,
+            key,
+            public: ed25519_point(&point)?,
+        })
+    }
+}
+
+impl KeySigner for Pkcs11Signer {
+    fn public_key(&self) -> Result<VerifyingKey, CryptoError> {
+        Ok(self.public)
+    }
+
+    fn sign_message(&self, msg: &[u8]) -> Result<Signature, CryptoError> {
+        let mechanism = Mechanism::Eddsa(EddsaParams::new(EddsaSignatureScheme::Pure));
+        let raw = self
+            .session
+            .sign(&mechanism, self.key, msg)
+            .map_err(token_err)?;
+        let sig = Signature::from_slice(&raw).map_err(token_err)?;
+        // Catch a public key object that does not belong to the private key.
+        self.public
+            .verify_strict(msg, &sig)
+            .map_err(|_| token_err("token signature does not verify against its public key"))?;
+        Ok(sig)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_rfc7512_uri() {
+        let uri = Pkcs11Uri::parse(
+            "pkcs11:token=Lusby%20Keys;object=baseline;id=%01%02;type=private\
+             ?module-path=/usr/lib/softhsm/libsofthsm2.so&pin-value=1234",
+        )
+        .unwrap();
+        assert_eq!(uri.token.as_deref(), Some("Lusby Keys"));
+        assert_eq!(uri.object.as_deref(), Some("baseline"));
+        assert_eq!(uri.id, Some(vec![1, 2]));
+        assert_eq!(
+            uri.module_path.as_deref(),
+            Some("/usr/lib/softhsm/libsofthsm2.so")
+        );
+        assert_eq!(uri.pin().unwrap().as_deref(), Some("1234"));
+
+        assert!(Pkcs11Uri::parse("pkcs11:token=x").is_err());
+        assert!(Pkcs11Uri::parse("pkcs11:object=x;type=cert").is_err());
+        assert!(Pkcs11Uri::parse("file:object=x").is_err());
+    }
+
+    #[test]
+    fn accepts_raw_and_der_ec_point() {
+        let vk = ed25519_dalek::SigningKey::from_bytes(&[7u8; 32]).verifying_key();
+        let mut der = vec![0x04, 0x20];
+        der.extend_from_slice(vk.as_bytes());
+        assert_eq!(ed25519_point(&der).unwrap(), vk);
+        assert_eq!(ed25519_point(vk.as_bytes()).unwrap(), vk);
+        assert!(ed25519_point(&der[..20]).is_err());
+    }
+}
@@ -0,0 +1,111 @@
+use chrono::{DateTime, Utc};
+use ed25519_dalek::VerifyingKey;
+use serde::{Deserialize, Serialize};
+
+use crate::crypto::{cosign_as, verify_cosign, CoSignature, KeySigner, SignatureFormat};
+use crate::types::DeviceInfo;
+
+/// Field name the list content is nested under in co-signed payloads.
+const COSIGN_KIND: &str = "revocation_list";
+
+fn normalize_id(s: &str) -> String {
+    s.trim_start_matches("0x").to_lowercase()
+}
+
+/// A revoked VID:PID, optionally narrowed to one serial.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RevokedDevice {
+    pub vendor_id: String,
+    pub product_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub serial: Option<String>,
+}
+
+impl RevokedDevice {
+    /// Parse `VID:PID` or `VID:PID:SERIAL`.
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut parts = s.splitn(3, ':');
+        let vid = parts.next().filter(|p| !p.is_empty())?;
+        let pid = parts.next().filter(|p| !p.is_empty())?;
+        Some(Self {
+            vendor_id: format!("0x{}", normalize_id(vid)),
+            product_id: format!("0x{}", normalize_id(pid)),
+            serial: parts.next().map(str::to_string),
+        })
+    }
+
+    pub fn matches(&self, device: &DeviceInfo) -> bool {
+        normalize_id(&self.vendor_id) == normalize_id(&device.vendor_id)
+            && normalize_id(&self.product_id) == normalize_id(&device.product_id)
+            && self.serial.as_deref().is_none_or(|s| s == device.serial)
+    }
+}
+
+/// Signed list of banned devices and signer keys. Takes priority over all baselines.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RevocationList {
+    pub version: u32,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    /// Lists with a lower sequence than the applied one are rejected.
+    pub sequence: u64,
+    #[serde(default)]
+    pub fingerprints: Vec<String>,
+    #[serde(default)]
+    pub devices: Vec<RevokedDevice>,
+    /// Key ids (see `crypto::key_id`) whose baselines are no longer trusted.
+    #[serde(default)]
+    pub revoked_keys: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub signatures: Vec<CoSignature>,
+}
+
+impl RevocationList {
+    pub fn without_signature(&self) -> Self {
+        let mut r = self.clone();
+        r.signatures.clear();
+        r
+    }
+
+    /// Add (or replace) this key's co-signature.
+    pub fn sign_attach<S: KeySigner + ?Sized>(&mut self, signer: &S) -> Result<(), String> {
+        self.sign_attach_as(signer, SignatureFormat::Ed25519)
+    }
+
+    /// Like `sign_attach`, choosing between a raw Ed25519 and an OpenSSH `sshsig` signature.
+    pub fn sign_attach_as<S: KeySigner + ?Sized>(
+        &mut self,
+        signer: &S,
+        format: SignatureFormat,
+    ) -> Result<(), String> {
+        let sig = cosign_as(signer, COSIGN_KIND, &self.without_signature(), format)
+            .map_err(|e| e.to_string())?;
+        self.signatures.retain(|s| s.key_id != sig.key_id);
+        self.signatures.push(sig);
+        Ok(())
+    }
+
+    pub fn verify_signature(&self, verifying_key: &VerifyingKey) -> Result<bool, String> {
+        let unsigned = self.without_signature();
+        for sig in &self.signatures {
+            if verify_cosign(verifying_key, COSIGN_KIND, &unsigned, sig)
+                .map_err(|e| e.to_string())?
+            {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    pub fn is_key_revoked(&self, key_id: &str) -> bool {
+        self.revoked_keys.iter().any(|k| k == key_id)
+    }
+
+    /// True if the device is banned by fingerprint (either scheme) or VID:PID[:serial].
+    pub fn blocks(&self, device: &DeviceInfo) -> bool {
+        [&device.fingerprint, &device.legacy_fingerprint]
+            .into_iter()
+            .any(|f| !f.is_empty() && self.fingerprints.contains(f))
+            || self.devices.iter().any(|d| d.matches(device))
+    }
+}
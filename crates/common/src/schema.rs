@@ -0,0 +1,218 @@
+//! Versioned baseline schemas.
+//!
+//! Signatures cover the canonical JSON of a baseline, so the serialized form of a released
+//! version must never change. Every older version is frozen here as its own struct and
+//! signed content is produced through it; `Baseline` is always the current version. Before
+//! changing `Baseline` or `DeviceEntry`, copy them here as the next frozen version, bump
+//! `BASELINE_VERSION` and add an upgrade step to `upgrade`.
+//!
+//! Versions:
+//! - 1: original format; VID/PID as written by the creator, unknown fields ignored.
+//! - 2: VID/PID are four lowercase hex digits, `device_type` is never empty and unknown
+//!   fields are rejected.
+
+use serde_json::Value;
+
+use crate::baseline::{normalize_usb_id, Baseline, DeviceEntry};
+
+/// Schema version written for new baselines.
+pub const BASELINE_VERSION: u32 = 2;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SchemaError {
+    #[error("baseline has no numeric \"version\" field")]
+    MissingVersion,
+    #[error(
+        "baseline schema version {found} is not supported (this lusby reads 1 to {}); \
+         upgrade lusby to use it",
+        BASELINE_VERSION
+    )]
+    UnsupportedVersion { found: u64 },
+    #[error("invalid version {version} baseline at {path}: {message}")]
+    Invalid {
+        version: u32,
+        /// JSONPath of the offending value, e.g. `$.devices[0].serial`.
+        path: String,
+        message: String,
+    },
+    #[error("baseline is not JSON: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Version 1, frozen.
+pub mod v1 {
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+
+    use crate::crypto::CoSignature;
+
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+    pub struct DeviceEntryV1 {
+        pub vendor_id: String,
+        pub product_id: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub serial: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub bus_path: Option<String>,
+        pub descriptors_hash: String,
+        pub device_type: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub comment: Option<String>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+    pub struct BaselineV1 {
+        pub version: u32,
+        pub created_by: String,
+        pub created_at: DateTime<Utc>,
+        pub devices: Vec<DeviceEntryV1>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub not_before: Option<DateTime<Utc>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub not_after: Option<DateTime<Utc>>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub hosts: Vec<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub sequence: Option<u64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub key_id: Option<String>,
+        #[serde(default)]
+        pub signature: Option<String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub signatures: Vec<CoSignature>,
+    }
+}
+
+use v1::{BaselineV1, DeviceEntryV1};
+
+impl From<DeviceEntryV1> for DeviceEntry {
+    fn from(d: DeviceEntryV1) -> Self {
+        Self {
+            vendor_id: d.vendor_id,
+            product_id: d.product_id,
+            serial: d.serial,
+            bus_path: d.bus_path,
+            descriptors_hash: d.descriptors_hash,
+            device_type: d.device_type,
+            comment: d.comment,
+        }
+    }
+}
+
+impl From<DeviceEntry> for DeviceEntryV1 {
+    fn from(d: DeviceEntry) -> Self {
+        Self {
+            vendor_id: d.vendor_id,
+            product_id: d.product_id,
+            serial: d.serial,
+            bus_path: d.bus_path,
+            descriptors_hash: d.descriptors_hash,
+            device_type: d.device_type,
+            comment: d.comment,
+        }
+    }
+}
+
+impl From<BaselineV1> for Baseline {
+    fn from(b: BaselineV1) -> Self {
+        Self {
+            version: 1,
+            created_by: b.created_by,
+            created_at: b.created_at,
+            devices: b.devices.into_iter().map(Into::into).collect(),
+            not_before: b.not_before,
+            not_after: b.not_after,
+            hosts: b.hosts,
+            sequence: b.sequence,
+            key_id: b.key_id,
+            signature: b.signature,
+            signatures: b.signatures,
+        }
+    }
+}
+
+impl From<Baseline> for BaselineV1 {
+    fn from(b: Baseline) -> Self {
+        Self {
+            version: 1,
+            created_by: b.created_by,
+            created_at: b.created_at,
+            devices: b.devices.into_iter().map(Into::into).collect(),
+            not_before: b.not_before,
+            not_after: b.not_after,
+            hosts: b.hosts,
+            sequence: b.sequence,
+            key_id: b.key_id,
+            signature: b.signature,
+            signatures: b.signatures,
+        }
+    }
+}
+
+/// Parse a baseline of any supported version. Older versions keep their version number
+/// and signatures; use `upgrade` to turn them into a current draft.
+pub fn parse(data: &[u8]) -> Result<Baseline, SchemaError> {
+    let value: Value = serde_json::from_slice(data)?;
+    let found = value
+        .get("version")
+        .and_then(Value::as_u64)
+        .ok_or(SchemaError::MissingVersion)?;
+    match found {
+        1 => from_value_at::<BaselineV1>(1, value).map(Baseline::from),
+        2 => from_value_at::<Baseline>(2, value),
+        _ => Err(SchemaError::UnsupportedVersion { found }),
+    }
+}
+
+/// Deserialize, reporting where in the document the first error is.
+fn from_value_at<T: serde::de::DeserializeOwned>(
+    version: u32,
+    value: Value,
+) -> Result<T, SchemaError> {
+    serde_path_to_error::deserialize(value).map_err(|e| {
+        let path = match e.path().to_string() {
+            p if p == "." => "$".to_string(),
+            p if p.starts_with('[') => format!("${}", p),
+            p => format!("$.{}", p),
+        };
+        SchemaError::Invalid {
+            version,
+            path,
+            message: e.into_inner().to_string(),
+        }
+    })
+}
+
+/// The JSON value signatures cover, serialized through the baseline's own schema version.
+pub fn signed_content(baseline: &Baseline) -> Result<Value, String> {
+    let unsigned = baseline.without_signature();
+    match unsigned.version {
+        1 => serde_json::to_value(BaselineV1::from(unsigned)),
+        BASELINE_VERSION => serde_json::to_value(unsigned),
+        v => return Err(SchemaError::UnsupportedVersion { found: v.into() }.to_string()),
+    }
+    .map_err(|e| e.to_string())
+}
+
+/// Upgrade a baseline to `BASELINE_VERSION`. The result is an unsigned draft: signatures
+/// are dropped because the signed content changes and the baseline must be re-signed.
+pub fn upgrade(baseline: &Baseline) -> Result<Baseline, SchemaError> {
+    let mut b = baseline.without_signature();
+    b.key_id = None;
+    if b.version == 1 {
+        for d in &mut b.devices {
+            d.vendor_id = normalize_usb_id(&d.vendor_id);
+            d.product_id = normalize_usb_id(&d.product_id);
+            if d.device_type.is_empty() {
+                d.device_type = "other".into();
+            }
+        }
+        b.version = 2;
+    }
+    if b.version != BASELINE_VERSION {
+        return Err(SchemaError::UnsupportedVersion {
+            found: b.version.into(),
+        });
+    }
+    Ok(b)
+}
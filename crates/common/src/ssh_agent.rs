@@ -0,0 +1,202 @@
+//! Minimal ssh-agent client (draft-miller-ssh-agent) for signing with Ed25519 agent keys.
+
+use base64::engine::general_purpose::STANDARD_NO_PAD as B64_NO_PAD;
+use base64::Engine;
+use ed25519_dalek::{Signature, VerifyingKey};
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+
+use crate::crypto::{key_id, CryptoError, KeySigner};
+
+const SSH_AGENT_FAILURE: u8 = 5;
+const SSH_AGENTC_REQUEST_IDENTITIES: u8 = 11;
+const SSH_AGENT_IDENTITIES_ANSWER: u8 = 12;
+const SSH_AGENTC_SIGN_REQUEST: u8 = 13;
+const SSH_AGENT_SIGN_RESPONSE: u8 = 14;
+const ED25519: &[u8] = b"ssh-ed25519";
+/// Agents refuse larger messages; lusby payloads are far smaller.
+const MAX_MESSAGE: usize = 256 * 1024;
+
+fn agent_err(e: impl std::fmt::Display) -> CryptoError {
+    CryptoError::Token(format!("ssh-agent: {}", e))
+}
+
+fn put_string(buf: &mut Vec<u8>, data: &[u8]) {
+    buf.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    buf.extend_from_slice(data);
+}
+
+/// Reads SSH wire-format fields from a message body.
+struct Cursor<'a>(&'a [u8]);
+
+impl<'a> Cursor<'a> {
+    fn u32(&mut self) -> Result<u32, CryptoError> {
+        let (head, rest) = self
+            .0
+            .split_first_chunk::<4>()
+            .ok_or_else(|| agent_err("truncated message"))?;
+        self.0 = rest;
+        Ok(u32::from_be_bytes(*head))
+    }
+
+    fn string(&mut self) -> Result<&'a [u8], CryptoError> {
+        let len = self.u32()? as usize;
+        if len > self.0.len() {
+            return Err(agent_err("truncated message"));
+        }
+        let (s, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(s)
+    }
+}
+
+/// Wire-format public key blob for an Ed25519 key.
+pub fn ed25519_key_blob(key: &VerifyingKey) -> Vec<u8> {
+    let mut blob = Vec::new();
+    put_string(&mut blob, ED25519);
+    put_string(&mut blob, key.as_bytes());
+    blob
+}
+
+/// OpenSSH-style fingerprint (`SHA256:<base64>`) as printed by `ssh-add -l`.
+pub fn openssh_fingerprint(key: &VerifyingKey) -> String {
+    format!(
+        "SHA256:{}",
+        B64_NO_PAD.encode(Sha256::digest(ed25519_key_blob(key)))
+    )
+}
+
+pub(crate) fn parse_ed25519_blob(blob: &[u8]) -> Option<VerifyingKey> {
+    let mut c = Cursor(blob);
+    if c.string().ok()? != ED25519 {
+        return None;
+    }
+    let raw: [u8; 32] = c.string().ok()?.try_into().ok()?;
+    VerifyingKey::from_bytes(&raw).ok()
+}
+
+fn request(socket: &Path, kind: u8, body: &[u8]) -> Result<(u8, Vec<u8>), CryptoError> {
+    let mut stream = UnixStream::connect(socket).map_err(agent_err)?;
+    let mut msg = Vec::with_capacity(body.len() + 5);
+    msg.extend_from_slice(&(body.len() as u32 + 1).to_be_bytes());
+    msg.push(kind);
+    msg.extend_from_slice(body);
+    stream.write_all(&msg).map_err(agent_err)?;
+
+    let mut len = [0u8; 4];
+    stream.read_exact(&mut len).map_err(agent_err)?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len == 0 || len > MAX_MESSAGE {
+        return Err(agent_err("invalid response length"));
+    }
+    let mut reply = vec![0u8; len];
+    stream.read_exact(&mut reply).map_err(agent_err)?;
+    let body = reply.split_off(1);
+    Ok((reply[0], body))
+}
+
+/// An Ed25519 key held by the agent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AgentIdentity {
+    pub key: VerifyingKey,
+    pub comment: String,
+}
+
+impl AgentIdentity {
+    /// Match by lusby key id, OpenSSH fingerprint or exact comment.
+    pub fn matches(&self, selector: &str) -> bool {
+        key_id(&self.key) == selector
+            || openssh_fingerprint(&self.key) == selector
+            || self.comment == selector
+    }
+}
+
+/// The agent socket from `$SSH_AUTH_SOCK`.
+pub fn default_socket() -> Result<PathBuf, CryptoError> {
+    std::env::var_os("SSH_AUTH_SOCK")
+        .map(PathBuf::from)
+        .ok_or_else(|| agent_err("SSH_AUTH_SOCK is not set"))
+}
+
+/// List the agent's Ed25519 keys; other key types are skipped.
+pub fn list_identities(socket: &Path) -> Result<Vec<AgentIdentity>, CryptoError> {
+    let (kind, body) = request(socket, SSH_AGENTC_REQUEST_IDENTITIES, &[])?;
+    if kind != SSH_AGENT_IDENTITIES_ANSWER {
+        return Err(agent_err(format!("unexpected reply {}", kind)));
+    }
+    let mut c = Cursor(&body);
+    let count = c.u32()?;
+    let mut out = Vec::new();
+    for _ in 0..count {
+        let blob = c.string()?;
+        let comment = String::from_utf8_lossy(c.string()?).into_owned();
+        if let Some(key) = parse_ed25519_blob(blob) {
+            out.push(AgentIdentity { key, comment });
+        }
+    }
+    Ok(out)
+}
+
+/// Signs through a running ssh-agent; the secret never leaves the agent.
+pub struct AgentSigner {
+    socket: PathBuf,
+    identity: AgentIdentity,
+}
+
+impl AgentSigner {
+    /// Pick the agent key matching `selector`, or the only Ed25519 key if none is given.
+    pub fn connect(socket: PathBuf, selector: Option<&str>) -> Result<Self, CryptoError> {
+        let ids = list_identities(&socket)?;
+        let mut candidates: Vec<AgentIdentity> = match selector {
+            Some(sel) => ids.into_iter().filter(|i| i.matches(sel)).collect(),
+            None => ids,
+        };
+        match candidates.len() {
+            1 => Ok(Self {
+                socket,
+                identity: candidates.remove(0),
+            }),
+            0 => Err(agent_err("no matching Ed25519 key in the agent")),
+            n => Err(agent_err(format!(
+                "{} Ed25519 keys in the agent, select one by fingerprint or comment",
+                n
+            ))),
+        }
+    }
+
+    pub fn identity(&self) -> &AgentIdentity {
+        &self.identity
+    }
+}
+
+impl KeySigner for AgentSigner {
+    fn public_key(&self) -> Result<VerifyingKey, CryptoError> {
+        Ok(self.identity.key)
+    }
+
+    fn sign_message(&self, msg: &[u8]) -> Result<Signature, CryptoError> {
+        let mut body = Vec::new();
+        put_string(&mut body, &ed25519_key_blob(&self.identity.key));
+        put_string(&mut body, msg);
+        body.extend_from_slice(&0u32.to_be_bytes());
+        let (kind, reply) = request(&self.socket, SSH_AGENTC_SIGN_REQUEST, &body)?;
+        match kind {
+            SSH_AGENT_SIGN_RESPONSE => {}
+            SSH_AGENT_FAILURE => return Err(agent_err("agent refused to sign")),
+            other => return Err(agent_err(format!("unexpected reply {}", other))),
+        }
+        let mut c = Cursor(&reply);
+        let mut sig = Cursor(c.string()?);
+        if sig.string()? != ED25519 {
+            return Err(agent_err("agent returned a non-Ed25519 signature"));
+        }
+        let sig = Signature::from_slice(sig.string()?).map_err(agent_err)?;
+        self.identity
+            .key
+            .verify_strict(msg, &sig)
+            .map_err(|_| agent_err("agent signature does not verify"))?;
+        Ok(sig)
+    }
+}
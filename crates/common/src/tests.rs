@@ -1,9 +1,35 @@
 #[cfg(test)]
 use crate::audit::{verify_chain, AuditEntry, AuditEntryPayload};
 #[cfg(test)]
-use crate::baseline::{Baseline, DeviceEntry};
+use crate::baseline::{
+    diff_baselines, glob_match, lint_baseline, Baseline, DeviceEntry, HostIdentity, KeyScope,
+};
 #[cfg(test)]
-use crate::fingerprint::{compute_fingerprint, short_fingerprint, FingerprintInput};
+use crate::crypto::{key_id, sign_canonical, SignatureFormat};
+#[cfg(test)]
+use crate::descriptors::{
+    class_name, interface_class_name, parse_descriptors, parse_hid_report, parse_string_descriptor,
+    render_tree, DescriptorError, HidItemType,
+};
+#[cfg(test)]
+use crate::fingerprint::{
+    compute_fingerprint, compute_legacy_fingerprint, short_fingerprint, sysfs_fingerprint,
+    verify_fingerprint, FingerprintInput, FingerprintSource,
+};
+#[cfg(test)]
+use crate::keyfile::{
+    from_openssh, is_openssh_private_key, openssh_is_encrypted, to_openssh, KeyFile, KeyFileError,
+};
+#[cfg(test)]
+use crate::revocation::{RevocationList, RevokedDevice};
+#[cfg(test)]
+use crate::ssh_agent::ed25519_key_blob;
+#[cfg(test)]
+use crate::trust::{parse_ssh_key_list, validate_key_name, SshKeyList, TrustedKey, TrustedKeyMeta};
+#[cfg(test)]
+use crate::types::DeviceInfo;
+#[cfg(test)]
+use base64::{engine::general_purpose::STANDARD as B64, Engine};
 #[cfg(test)]
 use chrono::Utc;
 #[cfg(test)]
@@ -27,6 +53,284 @@ fn fingerprint_deterministic() {
     assert_eq!(short_fingerprint(&fp1).len(), 8);
 }
 
+#[test]
+fn fingerprints_are_length_prefixed_and_versioned() {
+    let input = |serial, manufacturer| FingerprintInput {
+        vendor_id: "046d",
+        product_id: "c534",
+        serial,
+        manufacturer,
+        product: None,
+        raw_descriptors: None,
+    };
+    // `|` in a field moves the legacy field boundaries; lfp2 keeps them apart
+    let split = input(Some("a|b"), Some("c"));
+    let shifted = input(Some("a"), Some("b|c"));
+    assert_eq!(
+        compute_legacy_fingerprint(&split),
+        compute_legacy_fingerprint(&shifted)
+    );
+    assert_ne!(compute_fingerprint(&split), compute_fingerprint(&shifted));
+    // An absent field differs from an empty one
+    assert_ne!(
+        compute_fingerprint(&input(None, None)),
+        compute_fingerprint(&input(Some(""), None))
+    );
+
+    let lfp2 = compute_fingerprint(&split);
+    let legacy = compute_legacy_fingerprint(&split);
+    assert!(lfp2.starts_with("lfp2:") && lfp2.len() == 5 + 64);
+    assert!(verify_fingerprint(&lfp2, &split));
+    assert!(verify_fingerprint(&legacy, &split));
+    assert!(!verify_fingerprint(&lfp2, &shifted));
+    assert!(!verify_fingerprint(&lfp2.replace("lfp2:", "md5:"), &split));
+    assert_eq!(short_fingerprint(&lfp2), lfp2[5..13]);
+}
+
+#[test]
+fn sysfs_fingerprints_cover_the_descriptor_layout() {
+    // Device descriptor, then a configuration with one HID interface and an interrupt
+    // IN endpoint of 8 bytes
+    let descriptors: Vec<u8> = [
+        &[
+            18, 1, 0x00, 0x02, 0, 0, 0, 8, 0x6d, 0x04, 0x34, 0xc5, 0, 1, 1, 2, 3, 1,
+        ][..],
+        &[9, 2, 34, 0, 1, 1, 0, 0xa0, 50],
+        &[9, 4, 0, 0, 1, 3, 1, 1, 0],
+        &[9, 0x21, 0x11, 0x01, 0, 1, 0x22, 65, 0],
+        &[7, 5, 0x81, 3, 8, 0, 10],
+    ]
+    .concat();
+    let dir = tempfile::tempdir().unwrap();
+    let write = |name: &str, value: &[u8]| std::fs::write(dir.path().join(name), value).unwrap();
+    write("idVendor", b"046d\n");
+    write("idProduct", b"c534\n");
+    write("serial", b"ABC\n");
+    write("product", b"USB Receiver\n");
+    write("descriptors", &descriptors);
+    let fp = sysfs_fingerprint(dir.path()).unwrap();
+    assert_eq!(
+        fp,
+        compute_fingerprint(&FingerprintInput {
+            vendor_id: "046d",
+            product_id: "c534",
+            serial: Some("ABC"),
+            manufacturer: None,
+            product: Some("USB Receiver"),
+            raw_descriptors: Some(&descriptors),
+        })
+    );
+
+    // Same ids and strings, but a bigger endpoint: a different device
+    let mut cloned = descriptors.clone();
+    let last = cloned.len() - 3;
+    cloned[last] = 64;
+    write("descriptors", &cloned);
+    assert_ne!(sysfs_fingerprint(dir.path()).unwrap(), fp);
+
+    // A descriptor file alone yields the ids but not the strings
+    let from_file = FingerprintSource::from_descriptors(descriptors.clone()).unwrap();
+    assert_eq!(
+        (from_file.vendor_id.as_str(), from_file.product_id.as_str()),
+        ("046d", "c534")
+    );
+    assert_ne!(from_file.fingerprint(), fp);
+    assert!(FingerprintSource::from_descriptors(descriptors[9..].to_vec()).is_none());
+
+    std::fs::remove_file(dir.path().join("descriptors")).unwrap();
+    assert!(sysfs_fingerprint(dir.path()).is_none());
+}
+
+#[test]
+fn legacy_fingerprints_match_released_values() {
+    // Recorded by released lusby from udev properties: ID_VENDOR_ID=046d, ID_MODEL_ID=c534,
+    // ID_SERIAL_SHORT=ABC, ID_VENDOR=Logitech, ID_MODEL=USB_Receiver
+    const RECORDED: &str =
+        "sha256:6752df6214a2defb943ebcf5f78cce0a8b89717704ebc441956fb5d6c55581f3";
+    // Without strings, udev fell back to the ids and left the serial out
+    const RECORDED_BARE: &str =
+        "sha256:b3ad6a54d51c57b6b58e9cbe14197a0982f7adbbdbe58d7fda2f2c578a7c377d";
+
+    let dir = tempfile::tempdir().unwrap();
+    let write = |name: &str, value: &[u8]| std::fs::write(dir.path().join(name), value).unwrap();
+    write("idVendor", b"046d\n");
+    write("idProduct", b"c534\n");
+    write(
+        "descriptors",
+        &[
+            18, 1, 0, 2, 0, 0, 0, 8, 0x6d, 0x04, 0x34, 0xc5, 0, 0, 1, 2, 3, 1,
+        ],
+    );
+    let bare = FingerprintSource::from_sysfs(dir.path()).unwrap();
+    assert_eq!(bare.legacy_fingerprint(), RECORDED_BARE);
+
+    write("serial", b"ABC\n");
+    write("manufacturer", b"Logitech\n");
+    write("product", b"USB Receiver\n");
+    let source = FingerprintSource::from_sysfs(dir.path()).unwrap();
+    assert_eq!(source.legacy_fingerprint(), RECORDED);
+    assert!(source.verify(RECORDED));
+    assert!(source.verify(&source.fingerprint()));
+    assert!(!source.verify(RECORDED_BARE));
+
+    // Descriptors and the exact strings no longer change the legacy value
+    write("product", b"  USB   Receiver \n");
+    write(
+        "descriptors",
+        &[
+            18, 1, 0, 2, 0, 0, 0, 64, 0x6d, 0x04, 0x34, 0xc5, 0, 0, 1, 2, 3, 1,
+        ],
+    );
+    let source = FingerprintSource::from_sysfs(dir.path()).unwrap();
+    assert_eq!(source.legacy_fingerprint(), RECORDED);
+}
+
+/// A keyboard that is also a flash drive: HID boot keyboard on interface 0, bulk-only mass
+/// storage on interface 1.
+#[cfg(test)]
+fn keyboard_and_storage_descriptors() -> Vec<u8> {
+    [
+        &[
+            18, 1, 0x00, 0x02, 0, 0, 0, 64, 0x81, 0x07, 0x81, 0x55, 0x00, 0x01, 1, 2, 3, 1,
+        ][..],
+        &[9, 2, 57, 0, 2, 1, 0, 0xa0, 50],
+        &[9, 4, 0, 0, 1, 3, 1, 1, 0],
+        &[9, 0x21, 0x11, 0x01, 0, 1, 0x22, 63, 0],
+        &[7, 5, 0x81, 3, 8, 0, 10],
+        &[9, 4, 1, 0, 2, 8, 6, 0x50, 0],
+        &[7, 5, 0x82, 2, 0x00, 0x02, 0],
+        &[7, 5, 0x02, 2, 0x00, 0x02, 0],
+    ]
+    .concat()
+}
+
+#[test]
+fn descriptors_parse_into_a_tree() {
+    let parsed = parse_descriptors(&keyboard_and_storage_descriptors()).unwrap();
+    assert_eq!(
+        (parsed.device.vendor_id, parsed.device.product_id),
+        (0x0781, 0x5581)
+    );
+    assert_eq!(parsed.device.usb_version, 0x0200);
+    assert_eq!(parsed.configurations.len(), 1);
+    let config = &parsed.configurations[0];
+    assert_eq!(config.max_power_ma, 100);
+    assert!(!config.self_powered() && config.remote_wakeup());
+
+    let classes: Vec<&str> = parsed.interfaces().map(|i| i.class_name()).collect();
+    assert_eq!(classes, ["Keyboard", "Mass Storage"]);
+    let keyboard = &config.interfaces[0];
+    let hid = keyboard.hid.as_ref().unwrap();
+    assert_eq!((hid.hid_version, hid.report_length), (0x0111, 63));
+    assert_eq!(keyboard.endpoints[0].transfer_type(), "Interrupt");
+    let storage = &config.interfaces[1];
+    assert_eq!(storage.endpoints.len(), 2);
+    assert!(storage.endpoints[0].is_in() && !storage.endpoints[1].is_in());
+    assert_eq!(storage.endpoints[1].max_packet_size, 512);
+
+    let strings = [(2, "Cruzer Blade".to_string())].into_iter().collect();
+    let tree = render_tree(&parsed, &strings);
+    assert!(tree.starts_with("Device Descriptor:"));
+    assert!(tree.contains("idVendor"));
+    assert!(tree.contains("0x0781"));
+    assert!(tree.contains("Cruzer Blade"));
+    assert!(tree.contains("3 Keyboard"));
+    assert!(tree.contains("8 Mass Storage"));
+    assert!(tree.contains("EP 2 OUT"));
+    assert!(tree.contains("Bulk"));
+}
+
+#[test]
+fn malformed_descriptors_are_errors() {
+    let good = keyboard_and_storage_descriptors();
+    assert_eq!(
+        parse_descriptors(&[]),
+        Err(DescriptorError::Truncated { offset: 0 })
+    );
+    // Cut inside the last endpoint
+    assert_eq!(
+        parse_descriptors(&good[..good.len() - 2]),
+        Err(DescriptorError::Truncated { offset: 68 })
+    );
+    // A zero bLength would never advance
+    let mut zero = good.clone();
+    zero[18] = 0;
+    assert!(matches!(
+        parse_descriptors(&zero),
+        Err(DescriptorError::Malformed { offset: 18, .. })
+    ));
+    // Configuration data without a device descriptor
+    assert!(parse_descriptors(&good[18..]).is_err());
+    // An endpoint before any interface
+    let orphan = [&good[..27], &good[45..52]].concat();
+    assert!(parse_descriptors(&orphan).is_err());
+    // A HID descriptor claiming more class descriptors than it has
+    let mut hid = good.clone();
+    hid[27 + 9 + 5] = 4;
+    assert!(parse_descriptors(&hid).is_err());
+}
+
+#[test]
+fn hid_reports_and_strings_parse() {
+    // Start of a boot keyboard report: Usage Page (Generic Desktop), Usage (Keyboard),
+    // Collection (Application), Usage Page (Keys), Usage Minimum (224), End Collection
+    let report = [
+        0x05, 0x01, 0x09, 0x06, 0xa1, 0x01, 0x05, 0x07, 0x19, 0xe0, 0xc0,
+    ];
+    let items = parse_hid_report(&report).unwrap();
+    let names: Vec<&str> = items.iter().map(|i| i.name()).collect();
+    assert_eq!(
+        names,
+        [
+            "Usage Page",
+            "Usage",
+            "Collection",
+            "Usage Page",
+            "Usage Minimum",
+            "End Collection"
+        ]
+    );
+    assert_eq!(items[1].value(), 6);
+    assert_eq!(items[4].value(), 0xe0);
+    assert_eq!(items[5].item_type, HidItemType::Main);
+    assert!(parse_hid_report(&[0x27, 0xff, 0xff]).is_err());
+
+    let string = [10, 3, b'D', 0, b'i', 0, b's', 0, b'k', 0];
+    assert_eq!(parse_string_descriptor(&string).unwrap(), "Disk");
+    assert!(parse_string_descriptor(&string[..6]).is_err());
+    assert!(parse_string_descriptor(&[4, 2, 0, 0]).is_err());
+}
+
+#[test]
+fn interface_classes_read_as_device_kinds() {
+    assert_eq!(class_name(0x02, 0x06, 0x00), "CDC Ethernet");
+    assert_eq!(class_name(0x03, 0x00, 0x00), "HID");
+    assert_eq!(interface_class_name("03:01:02"), Some("Mouse"));
+    assert_eq!(interface_class_name("ff:00:00"), Some("Vendor Specific"));
+    assert_eq!(interface_class_name("03:01"), None);
+    assert_eq!(interface_class_name("zz:00:00"), None);
+
+    let device = DeviceInfo {
+        id: "1-2".into(),
+        vendor_id: "0781".into(),
+        product_id: "5581".into(),
+        serial: String::new(),
+        fingerprint: String::new(),
+        device_type: "storage".into(),
+        allowed: false,
+        persistent: false,
+        interfaces: ["03:01:01", "08:06:50", "03:01:01", "02:06:00", "bogus"]
+            .iter()
+            .map(|i| i.to_string())
+            .collect(),
+        legacy_fingerprint: String::new(),
+    };
+    assert_eq!(
+        device.interface_classes(),
+        ["Keyboard", "Mass Storage", "CDC Ethernet"]
+    );
+}
+
 #[test]
 fn canonical_sign_verify_baseline() {
     let mut rng = OsRng;
@@ -46,7 +350,13 @@ fn canonical_sign_verify_baseline() {
             device_type: "hid".into(),
             comment: Some("test".into()),
         }],
+        not_before: None,
+        not_after: None,
+        hosts: Vec::new(),
+        sequence: None,
+        key_id: None,
         signature: None,
+        signatures: Vec::new(),
     };
 
     b.sign_attach(&sk).expect("sign");
@@ -60,6 +370,390 @@ fn canonical_sign_verify_baseline() {
     assert!(!ok);
 }
 
+#[test]
+fn signer_key_id_is_bound_and_scoped() {
+    let sk = SigningKey::generate(&mut OsRng);
+    let other = SigningKey::generate(&mut OsRng);
+    let mut b = Baseline {
+        version: 1,
+        created_by: "helpdesk".into(),
+        created_at: Utc::now(),
+        devices: vec![DeviceEntry {
+            vendor_id: "0x0781".into(),
+            product_id: "0x5581".into(),
+            serial: None,
+            bus_path: None,
+            descriptors_hash: "sha256:aa".into(),
+            device_type: "storage".into(),
+            comment: None,
+        }],
+        not_before: None,
+        not_after: None,
+        hosts: Vec::new(),
+        sequence: None,
+        key_id: None,
+        signature: None,
+        signatures: Vec::new(),
+    };
+
+    // Legacy baselines signed without a key id still verify.
+    b.signature = Some(sign_canonical(&sk, &b.without_signature()).unwrap());
+    assert!(b.verify_signature(&sk.verifying_key()).unwrap());
+
+    b.sign_attach(&sk).unwrap();
+    assert_eq!(b.signatures[0].key_id, key_id(&sk.verifying_key()));
+    assert!(b.verify_signature(&sk.verifying_key()).unwrap());
+    assert!(!b.verify_signature(&other.verifying_key()).unwrap());
+
+    let helpdesk = KeyScope {
+        device_types: vec!["hid".into()],
+    };
+    assert!(helpdesk.violation(&b).is_some());
+    assert!(KeyScope::default().violation(&b).is_none());
+}
+
+#[test]
+fn cosignatures_accumulate_without_invalidating() {
+    let a = SigningKey::generate(&mut OsRng);
+    let b_key = SigningKey::generate(&mut OsRng);
+    let outsider = SigningKey::generate(&mut OsRng);
+    let mut b = Baseline {
+        version: 1,
+        created_by: "admin".into(),
+        created_at: Utc::now(),
+        devices: vec![],
+        not_before: None,
+        not_after: None,
+        hosts: Vec::new(),
+        sequence: None,
+        key_id: None,
+        signature: None,
+        signatures: Vec::new(),
+    };
+    // Start from a legacy single signature; co-signing must keep it valid.
+    b.signature = Some(sign_canonical(&a, &b.without_signature()).unwrap());
+    b.sign_attach(&b_key).unwrap();
+    b.sign_attach(&b_key).unwrap();
+    assert_eq!(b.signatures.len(), 1);
+
+    let keys = [
+        a.verifying_key(),
+        b_key.verifying_key(),
+        outsider.verifying_key(),
+    ];
+    let signers = b.verified_key_ids(&keys);
+    assert_eq!(
+        signers,
+        vec![key_id(&a.verifying_key()), key_id(&b_key.verifying_key())]
+    );
+
+    // A forged timestamp breaks only that co-signature.
+    b.signatures[0].signed_at = Utc::now() + chrono::Duration::days(1);
+    assert_eq!(b.verified_key_ids(&keys).len(), 1);
+}
+
+#[test]
+fn sshsig_cosignatures_verify() {
+    let a = SigningKey::generate(&mut OsRng);
+    let b_key = SigningKey::generate(&mut OsRng);
+    let mut b = Baseline {
+        version: 1,
+        created_by: "admin".into(),
+        created_at: Utc::now(),
+        devices: vec![],
+        not_before: None,
+        not_after: None,
+        hosts: Vec::new(),
+        sequence: Some(3),
+        key_id: None,
+        signature: None,
+        signatures: Vec::new(),
+    };
+    b.sign_attach_as(&a, SignatureFormat::Sshsig).unwrap();
+    b.sign_attach(&b_key).unwrap();
+    assert_eq!(b.signatures[0].format, SignatureFormat::Sshsig);
+    // Raw Ed25519 co-signatures serialize exactly as before
+    let raw = serde_json::to_value(&b.signatures[1]).unwrap();
+    assert!(raw.get("format").is_none());
+
+    let keys = [a.verifying_key(), b_key.verifying_key()];
+    assert_eq!(b.verified_key_ids(&keys).len(), 2);
+    assert_eq!(b.verified_key_ids(&[b_key.verifying_key()]).len(), 1);
+
+    // The sshsig blob carries its own public key; a different key must not verify it
+    let mut swapped = b.clone();
+    swapped.signatures[0].key_id = key_id(&b_key.verifying_key());
+    assert_eq!(swapped.verified_key_ids(&keys).len(), 1);
+
+    b.sequence = Some(4);
+    assert!(b.verified_key_ids(&keys).is_empty());
+}
+
+#[test]
+fn ssh_key_lists_import() {
+    let sk = SigningKey::generate(&mut OsRng);
+    let b64 = B64.encode(ed25519_key_blob(&sk.verifying_key()));
+    let authorized = format!(
+        "# admins\n\
+         ssh-ed25519 {b64} alice@laptop\n\
+         no-pty,command=\"echo a,b\" ssh-ed25519 {b64}\n\
+         cert-authority ssh-ed25519 {b64} ca\n\
+         ssh-rsa AAAAB3NzaC1yc2E= bob\n"
+    );
+    let entries = parse_ssh_key_list(&authorized, SshKeyList::AuthorizedKeys);
+    assert_eq!(entries.len(), 4);
+    let alice = entries[0].as_ref().unwrap();
+    assert_eq!(alice.name, "alice-laptop");
+    assert_eq!(alice.verifying_key, sk.verifying_key());
+    assert!(entries[1].as_ref().unwrap().name.starts_with("ssh-"));
+    assert!(entries[2].as_ref().unwrap_err().starts_with("line 4:"));
+    assert!(entries[3].is_err());
+
+    let signers = format!(
+        "carol@example.com namespaces=\"git,lusby\",valid-before=\"20300101Z\" ssh-ed25519 {b64}\n\
+         dave@example.com namespaces=\"git\" ssh-ed25519 {b64}\n\
+         ssh-deploy@example.com ssh-ed25519 {b64}\n\
+         sk-team@corp namespaces=\"lusby\" ssh-ed25519 {b64}\n\
+         erin@example.com valid-after=\"20990101Z\" ssh-ed25519 {b64}\n\
+         frank@example.com valid-after=\"20200101Z\" ssh-ed25519 {b64}\n"
+    );
+    let entries = parse_ssh_key_list(&signers, SshKeyList::AllowedSigners);
+    let carol = entries[0].as_ref().unwrap();
+    assert_eq!(carol.name, "carol-example.com");
+    assert_eq!(
+        carol.not_after.unwrap().to_rfc3339(),
+        "2030-01-01T00:00:00+00:00"
+    );
+    assert!(entries[1].is_err());
+    // Principals that start like a key type are still principals
+    assert_eq!(entries[2].as_ref().unwrap().name, "ssh-deploy-example.com");
+    assert_eq!(entries[3].as_ref().unwrap().name, "sk-team-corp");
+    // Keys are not trusted before their window opens
+    assert!(entries[4].as_ref().unwrap_err().contains("not valid until"));
+    assert_eq!(entries[5].as_ref().unwrap().name, "frank-example.com");
+}
+
+#[test]
+fn baseline_lint_reports_every_issue_with_its_path() {
+    let doc = serde_json::json!({
+        "version": 2,
+        "created_by": "admin",
+        "created_at": "2025-01-01T00:00:00Z",
+        "not_before": "2026-01-01T00:00:00Z",
+        "not_after": "2025-06-01T00:00:00Z",
+        "hosts": ["ws-*", " "],
+        "devices": [
+            {"vendor_id": "046d", "product_id": "c31c", "descriptors_hash": "", "device_type": "hid"},
+            {"vendor_id": "0x046D", "product_id": "c31c", "serial": "A\nB",
+             "descriptors_hash": "md5:00", "device_type": "keyboard"},
+            {"vendor_id": "046d", "product_id": "c31c", "descriptors_hash": "", "device_type": "hid"}
+        ],
+        "signature": null
+    });
+    let issues = lint_baseline(doc.to_string().as_bytes()).unwrap_err();
+    let paths: Vec<&str> = issues.iter().map(|i| i.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        [
+            "$.not_after",
+            "$.hosts[1]",
+            "$.devices[1].vendor_id",
+            "$.devices[1].serial",
+            "$.devices[1].descriptors_hash",
+            "$.devices[1].device_type",
+            "$.devices[2]",
+        ]
+    );
+    assert_eq!(
+        issues[6].to_string(),
+        "$.devices[2]: duplicate of $.devices[0]"
+    );
+
+    // Parse errors carry the location too
+    let mut bad = doc.clone();
+    bad["devices"][1]["serial"] = 5.into();
+    let issues = lint_baseline(bad.to_string().as_bytes()).unwrap_err();
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].path, "$.devices[1].serial");
+
+    // Version 1 tolerated prefixed ids and empty types
+    let mut v1 = doc;
+    v1["version"] = 1.into();
+    v1["devices"][1]["device_type"] = "".into();
+    let issues = lint_baseline(v1.to_string().as_bytes()).unwrap_err();
+    assert!(!issues.iter().any(|i| i.path.ends_with("vendor_id")));
+    assert!(!issues.iter().any(|i| i.path.ends_with("device_type")));
+}
+
+#[test]
+fn baseline_validity_window_and_hosts() {
+    let now = Utc::now();
+    let host = HostIdentity {
+        machine_id: "0123456789abcdef".into(),
+        hostname: "lab-pc-07".into(),
+    };
+    let mut b = Baseline {
+        version: 1,
+        created_by: "admin".into(),
+        created_at: now,
+        devices: vec![],
+        not_before: None,
+        not_after: None,
+        hosts: Vec::new(),
+        sequence: None,
+        key_id: None,
+        signature: None,
+        signatures: Vec::new(),
+    };
+    assert!(b.validity_error(now, &host).is_none());
+
+    b.not_before = Some(now + chrono::Duration::hours(1));
+    assert!(b.validity_error(now, &host).is_some());
+    b.not_before = None;
+
+    b.not_after = Some(now - chrono::Duration::seconds(1));
+    assert!(b.is_expired(now));
+    assert!(b.validity_error(now, &host).is_some());
+    b.not_after = Some(now + chrono::Duration::days(30));
+
+    b.hosts = vec!["lab-pc-*".into()];
+    assert!(b.validity_error(now, &host).is_none());
+    b.hosts = vec!["office-*".into(), "0123*".into()];
+    assert!(b.validity_error(now, &host).is_none());
+    b.hosts = vec!["office-*".into()];
+    assert!(b.validity_error(now, &host).is_some());
+
+    assert!(glob_match("a?c*", "abcdef"));
+    assert!(!glob_match("a?c", "abcd"));
+    assert!(glob_match("*", ""));
+}
+
+#[test]
+fn revocation_list_signs_and_blocks() {
+    let sk = SigningKey::generate(&mut OsRng);
+    let other = SigningKey::generate(&mut OsRng);
+    let mut list = RevocationList {
+        version: 1,
+        created_by: "secops".into(),
+        created_at: Utc::now(),
+        sequence: 3,
+        fingerprints: vec!["sha256:bad".into()],
+        devices: vec![RevokedDevice::parse("0781:5581:LOST").unwrap()],
+        revoked_keys: vec![key_id(&other.verifying_key())],
+        signatures: Vec::new(),
+    };
+    list.sign_attach(&sk).unwrap();
+    assert!(list.verify_signature(&sk.verifying_key()).unwrap());
+    assert!(!list.verify_signature(&other.verifying_key()).unwrap());
+    assert!(list.is_key_revoked(&key_id(&other.verifying_key())));
+
+    let mut tampered = list.clone();
+    tampered.revoked_keys.clear();
+    assert!(!tampered.verify_signature(&sk.verifying_key()).unwrap());
+
+    let device = |serial: &str, fingerprint: &str| DeviceInfo {
+        id: "1-2".into(),
+        vendor_id: "0781".into(),
+        product_id: "5581".into(),
+        serial: serial.into(),
+        fingerprint: fingerprint.into(),
+        device_type: "storage".into(),
+        allowed: true,
+        persistent: false,
+        interfaces: Vec::new(),
+        legacy_fingerprint: String::new(),
+    };
+    assert!(list.blocks(&device("LOST", "")));
+    assert!(list.blocks(&device("OTHER", "sha256:bad")));
+    assert!(!list.blocks(&device("OTHER", "sha256:good")));
+    // Lists written before lfp2 still match through the legacy fingerprint
+    let upgraded = DeviceInfo {
+        legacy_fingerprint: "sha256:bad".into(),
+        ..device("OTHER", "lfp2:new")
+    };
+    assert!(list.blocks(&upgraded));
+
+    assert!(RevokedDevice::parse("0781").is_none());
+    assert!(RevokedDevice::parse("0x0781:0x5581")
+        .unwrap()
+        .serial
+        .is_none());
+}
+
+#[test]
+fn key_file_roundtrip_and_tamper() {
+    let sk = SigningKey::generate(&mut OsRng);
+    // Low Argon2 cost keeps the test fast; the format records the parameters.
+    let file = KeyFile::encrypt_with_cost(&sk, "release", "correct horse", 256, 1, 1).unwrap();
+    assert_eq!(file.key_id, key_id(&sk.verifying_key()));
+    assert_eq!(file.verifying_key().unwrap(), sk.verifying_key());
+
+    let json = serde_json::to_string(&file).unwrap();
+    assert!(!json.contains(&B64.encode(sk.to_bytes())));
+    let parsed: KeyFile = serde_json::from_str(&json).unwrap();
+    assert_eq!(
+        parsed.decrypt("correct horse").unwrap().to_bytes(),
+        sk.to_bytes()
+    );
+    assert!(matches!(
+        parsed.decrypt("wrong"),
+        Err(KeyFileError::Decrypt)
+    ));
+
+    let mut renamed = parsed.clone();
+    renamed.name = "other".into();
+    assert!(renamed.decrypt("correct horse").is_err());
+}
+
+#[test]
+fn openssh_import_export() {
+    let sk = SigningKey::generate(&mut OsRng);
+    let plain = to_openssh(&sk, "admin@example", None).unwrap();
+    assert!(is_openssh_private_key(&plain));
+    assert!(!openssh_is_encrypted(&plain).unwrap());
+    let (back, comment) = from_openssh(&plain, None).unwrap();
+    assert_eq!(back.to_bytes(), sk.to_bytes());
+    assert_eq!(comment, "admin@example");
+
+    let encrypted = to_openssh(&sk, "", Some("pw")).unwrap();
+    assert!(openssh_is_encrypted(&encrypted).unwrap());
+    assert!(from_openssh(&encrypted, None).is_err());
+    assert!(from_openssh(&encrypted, Some("nope")).is_err());
+    let (back, _) = from_openssh(&encrypted, Some("pw")).unwrap();
+    assert_eq!(back.to_bytes(), sk.to_bytes());
+}
+
+#[test]
+fn trusted_key_names_and_metadata() {
+    assert_eq!(validate_key_name("release-2024.pub"), Ok("release-2024"));
+    assert_eq!(validate_key_name("ops_team.v2"), Ok("ops_team.v2"));
+    for bad in [
+        "",
+        ".pub",
+        "../etc",
+        "a/b",
+        ".hidden",
+        "-rf",
+        "name with space",
+        "ключ",
+    ] {
+        assert!(validate_key_name(bad).is_err(), "{bad:?} accepted");
+    }
+    assert!(validate_key_name(&"k".repeat(65)).is_err());
+
+    // Sidecars written before key metadata existed only carry the scope
+    let legacy: TrustedKeyMeta = serde_json::from_str(r#"{"device_types":["hid"]}"#).unwrap();
+    assert_eq!(legacy.scope.device_types, vec!["hid".to_string()]);
+    assert!(legacy.not_after.is_none());
+
+    let sk = SigningKey::generate(&mut OsRng);
+    let mut key = TrustedKey::new("ops", &sk.verifying_key(), legacy);
+    assert_eq!(key.verifying_key(), Some(sk.verifying_key()));
+    assert!(!key.is_expired(Utc::now()));
+    key.meta.not_after = Some(Utc::now());
+    assert!(key.is_expired(Utc::now()));
+}
+
 #[test]
 fn audit_chain_integrity() {
     let p1 = AuditEntryPayload {
@@ -68,6 +762,7 @@ fn audit_chain_integrity() {
         device_fingerprint: None,
         action: "daemon_start".into(),
         requester_uid: None,
+        signer_key_id: None,
     };
     let e1 = AuditEntry::new(None, p1);
     let p2 = AuditEntryPayload {
@@ -76,6 +771,7 @@ fn audit_chain_integrity() {
         device_fingerprint: Some("sha256:abc".into()),
         action: "allow_ephemeral".into(),
         requester_uid: Some(1000),
+        signer_key_id: None,
     };
     let e2 = AuditEntry::new(Some(e1.entry_hash.clone()), p2);
     let chain = vec![e1.clone(), e2.clone()];
@@ -85,6 +781,158 @@ fn audit_chain_integrity() {
     bad[1].payload.action = "tamper".into();
     assert!(!verify_chain(&bad));
 }
+#[test]
+fn baseline_entries_match_connected_devices() {
+    let entry = |serial: Option<&str>| DeviceEntry {
+        vendor_id: "046d".into(),
+        product_id: "c534".into(),
+        serial: serial.map(Into::into),
+        bus_path: None,
+        descriptors_hash: String::new(),
+        device_type: "hid".into(),
+        comment: None,
+    };
+    let mut device = DeviceInfo {
+        id: "7".into(),
+        vendor_id: "0x046D".into(),
+        product_id: "c534".into(),
+        serial: "ABC".into(),
+        fingerprint: String::new(),
+        device_type: "hid".into(),
+        allowed: false,
+        persistent: false,
+        interfaces: vec!["03:01:02".into()],
+        legacy_fingerprint: String::new(),
+    };
+    assert!(entry(None).matches(&device));
+    assert!(entry(Some("ABC")).matches(&device));
+    assert!(!entry(Some("XYZ")).matches(&device));
+    assert!(!entry(None).is_for(&device));
+
+    let mut baseline = Baseline {
+        version: 1,
+        created_by: "admin".into(),
+        created_at: Utc::now(),
+        devices: vec![entry(Some("XYZ"))],
+        not_before: None,
+        not_after: None,
+        hosts: Vec::new(),
+        sequence: None,
+        key_id: None,
+        signature: None,
+        signatures: Vec::new(),
+    };
+    assert!(baseline.entry_for(&device).is_none());
+    baseline.devices.push(entry(None));
+    assert_eq!(baseline.entry_for(&device), Some(&entry(None)));
+
+    assert!(!device.is_hub());
+    device.interfaces = vec!["09:00:01".into(), "09:00:02".into()];
+    assert!(device.is_hub());
+    device.interfaces.clear();
+    assert!(!device.is_hub());
+}
+
+#[test]
+fn baseline_diff_reports_field_changes() {
+    let entry = |vid: &str, serial: &str, comment: Option<&str>| DeviceEntry {
+        vendor_id: vid.into(),
+        product_id: "0x0001".into(),
+        serial: Some(serial.into()),
+        bus_path: None,
+        descriptors_hash: "sha256:aa".into(),
+        device_type: "hid".into(),
+        comment: comment.map(Into::into),
+    };
+    let baseline = |devices: Vec<DeviceEntry>| Baseline {
+        version: 1,
+        created_by: "admin".into(),
+        created_at: Utc::now(),
+        devices,
+        not_before: None,
+        not_after: None,
+        hosts: Vec::new(),
+        sequence: None,
+        key_id: None,
+        signature: None,
+        signatures: Vec::new(),
+    };
+    let old = baseline(vec![
+        entry("0x1111", "A", None),
+        entry("0x2222", "B", None),
+        entry("0x3333", "C", None),
+    ]);
+    let new = baseline(vec![
+        entry("0x1111", "A", None),
+        entry("0x2222", "B2", Some("replaced")),
+        entry("0x4444", "D", None),
+    ]);
+    let rules = |b: &Baseline| {
+        b.devices
+            .iter()
+            .map(|d| format!("allow {} {:?}\n", d.vendor_id, d.serial))
+            .collect::<String>()
+    };
+    let diff = diff_baselines(&old, &new, rules);
+    assert_eq!(diff.added.len(), 1);
+    assert_eq!(diff.added[0].vendor_id, "0x4444");
+    assert_eq!(diff.removed.len(), 1);
+    assert_eq!(diff.removed[0].vendor_id, "0x3333");
+    assert_eq!(diff.modified.len(), 1);
+    let fields: Vec<&str> = diff.modified[0]
+        .changes
+        .iter()
+        .map(|c| c.field.as_str())
+        .collect();
+    assert_eq!(fields, vec!["serial", "comment"]);
+    assert_eq!(diff.rules_added.len(), 2);
+    assert_eq!(diff.rules_removed.len(), 2);
+
+    assert!(diff_baselines(&old, &old, rules).is_empty());
+}
+
+#[test]
+fn dbus_errors_round_trip_with_exit_codes() {
+    use crate::error::Error;
+    use zbus::DBusError;
+
+    let call = zbus::Message::method("/org/lusby/Daemon", "ApplyPersistentAllow")
+        .unwrap()
+        .build(&())
+        .unwrap();
+    let cases = [
+        (Error::InvalidInput("bad ttl".into()), "InvalidInput", 3),
+        (Error::NotFound("no device".into()), "NotFound", 4),
+        (
+            Error::SignatureInvalid("not signed".into()),
+            "SignatureInvalid",
+            5,
+        ),
+        (Error::PolicyDenied("expired".into()), "PolicyDenied", 6),
+        (Error::NotAuthorized("polkit".into()), "NotAuthorized", 7),
+        (Error::BackendFailed("usbguard".into()), "BackendFailed", 8),
+    ];
+    for (err, name, code) in cases {
+        assert_eq!(err.name().as_str(), format!("org.lusby.Error.{}", name));
+        let reply = err.create_reply(&call.header()).unwrap();
+        // What a client sees: the same variant, message and exit code
+        let received = Error::from(zbus::Error::from(reply));
+        assert_eq!(received.name(), err.name());
+        assert_eq!(received.description(), err.description());
+        assert_eq!(received.to_string(), err.to_string());
+        assert_eq!(received.exit_code(), code);
+    }
+
+    // Names from a newer daemon keep their text and fall back to the transport code
+    let reply = zbus::Message::method_error(&call, "org.lusby.Error.Busy")
+        .unwrap()
+        .build(&("try again",))
+        .unwrap();
+    let unknown = Error::from(zbus::Error::from(reply));
+    assert_eq!(unknown.to_string(), "org.lusby.Error.Busy: try again");
+    assert_eq!(unknown.exit_code(), 9);
+}
+
 #[cfg(test)]
 mod proptests {
     use super::*;
@@ -114,8 +962,34 @@ mod proptests {
             };
             let fp1 = compute_fingerprint(&input);
             let fp2 = compute_fingerprint(&input);
-            prop_assert!(fp1.starts_with("sha256:"));
+            prop_assert!(fp1.starts_with("lfp2:"));
             prop_assert_eq!(fp1, fp2);
+            prop_assert!(compute_legacy_fingerprint(&input).starts_with("sha256:"));
+        }
+
+        #[test]
+        fn descriptor_parsers_survive_arbitrary_bytes(
+            bytes in proptest::collection::vec(any::<u8>(), 0..512)
+        ) {
+            if let Ok(parsed) = parse_descriptors(&bytes) {
+                let _ = render_tree(&parsed, &Default::default());
+            }
+            let _ = parse_hid_report(&bytes);
+            let _ = parse_string_descriptor(&bytes);
+        }
+
+        #[test]
+        fn descriptor_parser_survives_corrupted_devices(
+            cut in 0usize..=75,
+            at in 0usize..75,
+            value in any::<u8>()
+        ) {
+            let mut bytes = keyboard_and_storage_descriptors();
+            bytes[at] = value;
+            bytes.truncate(cut);
+            if let Ok(parsed) = parse_descriptors(&bytes) {
+                let _ = render_tree(&parsed, &Default::default());
+            }
         }
     }
 }
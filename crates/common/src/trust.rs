@@ -0,0 +1,296 @@
+use base64::engine::general_purpose::STANDARD as B64;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use ed25519_dalek::VerifyingKey;
+use serde::{Deserialize, Serialize};
+
+use crate::baseline::KeyScope;
+use crate::crypto::key_id;
+
+const MAX_KEY_NAME_LEN: usize = 64;
+
+/// Check a trusted key name and return it without a `.pub` suffix. Names become file names,
+/// so only ASCII letters, digits, `.`, `_` and `-` are accepted and they may not start with
+/// `.` or `-`.
+pub fn validate_key_name(name: &str) -> Result<&str, String> {
+    let stem = name.strip_suffix(".pub").unwrap_or(name);
+    if stem.is_empty() || stem.len() > MAX_KEY_NAME_LEN {
+        return Err(format!(
+            "key name must be 1 to {} characters",
+            MAX_KEY_NAME_LEN
+        ));
+    }
+    if stem.starts_with('.') || stem.starts_with('-') {
+        return Err("key name may not start with '.' or '-'".into());
+    }
+    if let Some(c) = stem
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-')))
+    {
+        return Err(format!("invalid character {:?} in key name", c));
+    }
+    Ok(stem)
+}
+
+/// Metadata kept next to a trusted key as `<name>.json`. Older sidecars that only hold
+/// `device_types` still parse.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TrustedKeyMeta {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub display_name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub owner: String,
+    #[serde(flatten)]
+    pub scope: KeyScope,
+    /// The key is ignored after this time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub not_after: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub added_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub added_by_uid: Option<u32>,
+    /// Set on the old key while a rotation to the named key is in progress.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotating_to: Option<String>,
+}
+
+/// A trusted key as listed to clients.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TrustedKey {
+    pub name: String,
+    /// Fingerprint, see `crypto::key_id`.
+    pub key_id: String,
+    pub public_key: String, // base64(raw 32 bytes)
+    #[serde(flatten)]
+    pub meta: TrustedKeyMeta,
+}
+
+impl TrustedKey {
+    pub fn new(name: &str, verifying_key: &VerifyingKey, meta: TrustedKeyMeta) -> Self {
+        Self {
+            name: name.to_string(),
+            key_id: key_id(verifying_key),
+            public_key: B64.encode(verifying_key.to_bytes()),
+            meta,
+        }
+    }
+
+    pub fn verifying_key(&self) -> Option<VerifyingKey> {
+        let raw: [u8; 32] = B64.decode(&self.public_key).ok()?.try_into().ok()?;
+        VerifyingKey::from_bytes(&raw).ok()
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.meta.not_after.is_some_and(|t| t <= now)
+    }
+}
+
+/// Which OpenSSH key list a file is in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SshKeyList {
+    /// `[options] keytype base64 [comment]`
+    AuthorizedKeys,
+    /// `principals [options] keytype base64 [comment]`, see ssh-keygen(1) ALLOWED SIGNERS
+    AllowedSigners,
+}
+
+/// An Ed25519 key imported from an OpenSSH key list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SshKeyEntry {
+    /// Suggested trusted key name derived from the principal or comment.
+    pub name: String,
+    pub verifying_key: VerifyingKey,
+    pub comment: String,
+    /// From `valid-before` (allowed_signers) or `expiry-time` (authorized_keys).
+    pub not_after: Option<DateTime<Utc>>,
+}
+
+/// Split on whitespace, keeping double-quoted runs together.
+fn ssh_fields(line: &str) -> Vec<&str> {
+    let mut fields = Vec::new();
+    let mut start = None;
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if let Some(s) = start.take() {
+                    fields.push(&line[s..i]);
+                }
+                continue;
+            }
+            _ => {}
+        }
+        start.get_or_insert(i);
+    }
+    if let Some(s) = start {
+        fields.push(&line[s..]);
+    }
+    fields
+}
+
+/// Split a comma-separated option list into `(name, value)`, unquoting values.
+fn ssh_options(opts: &str) -> Vec<(String, Option<String>)> {
+    let mut out = Vec::new();
+    let mut quoted = false;
+    let mut start = 0;
+    for (i, c) in opts.char_indices().chain([(opts.len(), ',')]) {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                let opt = &opts[start..i];
+                start = i + 1;
+                if opt.is_empty() {
+                    continue;
+                }
+                let (name, value) = match opt.split_once('=') {
+                    Some((n, v)) => (n, Some(v.trim_matches('"').to_string())),
+                    None => (opt, None),
+                };
+                out.push((name.to_ascii_lowercase(), value));
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// OpenSSH timestamps are `YYYYMMDD[HHMM[SS]]` in local time, or UTC with a `Z` suffix.
+fn ssh_time(value: &str) -> Option<DateTime<Utc>> {
+    use chrono::{Local, NaiveDate, NaiveDateTime, TimeZone};
+    let (digits, utc) = match value.strip_suffix(['Z', 'z']) {
+        Some(d) => (d, true),
+        None => (value, false),
+    };
+    let naive = match digits.len() {
+        8 => NaiveDate::parse_from_str(digits, "%Y%m%d")
+            .ok()?
+            .and_hms_opt(0, 0, 0)?,
+        12 => NaiveDateTime::parse_from_str(digits, "%Y%m%d%H%M").ok()?,
+        14 => NaiveDateTime::parse_from_str(digits, "%Y%m%d%H%M%S").ok()?,
+        _ => return None,
+    };
+    if utc {
+        Some(naive.and_utc())
+    } else {
+        Local
+            .from_local_datetime(&naive)
+            .earliest()
+            .map(|t| t.with_timezone(&Utc))
+    }
+}
+
+/// Turn a principal or comment into a valid key name, e.g. `alice@example.com` becomes
+/// `alice-example.com`. Falls back to `ssh-` plus a fingerprint prefix.
+fn ssh_key_name(hint: &str, verifying_key: &VerifyingKey) -> String {
+    let mapped: String = hint
+        .chars()
+        .map(|c| match c {
+            c if c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-') => c,
+            _ => '-',
+        })
+        .collect();
+    let name = mapped.trim_start_matches(['.', '-']);
+    let name: String = name.chars().take(MAX_KEY_NAME_LEN).collect();
+    if validate_key_name(&name).is_ok() {
+        name
+    } else {
+        let id = key_id(verifying_key);
+        format!("ssh-{}", &id["sha256:".len()..][..16])
+    }
+}
+
+/// Key types OpenSSH writes into `authorized_keys` and `allowed_signers`.
+const SSH_KEY_TYPES: &[&str] = &[
+    "ssh-ed25519",
+    "ssh-rsa",
+    "ssh-dss",
+    "ecdsa-sha2-nistp256",
+    "ecdsa-sha2-nistp384",
+    "ecdsa-sha2-nistp521",
+    "sk-ssh-ed25519@openssh.com",
+    "sk-ecdsa-sha2-nistp256@openssh.com",
+    "ssh-ed25519-cert-v01@openssh.com",
+    "ssh-rsa-cert-v01@openssh.com",
+    "ssh-dss-cert-v01@openssh.com",
+    "ecdsa-sha2-nistp256-cert-v01@openssh.com",
+    "ecdsa-sha2-nistp384-cert-v01@openssh.com",
+    "ecdsa-sha2-nistp521-cert-v01@openssh.com",
+    "sk-ssh-ed25519-cert-v01@openssh.com",
+    "sk-ecdsa-sha2-nistp256-cert-v01@openssh.com",
+];
+
+fn parse_ssh_line(line: &str, list: SshKeyList) -> Result<SshKeyEntry, String> {
+    let fields = ssh_fields(line);
+    // allowed_signers always starts with the principals, which may look like a key type
+    // (`ssh-deploy@example.com`); options come next in either format, if present
+    let (principal, rest) = match list {
+        SshKeyList::AuthorizedKeys => (None, &fields[..]),
+        SshKeyList::AllowedSigners => match fields.split_first() {
+            Some((p, rest)) => (Some(*p), rest),
+            None => return Err("malformed line".into()),
+        },
+    };
+    let is_key_type = |f: &&str| SSH_KEY_TYPES.contains(f);
+    let (options, key_at) = match rest {
+        [k, ..] if is_key_type(k) => (None, 0),
+        [opts, k, ..] if is_key_type(k) => (Some(*opts), 1),
+        _ => return Err("no public key found".into()),
+    };
+    let key_at = fields.len() - rest.len() + key_at;
+    if fields[key_at] != "ssh-ed25519" {
+        return Err(format!("unsupported key type {}", fields[key_at]));
+    }
+    let blob = fields
+        .get(key_at + 1)
+        .and_then(|b| B64.decode(b).ok())
+        .ok_or("invalid base64 key")?;
+    let verifying_key =
+        crate::ssh_agent::parse_ed25519_blob(&blob).ok_or("invalid ssh-ed25519 key")?;
+
+    let mut not_after = None;
+    let now = Utc::now();
+    for (name, value) in ssh_options(options.unwrap_or("")) {
+        match (name.as_str(), value) {
+            ("cert-authority", _) => return Err("certificate authorities are not supported".into()),
+            ("namespaces", Some(ns))
+                if !ns.split(',').any(|n| n == crate::crypto::SSHSIG_NAMESPACE) =>
+            {
+                return Err(format!("namespaces {:?} exclude lusby", ns));
+            }
+            ("valid-before" | "expiry-time", Some(t)) => {
+                not_after = Some(ssh_time(&t).ok_or_else(|| format!("invalid time {:?}", t))?);
+            }
+            // Trusted keys have no start time, so a window that has not opened yet is refused
+            ("valid-after", Some(t)) => {
+                let from = ssh_time(&t).ok_or_else(|| format!("invalid time {:?}", t))?;
+                if from > now {
+                    return Err(format!("not valid until {}", from.to_rfc3339()));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let comment = fields[key_at + 2..].join(" ");
+    let hint = principal
+        .map(|p| p.trim_matches('"').split(',').next().unwrap_or(""))
+        .unwrap_or(&comment);
+    Ok(SshKeyEntry {
+        name: ssh_key_name(hint, &verifying_key),
+        verifying_key,
+        comment,
+        not_after,
+    })
+}
+
+/// Parse an `authorized_keys` or `allowed_signers` file. Blank lines and comments are
+/// skipped; every other line yields an entry or a `line N: reason` error.
+pub fn parse_ssh_key_list(text: &str, list: SshKeyList) -> Vec<Result<SshKeyEntry, String>> {
+    text.lines()
+        .enumerate()
+        .map(|(i, l)| (i + 1, l.trim()))
+        .filter(|(_, l)| !l.is_empty() && !l.starts_with('#'))
+        .map(|(n, l)| parse_ssh_line(l, list).map_err(|e| format!("line {}: {}", n, e)))
+        .collect()
+}
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
-use zvariant::Type;
+use zvariant::{OwnedValue, Type, Value};
+
+use crate::descriptors::interface_class_name;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Type)]
 pub struct PolicyStatus {
@@ -16,4 +18,43 @@ pub struct DeviceInfo {
     pub device_type: String,
     pub allowed: bool,
     pub persistent: bool,
+    /// Interface classes as `cc:ss:pp` hex triples, when the backend reports them.
+    #[serde(default)]
+    pub interfaces: Vec<String>,
+    /// The `sha256:` fingerprint used before `lfp2`, so values recorded earlier (e.g. in
+    /// revocation lists) still match; empty if unknown.
+    #[serde(default)]
+    pub legacy_fingerprint: String,
+}
+
+impl DeviceInfo {
+    /// True for hubs: every reported interface has class 09. Unknown layouts are not hubs.
+    pub fn is_hub(&self) -> bool {
+        !self.interfaces.is_empty() && self.interfaces.iter().all(|i| i.starts_with("09:"))
+    }
+
+    /// The interface classes in human terms ("Keyboard", "Mass Storage"), each once, in
+    /// interface order.
+    pub fn interface_classes(&self) -> Vec<&'static str> {
+        let mut names = Vec::new();
+        for name in self
+            .interfaces
+            .iter()
+            .filter_map(|i| interface_class_name(i))
+        {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        names
+    }
+}
+
+/// A temporary approval, as published in the daemon's `ActiveEphemeralGrants` property.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Type, Value, OwnedValue)]
+pub struct EphemeralGrant {
+    pub device_id: String,
+    /// Unix time in seconds when the grant runs out.
+    pub expires_at: u64,
+    pub requester_uid: u32,
 }
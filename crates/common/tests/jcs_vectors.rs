@@ -0,0 +1,72 @@
+//! RFC 8785 conformance. `tests/jcs/input/*.json` canonicalize to the exact bytes in
+//! `tests/jcs/output/`; the expected outputs come from the RFC's reference JavaScript
+//! canonicalizer, so Python and Go signers can check themselves against the same files.
+
+use std::fs;
+use std::path::Path;
+
+use lusby_common::canonical::{format_double, to_jcs_string};
+use lusby_common::crypto::canonical_json_vec;
+use serde_json::{json, Value};
+
+#[test]
+fn vectors_match_reference_output() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/jcs");
+    let mut count = 0;
+    for entry in fs::read_dir(dir.join("input")).unwrap() {
+        let path = entry.unwrap().path();
+        let name = path.file_name().unwrap();
+        let input: Value = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        let expected = fs::read(dir.join("output").join(name)).unwrap();
+        assert_eq!(
+            to_jcs_string(&input).unwrap(),
+            String::from_utf8(expected).unwrap(),
+            "{:?}",
+            name
+        );
+        count += 1;
+    }
+    assert!(count >= 5);
+}
+
+/// RFC 8785 Appendix B: IEEE 754 bit patterns and their serialization.
+#[test]
+fn number_serialization_appendix_b() {
+    for (bits, expected) in [
+        (0x0000000000000000u64, "0"),
+        (0x8000000000000000, "0"),
+        (0x0000000000000001, "5e-324"),
+        (0x8000000000000001, "-5e-324"),
+        (0x7fefffffffffffff, "1.7976931348623157e+308"),
+        (0xffefffffffffffff, "-1.7976931348623157e+308"),
+        (0x4340000000000000, "9007199254740992"),
+        (0xc340000000000000, "-9007199254740992"),
+        (0x4430000000000000, "295147905179352830000"),
+        (0x44b52d02c7e14af5, "9.999999999999997e+22"),
+        (0x44b52d02c7e14af6, "1e+23"),
+        (0x44b52d02c7e14af7, "1.0000000000000001e+23"),
+        (0x444b1ae4d6e2ef4e, "999999999999999700000"),
+        (0x444b1ae4d6e2ef4f, "999999999999999900000"),
+        (0x444b1ae4d6e2ef50, "1e+21"),
+        (0x3eb0c6f7a0b5ed8c, "9.999999999999997e-7"),
+        (0x3eb0c6f7a0b5ed8d, "0.000001"),
+        (0x41b3de4355555553, "333333333.3333332"),
+        (0x41b3de4355555554, "333333333.33333325"),
+        (0x41b3de4355555555, "333333333.3333333"),
+        (0x41b3de4355555556, "333333333.3333334"),
+        (0x41b3de4355555557, "333333333.33333343"),
+        (0xbecbf647612f3696, "-0.0000033333333333333333"),
+        (0x43143ff3c1cb0959, "1424953923781206.2"),
+    ] {
+        assert_eq!(format_double(f64::from_bits(bits)), expected, "{:#x}", bits);
+    }
+}
+
+#[test]
+fn unrepresentable_numbers_are_rejected() {
+    assert!(to_jcs_string(&json!(9007199254740992u64)).is_ok());
+    assert!(to_jcs_string(&json!(9007199254740993u64)).is_err());
+    assert!(to_jcs_string(&json!(-9007199254740993i64)).is_err());
+    // Integers within range stay exact through the serde layer
+    assert_eq!(canonical_json_vec(&42u64).unwrap(), b"42");
+}
@@ -0,0 +1,110 @@
+#![cfg(feature = "pkcs11")]
+
+use chrono::Utc;
+use cryptoki::context::{CInitializeArgs, Pkcs11};
+use cryptoki::mechanism::Mechanism;
+use cryptoki::object::Attribute;
+use cryptoki::session::UserType;
+use cryptoki::types::AuthPin;
+use ed25519_dalek::SigningKey;
+use lusby_common::baseline::Baseline;
+use lusby_common::crypto::{key_id, KeySigner};
+use lusby_common::pkcs11::{Pkcs11Signer, Pkcs11Uri};
+use std::process::Command;
+
+// Requires SoftHSM2 (softhsm2-util on PATH and its module).
+// Enable with: LUSBY_TEST_SOFTHSM=/usr/lib/softhsm/libsofthsm2.so \
+//   cargo test -p lusby-common --features pkcs11 --test pkcs11_softhsm -- --ignored
+
+/// DER-encoded OID 1.3.101.112 (Ed25519) for CKA_EC_PARAMS.
+const ED25519_OID: [u8; 5] = [0x06, 0x03, 0x2b, 0x65, 0x70];
+
+#[test]
+#[ignore]
+fn sign_baseline_with_softhsm_key() {
+    let Ok(module) = std::env::var("LUSBY_TEST_SOFTHSM") else {
+        return;
+    };
+    let dir = tempfile::tempdir().unwrap();
+    let tokens = dir.path().join("tokens");
+    std::fs::create_dir(&tokens).unwrap();
+    let conf = dir.path().join("softhsm2.conf");
+    std::fs::write(
+        &conf,
+        format!("directories.tokendir = {}\n", tokens.display()),
+    )
+    .unwrap();
+    std::env::set_var("SOFTHSM2_CONF", &conf);
+    let status = Command::new("softhsm2-util")
+        .args(["--init-token", "--free", "--label", "lusby"])
+        .args(["--pin", "1234", "--so-pin", "5678"])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    // Generate the key pair on the token, as an administrator would with pkcs11-tool.
+    {
+        let ctx = Pkcs11::new(&module).unwrap();
+        ctx.initialize(CInitializeArgs::OsThreads).unwrap();
+        let slot = ctx.get_slots_with_token().unwrap()[0];
+        let session = ctx.open_rw_session(slot).unwrap();
+        session
+            .login(UserType::User, Some(&AuthPin::new("1234".into())))
+            .unwrap();
+        let label = Attribute::Label(b"baseline".to_vec());
+        session
+            .generate_key_pair(
+                &Mechanism::EccEdwardsKeyPairGen,
+                &[
+                    Attribute::Token(true),
+                    Attribute::EcParams(ED25519_OID.to_vec()),
+                    Attribute::Verify(true),
+                    label.clone(),
+                ],
+                &[
+                    Attribute::Token(true),
+                    Attribute::Private(true),
+                    Attribute::Sensitive(true),
+                    Attribute::Sign(true),
+                    label,
+                ],
+            )
+            .unwrap();
+        ctx.finalize();
+    }
+
+    let uri = Pkcs11Uri::parse(&format!(
+        "pkcs11:token=lusby;object=baseline;type=private?module-path={module}&pin-value=1234"
+    ))
+    .unwrap();
+    let signer = Pkcs11Signer::open(&uri, None).unwrap();
+    let vk = signer.public_key().unwrap();
+
+    let mut baseline = Baseline {
+        version: 1,
+        created_by: "token".into(),
+        created_at: Utc::now(),
+        devices: vec![],
+        not_before: None,
+        not_after: None,
+        hosts: Vec::new(),
+        sequence: Some(1),
+        key_id: None,
+        signature: None,
+        signatures: Vec::new(),
+    };
+    baseline.sign_attach(&signer).unwrap();
+    assert_eq!(baseline.signatures[0].key_id, key_id(&vk));
+    assert!(baseline.verify_signature(&vk).unwrap());
+    assert!(!baseline
+        .verify_signature(&SigningKey::from_bytes(&[1; 32]).verifying_key())
+        .unwrap());
+
+    // The module can only be initialized once per process at a time.
+    drop(signer);
+    let wrong = Pkcs11Uri {
+        object: Some("other".into()),
+        ..uri
+    };
+    assert!(Pkcs11Signer::open(&wrong, None).is_err());
+}
@@ -0,0 +1,81 @@
+//! Pins the canonical signed bytes of every baseline schema version. If one of these tests
+//! fails, existing signatures of that version would no longer verify.
+
+use ed25519_dalek::SigningKey;
+use lusby_common::baseline::Baseline;
+use lusby_common::crypto::{canonical_json_vec, legacy_canonical_json_vec};
+use lusby_common::schema::{signed_content, upgrade, SchemaError, BASELINE_VERSION};
+
+const GOLDEN: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden");
+
+fn golden(name: &str) -> Vec<u8> {
+    std::fs::read(format!("{}/{}", GOLDEN, name)).unwrap()
+}
+
+/// Golden files are signed with this fixed key.
+fn golden_key() -> ed25519_dalek::VerifyingKey {
+    SigningKey::from_bytes(&[1; 32]).verifying_key()
+}
+
+#[test]
+fn canonical_bytes_are_pinned_per_version() {
+    for version in 1..=BASELINE_VERSION {
+        let b = Baseline::from_json(&golden(&format!("baseline-v{}.json", version))).unwrap();
+        assert_eq!(b.version, version);
+        let content = signed_content(&b).unwrap();
+        for (bytes, file) in [
+            (canonical_json_vec(&content).unwrap(), "canonical"),
+            (legacy_canonical_json_vec(&content).unwrap(), "legacy"),
+        ] {
+            assert_eq!(
+                String::from_utf8(bytes).unwrap(),
+                String::from_utf8(golden(&format!("baseline-v{}.{}", version, file))).unwrap(),
+                "{} bytes of schema v{} changed",
+                file,
+                version
+            );
+        }
+        // The golden signatures predate JCS and must keep verifying
+        assert_eq!(b.verify_signature(&golden_key()), Ok(true));
+    }
+}
+
+#[test]
+fn v1_upgrades_to_an_unsigned_current_draft() {
+    let v1 = Baseline::from_json(&golden("baseline-v1.json")).unwrap();
+    let draft = upgrade(&v1).unwrap();
+    assert_eq!(draft.version, BASELINE_VERSION);
+    assert!(draft.signature.is_none() && draft.signatures.is_empty());
+    assert_eq!(draft.devices[0].vendor_id, "0781");
+    assert_eq!(draft.devices[0].product_id, "55ab");
+    assert_eq!(draft.devices[1], v1.devices[1]);
+    assert_eq!(draft.verify_signature(&golden_key()), Ok(false));
+
+    // Upgrading is idempotent and re-parses as the current version
+    let json = serde_json::to_vec(&draft).unwrap();
+    assert_eq!(upgrade(&draft).unwrap(), draft);
+    assert_eq!(Baseline::from_json(&json).unwrap(), draft);
+}
+
+#[test]
+fn unknown_versions_and_fields_are_rejected() {
+    let mut value: serde_json::Value = serde_json::from_slice(&golden("baseline-v2.json")).unwrap();
+    value["version"] = 99.into();
+    let err = Baseline::from_json(value.to_string().as_bytes()).unwrap_err();
+    assert!(matches!(err, SchemaError::UnsupportedVersion { found: 99 }));
+    assert!(err.to_string().contains("upgrade lusby"));
+
+    value["version"] = BASELINE_VERSION.into();
+    value["devices"][0]["interfaces"] = "08:06:50".into();
+    let err = Baseline::from_json(value.to_string().as_bytes()).unwrap_err();
+    assert!(
+        err.to_string().contains("unknown field `interfaces`"),
+        "{err}"
+    );
+
+    value.as_object_mut().unwrap().remove("version");
+    assert!(matches!(
+        Baseline::from_json(value.to_string().as_bytes()),
+        Err(SchemaError::MissingVersion)
+    ));
+}
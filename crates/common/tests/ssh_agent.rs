@@ -0,0 +1,111 @@
+//! Signs a baseline through a minimal ssh-agent served from a temporary socket.
+
+use std::io::{Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::thread;
+
+use chrono::Utc;
+use ed25519_dalek::{Signer, SigningKey};
+use lusby_common::baseline::Baseline;
+use lusby_common::crypto::{key_id, SignatureFormat};
+use lusby_common::ssh_agent::{ed25519_key_blob, list_identities, AgentSigner};
+
+fn put_string(buf: &mut Vec<u8>, data: &[u8]) {
+    buf.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    buf.extend_from_slice(data);
+}
+
+fn read_string(data: &mut &[u8]) -> Vec<u8> {
+    let len = u32::from_be_bytes(data[..4].try_into().unwrap()) as usize;
+    let s = data[4..4 + len].to_vec();
+    *data = &data[4 + len..];
+    s
+}
+
+/// Answer one request per connection the way OpenSSH's agent does.
+fn serve(mut stream: UnixStream, keys: &[(SigningKey, &str)]) {
+    let mut len = [0u8; 4];
+    stream.read_exact(&mut len).unwrap();
+    let mut msg = vec![0u8; u32::from_be_bytes(len) as usize];
+    stream.read_exact(&mut msg).unwrap();
+
+    let mut reply = Vec::new();
+    match msg[0] {
+        11 => {
+            reply.push(12);
+            reply.extend_from_slice(&(keys.len() as u32 + 1).to_be_bytes());
+            // Non-Ed25519 identities must be skipped by the client
+            put_string(&mut reply, b"\0\0\0\x07ssh-rsa");
+            put_string(&mut reply, b"rsa key");
+            for (sk, comment) in keys {
+                put_string(&mut reply, &ed25519_key_blob(&sk.verifying_key()));
+                put_string(&mut reply, comment.as_bytes());
+            }
+        }
+        13 => {
+            let mut body = &msg[1..];
+            let blob = read_string(&mut body);
+            let data = read_string(&mut body);
+            match keys
+                .iter()
+                .find(|(sk, _)| ed25519_key_blob(&sk.verifying_key()) == blob)
+            {
+                Some((sk, _)) => {
+                    let mut sig = Vec::new();
+                    put_string(&mut sig, b"ssh-ed25519");
+                    put_string(&mut sig, &sk.sign(&data).to_bytes());
+                    reply.push(14);
+                    put_string(&mut reply, &sig);
+                }
+                None => reply.push(5),
+            }
+        }
+        _ => reply.push(5),
+    }
+    let mut framed = (reply.len() as u32).to_be_bytes().to_vec();
+    framed.extend_from_slice(&reply);
+    stream.write_all(&framed).unwrap();
+}
+
+#[test]
+fn baseline_signed_through_agent() {
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("agent.sock");
+    let listener = UnixListener::bind(&socket).unwrap();
+    let alice = SigningKey::from_bytes(&[7; 32]);
+    let bob = SigningKey::from_bytes(&[9; 32]);
+    let keys = [(alice.clone(), "alice@laptop"), (bob.clone(), "bob@desk")];
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            serve(stream.unwrap(), &keys);
+        }
+    });
+
+    assert_eq!(list_identities(&socket).unwrap().len(), 2);
+    // Two Ed25519 keys: the caller has to pick one
+    assert!(AgentSigner::connect(socket.clone(), None).is_err());
+    assert!(AgentSigner::connect(socket.clone(), Some("nobody")).is_err());
+    let signer = AgentSigner::connect(socket.clone(), Some("bob@desk")).unwrap();
+    assert_eq!(signer.identity().key, bob.verifying_key());
+
+    let mut baseline = Baseline {
+        version: 1,
+        created_by: "admin".into(),
+        created_at: Utc::now(),
+        devices: vec![],
+        not_before: None,
+        not_after: None,
+        hosts: Vec::new(),
+        sequence: Some(1),
+        key_id: None,
+        signature: None,
+        signatures: Vec::new(),
+    };
+    baseline
+        .sign_attach_as(&signer, SignatureFormat::Sshsig)
+        .unwrap();
+    assert_eq!(
+        baseline.verified_key_ids(&[alice.verifying_key(), bob.verifying_key()]),
+        vec![key_id(&bob.verifying_key())]
+    );
+}
@@ -42,6 +42,18 @@ impl AuditLogger {
         device_fingerprint: Option<String>,
         action: &str,
         requester_uid: Option<u32>,
+    ) {
+        self.log_signed(event_type, device_fingerprint, action, requester_uid, None)
+    }
+
+    /// Like `log`, additionally recording the key id of the signer involved.
+    pub fn log_signed(
+        &mut self,
+        event_type: &str,
+        device_fingerprint: Option<String>,
+        action: &str,
+        requester_uid: Option<u32>,
+        signer_key_id: Option<String>,
     ) {
         let payload = AuditEntryPayload {
             timestamp: Utc::now(),
@@ -49,6 +61,7 @@ impl AuditLogger {
             device_fingerprint,
             action: action.into(),
             requester_uid,
+            signer_key_id,
         };
         let prev = self.last_hash.clone();
         let entry = AuditEntry::new(prev, payload);
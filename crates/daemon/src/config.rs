@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use lusby_common::baseline::Baseline;
+use serde::Deserialize;
+
+pub const DEFAULT_CONFIG_PATH: &str = "/etc/lusby/config.toml";
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PolicyConfig {
+    #[serde(default = "default_deny_unknown")]
+    pub deny_unknown: bool,
+    /// Distinct trusted signers required per device type; `"*"` applies to every type.
+    #[serde(default)]
+    pub required_signatures: HashMap<String, usize>,
+    /// How often expired baselines are swept out of the active set.
+    #[serde(default = "default_sweep_secs")]
+    pub baseline_sweep_secs: u64,
+    /// Distinct unscoped trusted signers a revocation list needs; by default the strictest
+    /// baseline threshold, since a list can revoke any device or key.
+    #[serde(default)]
+    pub revocation_signatures: Option<usize>,
+}
+
+impl Default for PolicyConfig {
+    fn default() -> Self {
+        Self {
+            deny_unknown: default_deny_unknown(),
+            required_signatures: HashMap::new(),
+            baseline_sweep_secs: default_sweep_secs(),
+            revocation_signatures: None,
+        }
+    }
+}
+
+impl PolicyConfig {
+    /// Signatures a baseline needs: the strictest requirement over its device types.
+    pub fn required_signatures_for(&self, baseline: &Baseline) -> usize {
+        let wildcard = self.required_signatures.get("*").copied().unwrap_or(1);
+        baseline
+            .devices
+            .iter()
+            .map(|d| {
+                self.required_signatures
+                    .get(&d.device_type)
+                    .copied()
+                    .unwrap_or(wildcard)
+            })
+            .chain(std::iter::once(wildcard))
+            .max()
+            .unwrap_or(1)
+            .max(1)
+    }
+
+    /// Signatures a revocation list needs.
+    pub fn required_revocation_signatures(&self) -> usize {
+        self.revocation_signatures
+            .unwrap_or_else(|| {
+                self.required_signatures
+                    .values()
+                    .copied()
+                    .max()
+                    .unwrap_or(1)
+            })
+            .max(1)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PathsConfig {
+    #[serde(default = "default_baselines")]
+    pub baselines_system: PathBuf,
+    #[serde(default = "default_trusted_pubkeys")]
+    pub trusted_pubkeys: PathBuf,
+    #[serde(default = "default_audit_log")]
+    pub audit_log: PathBuf,
+    #[serde(default = "default_state_dir")]
+    pub state_dir: PathBuf,
+}
+
+impl Default for PathsConfig {
+    fn default() -> Self {
+        Self {
+            baselines_system: default_baselines(),
+            trusted_pubkeys: default_trusted_pubkeys(),
+            audit_log: default_audit_log(),
+            state_dir: default_state_dir(),
+        }
+    }
+}
+
+/// Which `UsbBackend` enforces policy.
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    #[default]
+    Usbguard,
+    /// The kernel's authorization attributes in sysfs, for systems without usbguard.
+    Sysfs,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BackendConfig {
+    #[serde(default)]
+    pub kind: BackendKind,
+    /// Where sysfs is mounted, for the sysfs backend.
+    #[serde(default = "default_sysfs_root")]
+    pub sysfs_root: PathBuf,
+}
+
+impl Default for BackendConfig {
+    fn default() -> Self {
+        Self {
+            kind: BackendKind::default(),
+            sysfs_root: default_sysfs_root(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DaemonConfig {
+    #[serde(default)]
+    pub policy: PolicyConfig,
+    #[serde(default)]
+    pub paths: PathsConfig,
+    #[serde(default)]
+    pub backend: BackendConfig,
+}
+
+impl DaemonConfig {
+    /// Load the config file, or defaults if there is none. A file that cannot be read or
+    /// parsed is an error: falling back would silently drop settings such as signature
+    /// thresholds.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e).with_context(|| format!("reading {}", path.display())),
+        };
+        toml::from_str(&text).with_context(|| format!("parsing {}", path.display()))
+    }
+}
+
+fn default_deny_unknown() -> bool {
+    true
+}
+fn default_sweep_secs() -> u64 {
+    60
+}
+fn default_baselines() -> PathBuf {
+    PathBuf::from("/etc/lusby/baselines")
+}
+fn default_trusted_pubkeys() -> PathBuf {
+    PathBuf::from("/etc/lusby/trusted_pubkeys")
+}
+fn default_audit_log() -> PathBuf {
+    PathBuf::from("/var/log/lusby/audit.log")
+}
+fn default_state_dir() -> PathBuf {
+    PathBuf::from("/var/lib/lusby")
+}
+fn default_sysfs_root() -> PathBuf {
+    PathBuf::from("/sys")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_packaged_config() {
+        let text = include_str!("../../../packaging/config/config.toml");
+        let cfg: DaemonConfig = toml::from_str(text).expect("packaged config parses");
+        assert!(cfg.policy.deny_unknown);
+        assert_eq!(
+            cfg.paths.audit_log,
+            PathBuf::from("/var/log/lusby/audit.log")
+        );
+        assert_eq!(cfg.policy.required_signatures.get("*"), Some(&1));
+        assert_eq!(cfg.backend.kind, BackendKind::Usbguard);
+    }
+
+    #[test]
+    fn selects_the_sysfs_backend() {
+        let cfg: DaemonConfig =
+            toml::from_str("[backend]\nkind = \"sysfs\"\nsysfs_root = \"/tmp/sys\"\n").unwrap();
+        assert_eq!(cfg.backend.kind, BackendKind::Sysfs);
+        assert_eq!(cfg.backend.sysfs_root, PathBuf::from("/tmp/sys"));
+        assert!(toml::from_str::<DaemonConfig>("[backend]\nkind = \"udev\"\n").is_err());
+    }
+
+    #[test]
+    fn only_a_missing_file_falls_back_to_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let cfg = DaemonConfig::load(&path).unwrap();
+        assert!(cfg.policy.required_signatures.is_empty());
+
+        std::fs::write(&path, "[policy.required_signatures]\n\"*\" = 2\n").unwrap();
+        let cfg = DaemonConfig::load(&path).unwrap();
+        assert_eq!(cfg.policy.required_signatures.get("*"), Some(&2));
+
+        // A typo must not quietly reset the threshold to one signer
+        std::fs::write(&path, "[policy.required_signatures]\n\"*\" = 2x\n").unwrap();
+        assert!(DaemonConfig::load(&path).is_err());
+    }
+
+    #[test]
+    fn revocation_lists_need_the_strictest_threshold() {
+        let cfg: DaemonConfig =
+            toml::from_str("[policy.required_signatures]\n\"*\" = 1\nstorage = 3\n").unwrap();
+        assert_eq!(cfg.policy.required_revocation_signatures(), 3);
+        let cfg: DaemonConfig = toml::from_str(
+            "[policy]\nrevocation_signatures = 2\n[policy.required_signatures]\nstorage = 3\n",
+        )
+        .unwrap();
+        assert_eq!(cfg.policy.required_revocation_signatures(), 2);
+        assert_eq!(
+            DaemonConfig::default()
+                .policy
+                .required_revocation_signatures(),
+            1
+        );
+    }
+}
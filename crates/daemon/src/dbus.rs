@@ -1,30 +1,51 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::fs;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
-use std::time::{Duration, Instant};
 
 use base64::Engine as _;
 use ed25519_dalek::VerifyingKey;
 use zbus::message::Header;
 use zbus::{interface, Connection, SignalContext};
 
-use lusby_backend_usbguard::UsbguardBackend;
-use lusby_common::backend::UsbBackend;
-use lusby_common::baseline::Baseline;
-use lusby_common::types::{DeviceInfo, PolicyStatus};
+use futures_util::StreamExt;
+use lusby_backend_usbguard::{generate_block_rules, generate_rules_from_baseline};
+use lusby_common::backend::{DeviceEvent, DeviceEventStream, UsbBackend};
+use lusby_common::baseline::{
+    describe_issues, diff_baselines, lint_baseline, Baseline, DeviceEntry, HostIdentity, KeyScope,
+};
+use lusby_common::crypto::key_id;
+use lusby_common::error::Error;
+use lusby_common::revocation::RevocationList;
+use lusby_common::schema::BASELINE_VERSION;
+use lusby_common::trust::{TrustedKey, TrustedKeyMeta};
+use lusby_common::types::{DeviceInfo, EphemeralGrant, PolicyStatus};
 
 use crate::audit::AuditLogger;
-use crate::polkit::check_manage_authorization;
+use crate::config::{DaemonConfig, PolicyConfig};
+use crate::devices::{device_path, DeviceObject};
+use crate::keystore::KeyStore;
+use crate::polkit::{require_authorization, sender_uid, MANAGE_ACTION, ROLLBACK_ACTION};
+use crate::sequence::SequenceStore;
+
+/// Object path of the `org.lusby.Daemon` interface.
+pub const OBJECT_PATH: &str = "/org/lusby/Daemon";
 
 #[derive(Clone)]
 pub struct DaemonState {
     inner: Arc<Mutex<StateInner>>,
     backend: Arc<dyn UsbBackend>,
     audit: Arc<Mutex<AuditLogger>>,
+    policy: PolicyConfig,
+    host: HostIdentity,
+    sequences: Arc<SequenceStore>,
     baselines_dir: PathBuf,
-    trusted_pubkeys_dir: PathBuf,
+    keys: Arc<KeyStore>,
+    revocation_path: PathBuf,
+    draft_path: PathBuf,
+    /// Ids of devices exported under `DEVICES_PATH`; also serializes `sync_device_objects`.
+    device_objects: Arc<tokio::sync::Mutex<BTreeSet<String>>>,
 }
 
 impl DaemonState {
@@ -53,138 +74,602 @@ impl DaemonState {
         }
         self.inner.lock().unwrap().ephemeral.clear();
     }
-}
-
-fn sanitize_rule_string(s: &str) -> String {
-    s.chars()
-        .map(|c| if c.is_control() { '?' } else { c })
-        .collect()
-}
 
-fn generate_rules_from_baseline(b: &Baseline) -> String {
-    // Very conservative rule builder: allow by vid:pid and optional serial.
-    // Example: "allow id 046d:c534 serial \"ABC\" with-interface *:*:*".
-    let mut out = String::new();
-    for d in &b.devices {
-        // Sanitize IDs
-        let vid = sanitize_rule_string(&d.vendor_id)
-            .trim_start_matches("0x")
-            .to_lowercase();
-        let pid = sanitize_rule_string(&d.product_id)
-            .trim_start_matches("0x")
-            .to_lowercase();
-        let id = format!("{}:{}", vid, pid);
-
-        if let Some(serial) = &d.serial {
-            // Sanitize serial: reject control chars including newlines
-            let sanitized_serial = sanitize_rule_string(serial);
-            out.push_str(&format!(
-                "allow id {} serial \"{}\"\n",
-                id,
-                sanitized_serial.replace('"', "\\\"")
-            ));
-        } else {
-            out.push_str(&format!("allow id {}\n", id));
+    /// Revoke devices whose grant ran out; backends may not enforce the TTL themselves.
+    /// Grants are dropped only once revoked, so a failed revoke is retried on the next sweep.
+    pub async fn sweep_expired_grants(&self) {
+        let now = unix_now();
+        let expired: Vec<String> = self
+            .inner
+            .lock()
+            .unwrap()
+            .ephemeral
+            .values()
+            .filter(|g| g.expires_at <= now)
+            .map(|g| g.device_id.clone())
+            .collect();
+        for id in expired {
+            let subject = self.audit_subject(&id).await;
+            let ok = self.backend.revoke(&id).await;
+            self.audit.lock().unwrap().log(
+                "ephemeral_expire",
+                Some(subject),
+                if ok { "revoke_ok" } else { "revoke_fail" },
+                None,
+            );
+            if ok {
+                self.inner.lock().unwrap().ephemeral.remove(&id);
+            }
         }
     }
-    out
-}
-
-#[derive(Default)]
-struct StateInner {
-    deny_unknown: bool,
-    ephemeral: HashMap<String, Instant>,
-}
 
-impl DaemonState {
-    pub fn new<B>(backend: B) -> Self
-    where
-        B: UsbBackend + 'static,
-    {
-        Self::new_with_audit_path(backend, PathBuf::from("/var/log/lusby/audit.log"))
+    /// Unexpired ephemeral grants, ordered by device id.
+    fn active_grants(&self) -> Vec<EphemeralGrant> {
+        let now = unix_now();
+        let inner = self.inner.lock().unwrap();
+        let mut grants: Vec<EphemeralGrant> = inner
+            .ephemeral
+            .values()
+            .filter(|g| g.expires_at > now)
+            .cloned()
+            .collect();
+        grants.sort_by(|a, b| a.device_id.cmp(&b.device_id));
+        grants
     }
 
-    pub fn new_with_audit_path<B>(backend: B, audit_path: PathBuf) -> Self
-    where
-        B: UsbBackend + 'static,
-    {
-        let audit = AuditLogger::new(audit_path).expect("init audit");
-        Self {
-            inner: Arc::new(Mutex::new(StateInner {
-                deny_unknown: true,
-                ephemeral: HashMap::new(),
-            })),
-            backend: Arc::new(backend),
-            audit: Arc::new(Mutex::new(audit)),
-            baselines_dir: PathBuf::from("/etc/lusby/baselines"),
-            trusted_pubkeys_dir: PathBuf::from("/etc/lusby/trusted_pubkeys"),
+    /// Emit `PropertiesChanged` for every property whose value moved since the last call.
+    ///
+    /// Called after each state change and from the periodic sweep, which also catches grants
+    /// that ran out and the backend going up or down.
+    pub async fn publish_changes(&self, ctxt: &SignalContext<'_>) -> zbus::Result<()> {
+        let healthy = self.backend.healthy().await && self.revocation_list().is_ok();
+        let current = Published {
+            grants: self.active_grants(),
+            baseline_count: self.enforced_baselines().len() as u32,
+            backend_healthy: healthy,
+        };
+        let previous = {
+            let mut inner = self.inner.lock().unwrap();
+            inner.backend_healthy = healthy;
+            inner.published.replace(current.clone())
+        };
+        let previous = previous.as_ref();
+        if previous.map(|p| &p.grants) != Some(&current.grants) {
+            self.active_ephemeral_grants_changed(ctxt).await?;
+        }
+        if previous.map(|p| p.baseline_count) != Some(current.baseline_count) {
+            self.baseline_count_changed(ctxt).await?;
+        }
+        if previous.map(|p| p.backend_healthy) != Some(current.backend_healthy) {
+            self.backend_healthy_changed(ctxt).await?;
         }
+        Ok(())
     }
-}
 
-#[interface(name = "org.lusby.Daemon")]
-impl DaemonState {
-    async fn get_policy_status(&self) -> PolicyStatus {
-        let deny: bool = self.inner.lock().unwrap().deny_unknown;
-        PolicyStatus { deny_unknown: deny }
+    /// Reject ids that cannot come from a backend, auditing `<event>_reject`.
+    fn check_device_id(&self, device_id: &str, event: &str, uid: Option<u32>) -> Result<(), Error> {
+        if valid_device_id(device_id) {
+            return Ok(());
+        }
+        self.audit.lock().unwrap().log(
+            &format!("{}_reject", event),
+            Some(device_id.to_string()),
+            "invalid_input",
+            uid,
+        );
+        Err(Error::InvalidInput(format!(
+            "invalid device id {:?}",
+            device_id
+        )))
     }
 
-    async fn list_devices(&self) -> Vec<DeviceInfo> {
-        self.backend.list_devices().await
+    /// What the audit log records for a device id: see `device_subject`.
+    async fn audit_subject(&self, device_id: &str) -> String {
+        match self.backend.get_device(device_id).await {
+            Some(device) => device_subject(&device),
+            None => device_id.to_string(),
+        }
     }
 
-    async fn request_ephemeral_allow(&self, device_id: &str, ttl: u32, requester_uid: u32) -> bool {
-        // Eingabevalidierung
-        let valid_id: bool = !device_id.is_empty() && device_id.len() <= 64 && device_id.is_ascii();
-        let valid_ttl: bool = (1..=86400).contains(&ttl);
-        let valid_uid: bool = requester_uid > 0;
-        if !valid_id || !valid_ttl || !valid_uid {
+    /// Allow a device for `ttl` seconds and record the grant. Callers publish the change.
+    pub async fn grant_ephemeral(
+        &self,
+        device_id: &str,
+        ttl: u32,
+        requester_uid: u32,
+    ) -> Result<(), Error> {
+        self.check_device_id(device_id, "ephemeral_allow", Some(requester_uid))?;
+        if !(1..=86400).contains(&ttl) {
             self.audit.lock().unwrap().log(
                 "ephemeral_allow_reject",
                 Some(device_id.to_string()),
                 "invalid_input",
                 Some(requester_uid),
             );
-            return false;
+            return Err(Error::InvalidInput(format!(
+                "ttl {} is outside 1..=86400 seconds",
+                ttl
+            )));
+        }
+        // The revocation list outranks every approval, temporary ones included
+        let revocations = match self.revocation_list() {
+            Ok(list) => list,
+            Err(e) => {
+                self.audit.lock().unwrap().log(
+                    "ephemeral_allow_reject",
+                    Some(device_id.to_string()),
+                    "revocation_list_unreadable",
+                    Some(requester_uid),
+                );
+                return Err(e);
+            }
+        };
+        if let Some(device) = self.backend.get_device(device_id).await {
+            if revocations.as_ref().is_some_and(|l| l.blocks(&device)) {
+                self.audit.lock().unwrap().log(
+                    "ephemeral_allow_reject",
+                    Some(device_subject(&device)),
+                    "revoked",
+                    Some(requester_uid),
+                );
+                return Err(Error::PolicyDenied(format!("{} is revoked", device_id)));
+            }
         }
+        let subject = self.audit_subject(device_id).await;
         let ok: bool = self.backend.allow_ephemeral(device_id, ttl).await;
         self.audit.lock().unwrap().log(
             "ephemeral_allow",
-            Some(device_id.to_string()),
+            Some(subject),
             if ok { "allow_ok" } else { "allow_fail" },
             Some(requester_uid),
         );
-        if ok {
-            let expiry: Instant = Instant::now() + Duration::from_secs(ttl as u64);
-            self.inner
-                .lock()
-                .unwrap()
-                .ephemeral
-                .insert(device_id.to_string(), expiry);
+        if !ok {
+            return Err(Error::BackendFailed(format!(
+                "could not allow {}",
+                device_id
+            )));
         }
-        ok
+        let grant = EphemeralGrant {
+            device_id: device_id.to_string(),
+            expires_at: unix_now() + ttl as u64,
+            requester_uid,
+        };
+        self.inner
+            .lock()
+            .unwrap()
+            .ephemeral
+            .insert(device_id.to_string(), grant);
+        Ok(())
     }
 
-    async fn apply_persistent_allow(
+    /// Reject (logically remove) a device and drop its grant.
+    pub(crate) async fn reject_device(&self, device_id: &str) -> Result<(), Error> {
+        self.check_device_id(device_id, "revoke", None)?;
+        let subject = self.audit_subject(device_id).await;
+        let ok = self.backend.revoke(device_id).await;
+        self.audit.lock().unwrap().log(
+            "revoke",
+            Some(subject),
+            if ok { "revoke_ok" } else { "revoke_fail" },
+            None,
+        );
+        if !ok {
+            return Err(Error::BackendFailed(format!(
+                "could not revoke {}",
+                device_id
+            )));
+        }
+        self.inner.lock().unwrap().ephemeral.remove(device_id);
+        Ok(())
+    }
+
+    /// Deauthorize a device but keep it connected, and drop its grant.
+    pub(crate) async fn block_device(
         &self,
-        _baseline_path: &str,
-        _signer_id: &str,
-        #[zbus(connection)] conn: &Connection,
-        #[zbus(header)] header: Header<'_>,
-    ) -> bool {
-        // Polkit authorization
-        if !check_manage_authorization(conn, &header)
+        device_id: &str,
+        uid: Option<u32>,
+    ) -> Result<(), Error> {
+        self.check_device_id(device_id, "block", uid)?;
+        let subject = self.audit_subject(device_id).await;
+        let ok = self.backend.block(device_id).await;
+        self.audit.lock().unwrap().log(
+            "block",
+            Some(subject),
+            if ok { "block_ok" } else { "block_fail" },
+            uid,
+        );
+        if !ok {
+            return Err(Error::BackendFailed(format!(
+                "could not block {}",
+                device_id
+            )));
+        }
+        self.inner.lock().unwrap().ephemeral.remove(device_id);
+        Ok(())
+    }
+
+    /// The unexpired grant for a device, if any.
+    pub(crate) fn grant_for(&self, device_id: &str) -> Option<EphemeralGrant> {
+        self.active_grants()
+            .into_iter()
+            .find(|g| g.device_id == device_id)
+    }
+
+    /// Add a device to the unsigned draft baseline in the state directory, creating it if
+    /// needed. Devices already in the draft are not added twice.
+    pub(crate) fn add_to_baseline_draft(
+        &self,
+        device: &DeviceInfo,
+        uid: Option<u32>,
+    ) -> Result<PathBuf, Error> {
+        let mut draft = fs::read(&self.draft_path)
+            .ok()
+            .and_then(|data| Baseline::from_json(&data).ok())
+            .unwrap_or_else(|| Baseline {
+                version: BASELINE_VERSION,
+                created_by: "lusby-daemon".into(),
+                created_at: chrono::Utc::now(),
+                devices: Vec::new(),
+                not_before: None,
+                not_after: None,
+                hosts: Vec::new(),
+                sequence: None,
+                key_id: None,
+                signature: None,
+                signatures: Vec::new(),
+            });
+        if !draft.devices.iter().any(|e| e.is_for(device)) {
+            draft.devices.push(DeviceEntry::from_device_info(device));
+        }
+        let write = || -> std::io::Result<()> {
+            if let Some(dir) = self.draft_path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let tmp = self.draft_path.with_extension("json.tmp");
+            fs::write(&tmp, serde_json::to_vec_pretty(&draft)?)?;
+            fs::rename(&tmp, &self.draft_path)
+        };
+        let result = write();
+        self.audit.lock().unwrap().log(
+            "baseline_draft",
+            Some(device.id.clone()),
+            if result.is_ok() {
+                "draft_add_ok"
+            } else {
+                "draft_add_fail"
+            },
+            uid,
+        );
+        result.map_err(|e| {
+            Error::BackendFailed(format!("cannot write {}: {}", self.draft_path.display(), e))
+        })?;
+        Ok(self.draft_path.clone())
+    }
+
+    /// Export one `org.lusby.Device` object per connected device under `DEVICES_PATH`, and
+    /// remove the objects of devices that went away. Objects of remaining devices pick up
+    /// state changes reported by the backend.
+    pub async fn sync_device_objects(&self, conn: &Connection) -> zbus::Result<()> {
+        let mut exported = self.device_objects.lock().await;
+        let server = conn.object_server();
+        let mut current = BTreeSet::new();
+        for device in self.backend.list_devices().await {
+            if !valid_device_id(&device.id) || !current.insert(device.id.clone()) {
+                continue;
+            }
+            let path = device_path(&device.id);
+            if exported.contains(&device.id) {
+                let iface = server.interface::<_, DeviceObject>(&path).await?;
+                let changed = iface.get_mut().await.update(device);
+                if changed {
+                    iface
+                        .get()
+                        .await
+                        .state_changed(iface.signal_context())
+                        .await?;
+                }
+            } else {
+                server
+                    .at(&path, DeviceObject::new(self.clone(), device))
+                    .await?;
+            }
+        }
+        for id in exported.difference(&current) {
+            server.remove::<DeviceObject, _>(device_path(id)).await?;
+        }
+        *exported = current;
+        Ok(())
+    }
+
+    /// The backend's event stream, if it has one.
+    pub fn device_events(&self) -> Option<DeviceEventStream> {
+        self.backend.events()
+    }
+
+    #[cfg_attr(not(feature = "udev-monitor"), allow(dead_code))]
+    pub(crate) fn backend(&self) -> Arc<dyn UsbBackend> {
+        self.backend.clone()
+    }
+
+    /// Turn backend events into device objects, signals and property changes until the
+    /// stream ends. This is the only source of `UnknownDeviceInserted` and `DeviceRemoved`.
+    pub async fn run_device_events(&self, conn: &Connection, mut events: DeviceEventStream) {
+        while let Some(event) = events.next().await {
+            let result = match &event {
+                DeviceEvent::Inserted(device) => self.handle_device_added(conn, device).await,
+                DeviceEvent::Removed { device_id } => {
+                    self.handle_device_removed(conn, device_id).await
+                }
+                DeviceEvent::PolicyChanged(_) => self.sync_device_objects(conn).await,
+            };
+            if let Err(e) = result {
+                tracing::warn!(error=?e, ?event, "cannot publish device event");
+            }
+        }
+    }
+
+    /// The enforced baseline entry that admits `device`, if any; never for revoked devices.
+    pub fn baseline_entry_for(&self, device: &DeviceInfo) -> Option<DeviceEntry> {
+        if self.is_revoked(device) {
+            return None;
+        }
+        self.enforced_baselines()
+            .iter()
+            .find_map(|b| b.entry_for(device).cloned())
+    }
+
+    /// A device was plugged in: export its object, then announce it. Revoked devices are
+    /// removed before anything is announced. Devices matching an enforced baseline are
+    /// announced as known together with the entry; hubs and devices the backend already
+    /// allows for another reason are not announced, so only blocked, unmatched devices reach
+    /// users as unknown.
+    pub async fn handle_device_added(
+        &self,
+        conn: &Connection,
+        device: &DeviceInfo,
+    ) -> zbus::Result<()> {
+        // The backend may have applied its policy since the event was raised
+        let device = self
+            .backend
+            .get_device(&device.id)
             .await
-            .unwrap_or(false)
+            .unwrap_or_else(|| device.clone());
+        // Generated rules only cover VID:PID[:serial], so fingerprints are enforced here
+        if let Some(list) = self
+            .revocation_list()
+            .ok()
+            .flatten()
+            .filter(|l| l.blocks(&device))
         {
-            self.audit
-                .lock()
-                .unwrap()
-                .log("policy_denied", None, "polkit_denied", None);
-            return false;
+            self.revoke_banned(&list, &device).await;
+            return self.sync_device_objects(conn).await;
+        }
+        self.sync_device_objects(conn).await?;
+        let ctxt = SignalContext::new(conn, OBJECT_PATH)?;
+        if let Some(entry) = self.baseline_entry_for(&device) {
+            let entry = serde_json::to_string(&entry).unwrap_or_default();
+            return Self::known_device_inserted(&ctxt, &device, &entry).await;
+        }
+        if device.is_hub() || device.allowed {
+            return Ok(());
+        }
+        Self::unknown_device_inserted(&ctxt, &device).await
+    }
+
+    /// A device went away: drop its object and any ephemeral grant, then announce it.
+    pub async fn handle_device_removed(
+        &self,
+        conn: &Connection,
+        device_id: &str,
+    ) -> zbus::Result<()> {
+        self.sync_device_objects(conn).await?;
+        self.inner.lock().unwrap().ephemeral.remove(device_id);
+        let ctxt = SignalContext::new(conn, OBJECT_PATH)?;
+        Self::device_removed(&ctxt, device_id).await?;
+        self.publish_changes(&ctxt).await
+    }
+
+    /// All baselines currently installed in the baselines directory.
+    pub fn active_baselines(&self) -> Vec<Baseline> {
+        let mut out = Vec::new();
+        if let Ok(entries) = fs::read_dir(&self.baselines_dir) {
+            let mut paths: Vec<PathBuf> = entries
+                .flatten()
+                .map(|e| e.path())
+                .filter(|p| p.extension().and_then(|s| s.to_str()) == Some("json"))
+                .collect();
+            paths.sort();
+            for p in paths {
+                if let Ok(data) = fs::read(&p) {
+                    if let Ok(b) = Baseline::from_json(&data) {
+                        out.push(b);
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    /// Trusted keys that are neither expired nor revoked by the revocation list; none while
+    /// the list cannot be read.
+    fn usable_keys(&self) -> Vec<TrustedKey> {
+        let now = chrono::Utc::now();
+        let Ok(revocations) = self.revocation_list() else {
+            return Vec::new();
+        };
+        self.keys
+            .list()
+            .into_iter()
+            .filter(|k| !k.is_expired(now))
+            .filter(|k| {
+                revocations
+                    .as_ref()
+                    .is_none_or(|r| !r.is_key_revoked(&k.key_id))
+            })
+            .collect()
+    }
+
+    /// All usable trusted keys with their scopes.
+    fn trusted_keys(&self) -> Vec<(VerifyingKey, KeyScope)> {
+        self.usable_keys()
+            .into_iter()
+            .filter_map(|k| Some((k.verifying_key()?, k.meta.scope)))
+            .collect()
+    }
+
+    /// The currently applied revocation list, if any. Only a missing file means no list: an
+    /// unreadable or corrupt one is an error, audited once per distinct failure, and marks the
+    /// backend unhealthy, so callers fail closed instead of trusting what it revoked.
+    fn revocation_list(&self) -> Result<Option<RevocationList>, Error> {
+        let read = match fs::read(&self.revocation_path) {
+            Ok(data) => serde_json::from_slice(&data)
+                .map(Some)
+                .map_err(|e| e.to_string()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.to_string()),
+        };
+        let e = match read {
+            Ok(list) => {
+                self.inner.lock().unwrap().revocation_error = None;
+                return Ok(list);
+            }
+            Err(e) => e,
+        };
+        let first = {
+            let mut inner = self.inner.lock().unwrap();
+            inner.backend_healthy = false;
+            inner.revocation_error.replace(e.clone()).as_ref() != Some(&e)
+        };
+        if first {
+            tracing::error!(error = %e, "revocation list unreadable");
+            self.audit.lock().unwrap().log(
+                "security",
+                None,
+                &format!("revocation_list_unreadable: {}", e),
+                None,
+            );
+        }
+        Err(Error::BackendFailed(format!(
+            "revocation list unreadable: {}",
+            e
+        )))
+    }
+
+    /// Resolve a signer given either as trusted key name or as key id.
+    fn resolve_signer(&self, signer_id: &str) -> Option<(VerifyingKey, KeyScope)> {
+        let name = signer_id.strip_suffix(".pub").unwrap_or(signer_id);
+        let key = self
+            .usable_keys()
+            .into_iter()
+            .find(|k| k.name == name || k.key_id == signer_id)?;
+        Some((key.verifying_key()?, key.meta.scope))
+    }
+
+    /// Key ids of trusted keys whose scope covers `baseline` and that validly signed it.
+    fn baseline_signers(&self, baseline: &Baseline) -> Vec<String> {
+        let keys: Vec<VerifyingKey> = self
+            .trusted_keys()
+            .into_iter()
+            .filter(|(_, scope)| scope.violation(baseline).is_none())
+            .map(|(vk, _)| vk)
+            .collect();
+        baseline.verified_key_ids(&keys)
+    }
+
+    /// Re-apply the rules of the active baselines and revocation list, for backends that do
+    /// not keep them across restarts.
+    pub fn restore_rules(&self) -> Result<(), Error> {
+        self.regenerate_rules()
+    }
+
+    /// Active baselines that are currently valid and still signed by enough non-revoked
+    /// trusted keys; these are the ones turned into allow rules.
+    fn enforced_baselines(&self) -> Vec<Baseline> {
+        let now = chrono::Utc::now();
+        self.active_baselines()
+            .into_iter()
+            .filter(|b| b.validity_error(now, &self.host).is_none())
+            .filter(|b| self.baseline_signers(b).len() >= self.policy.required_signatures_for(b))
+            .collect()
+    }
+
+    /// Rebuild usbguard rules: revoked devices first, then every enforced baseline.
+    fn regenerate_rules(&self) -> Result<(), Error> {
+        let mut rules = self
+            .revocation_list()?
+            .map(|r| generate_block_rules(&r))
+            .unwrap_or_default();
+        rules.extend(
+            self.enforced_baselines()
+                .iter()
+                .map(generate_rules_from_baseline),
+        );
+        self.backend.apply_rules(&rules).map_err(|e| {
+            tracing::error!(error=?e, "failed to apply rules");
+            Error::BackendFailed(format!("applying rules failed: {}", e))
+        })
+    }
+
+    /// Move expired baselines out of the active set and regenerate rules if any changed.
+    pub async fn sweep_expired_baselines(&self) {
+        let now = chrono::Utc::now();
+        let Ok(entries) = fs::read_dir(&self.baselines_dir) else {
+            return;
+        };
+        let expired_dir = self.baselines_dir.join("expired");
+        let mut changed = false;
+        for path in entries.flatten().map(|e| e.path()) {
+            if path.extension().and_then(|s| s.to_str()) != Some("json") {
+                continue;
+            }
+            let expired = fs::read(&path)
+                .ok()
+                .and_then(|d| Baseline::from_json(&d).ok())
+                .is_some_and(|b| b.is_expired(now));
+            if !expired {
+                continue;
+            }
+            let Some(name) = path.file_name() else {
+                continue;
+            };
+            let moved = fs::create_dir_all(&expired_dir).is_ok()
+                && fs::rename(&path, expired_dir.join(name)).is_ok();
+            self.audit.lock().unwrap().log(
+                "baseline_expired",
+                None,
+                &format!(
+                    "{}: {}",
+                    name.to_string_lossy(),
+                    if moved {
+                        "deactivated"
+                    } else {
+                        "deactivate_failed"
+                    }
+                ),
+                None,
+            );
+            changed |= moved;
+        }
+        if changed && self.regenerate_rules().is_err() {
+            self.audit.lock().unwrap().log(
+                "baseline_expired",
+                None,
+                "rules_regenerate_failed",
+                None,
+            );
         }
-        // Load baseline JSON, verify against any trusted key, then copy into baselines_dir
-        let path = PathBuf::from(_baseline_path);
+    }
+
+    /// Verify a baseline file and install it into the active set.
+    /// Without `rollback`, baselines older than the last applied sequence are rejected.
+    fn apply_baseline_file(
+        &self,
+        baseline_path: &str,
+        signer_id: &str,
+        rollback: bool,
+    ) -> Result<(), Error> {
+        // Load baseline JSON, verify against the named trusted key, then copy into baselines_dir
+        let path = PathBuf::from(baseline_path);
         if path
             .components()
             .any(|c| matches!(c, std::path::Component::ParentDir))
@@ -195,8 +680,12 @@ impl DaemonState {
                 "baseline_path_traversal_attempt",
                 None,
             );
-            return false;
+            return Err(Error::InvalidInput(
+                "baseline path must not contain `..`".into(),
+            ));
         }
+        // Without the revocation list there is no telling which keys are still trusted
+        self.revocation_list()?;
         let data = match fs::read(&path) {
             Ok(d) => d,
             Err(e) => {
@@ -206,34 +695,124 @@ impl DaemonState {
                     &format!("baseline_read_failed: {}", e),
                     None,
                 );
-                return false;
+                return Err(match e.kind() {
+                    std::io::ErrorKind::NotFound => Error::NotFound(baseline_path.to_string()),
+                    _ => Error::InvalidInput(format!("cannot read {}: {}", baseline_path, e)),
+                });
             }
         };
-        let baseline: Baseline = match serde_json::from_slice(&data) {
+        let baseline = match lint_baseline(&data) {
             Ok(b) => b,
-            Err(_) => return false,
+            Err(issues) => {
+                self.audit.lock().unwrap().log(
+                    "security",
+                    None,
+                    &format!("baseline_invalid: {}", describe_issues(&issues)),
+                    None,
+                );
+                return Err(Error::InvalidInput(describe_issues(&issues)));
+            }
         };
-        // Load trusted keys
-        let mut verified = false;
-        if let Ok(entries) = fs::read_dir(&self.trusted_pubkeys_dir) {
-            for e in entries.flatten() {
-                if e.path().extension().and_then(|s| s.to_str()) == Some("pub") {
-                    if let Ok(bytes) = fs::read(e.path()) {
-                        if let Ok(arr) = <[u8; 32]>::try_from(bytes.as_slice()) {
-                            let vk = VerifyingKey::from_bytes(&arr);
-                            if let Ok(vk) = vk {
-                                if let Ok(true) = baseline.verify_signature(&vk) {
-                                    verified = true;
-                                    break;
-                                }
-                            }
-                        }
-                    }
-                }
+        if let Some(reason) = baseline.validity_error(chrono::Utc::now(), &self.host) {
+            self.audit.lock().unwrap().log(
+                "security",
+                None,
+                &format!("baseline_not_valid: {}", reason),
+                None,
+            );
+            return Err(Error::PolicyDenied(reason));
+        }
+        // Resolve the named signer to exactly one trusted key
+        let (vk, scope) = match self.resolve_signer(signer_id) {
+            Some(k) => k,
+            None => {
+                self.audit.lock().unwrap().log(
+                    "security",
+                    None,
+                    &format!("baseline_signer_unknown: {}", signer_id),
+                    None,
+                );
+                return Err(Error::NotFound(format!("no trusted key {}", signer_id)));
+            }
+        };
+        let signer_key_id = key_id(&vk);
+        if !matches!(baseline.verify_signature(&vk), Ok(true)) {
+            self.audit.lock().unwrap().log_signed(
+                "security",
+                None,
+                "baseline_signature_invalid",
+                None,
+                Some(signer_key_id.clone()),
+            );
+            return Err(Error::SignatureInvalid(format!(
+                "baseline is not signed by {} ({})",
+                signer_id, signer_key_id
+            )));
+        }
+        if let Some(d) = scope.violation(&baseline) {
+            let device = format!("{}:{} type={}", d.vendor_id, d.product_id, d.device_type);
+            self.audit.lock().unwrap().log_signed(
+                "security",
+                None,
+                &format!("baseline_out_of_scope: {}", device),
+                None,
+                Some(signer_key_id),
+            );
+            return Err(Error::PolicyDenied(format!(
+                "{} may not sign {}",
+                signer_id, device
+            )));
+        }
+        let signers = self.baseline_signers(&baseline);
+        let required = self.policy.required_signatures_for(&baseline);
+        if signers.len() < required {
+            self.audit.lock().unwrap().log_signed(
+                "security",
+                None,
+                &format!(
+                    "baseline_insufficient_signatures: {}/{}",
+                    signers.len(),
+                    required
+                ),
+                None,
+                Some(signer_key_id),
+            );
+            return Err(Error::PolicyDenied(format!(
+                "{} of {} required signatures",
+                signers.len(),
+                required
+            )));
+        }
+        let sequence = baseline.sequence.unwrap_or(0);
+        if !rollback {
+            if let Some((newer_key, last)) = self.sequences.find_newer(&signers, sequence) {
+                self.audit.lock().unwrap().log_signed(
+                    "security",
+                    None,
+                    &format!("baseline_replay_rejected: sequence {} < {}", sequence, last),
+                    None,
+                    Some(newer_key),
+                );
+                return Err(Error::PolicyDenied(format!(
+                    "sequence {} is older than the last applied {}; use rollback",
+                    sequence, last
+                )));
             }
         }
-        if !verified {
-            return false;
+        // Record the sequence before installing: if it cannot be persisted, installing
+        // anyway would let the older baselines it replaces be replayed
+        if let Err(e) = self.sequences.record(&signers, sequence, rollback) {
+            self.audit.lock().unwrap().log_signed(
+                "security",
+                None,
+                &format!("baseline_sequence_record_failed: {}", e),
+                None,
+                Some(signer_key_id),
+            );
+            return Err(Error::BackendFailed(format!(
+                "cannot record baseline sequence: {}",
+                e
+            )));
         }
         // Copy file into baselines_dir with a timestamped name
         let filename = format!(
@@ -249,7 +828,11 @@ impl DaemonState {
                     &format!("baseline_dir_create_failed: {}", e),
                     None,
                 );
-                return false;
+                return Err(Error::BackendFailed(format!(
+                    "cannot create {}: {}",
+                    dir.display(),
+                    e
+                )));
             }
         }
         // (Entfernt: doppelter Schreibvorgang)
@@ -270,68 +853,552 @@ impl DaemonState {
                     &format!("baseline_file_create_failed: {}", e),
                     None,
                 );
-                return false;
+                return Err(Error::BackendFailed(format!(
+                    "cannot create {}: {}",
+                    dest.display(),
+                    e
+                )));
             }
         };
-        let ok = file.write_all(&data).is_ok();
-        self.audit.lock().unwrap().log(
+        let written = file.write_all(&data);
+        let ok = written.is_ok();
+        self.audit.lock().unwrap().log_signed(
             "persistent_allow",
             None,
-            if ok {
-                "baseline_applied"
-            } else {
-                "baseline_apply_failed"
+            match (ok, rollback) {
+                (true, false) => "baseline_applied",
+                (true, true) => "baseline_rolled_back",
+                (false, _) => "baseline_apply_failed",
             },
             None,
+            Some(signer_key_id),
         );
-        if !ok {
-            return false;
+        if let Err(e) = written {
+            return Err(Error::BackendFailed(format!(
+                "cannot write {}: {}",
+                dest.display(),
+                e
+            )));
         }
+        self.supersede_older(&dest, &signers, sequence, rollback);
 
-        // Generate usbguard rules from baseline and apply atomically, dann reload
-        let rules = generate_rules_from_baseline(&baseline);
-        if let Err(e) = UsbguardBackend::apply_rules_atomically(&rules) {
-            tracing::error!(error=?e, "failed to apply usbguard rules atomically");
-            return false;
-        }
-        true
+        // Regenerate usbguard rules from all active baselines and apply atomically
+        self.regenerate_rules()
     }
 
-    async fn revoke_device(&self, device_id: &str) -> bool {
-        let valid_id = !device_id.is_empty() && device_id.len() <= 64 && device_id.is_ascii();
-        if !valid_id {
+    /// Deactivate active baselines from the same signers that `sequence` replaces.
+    fn supersede_older(&self, applied: &Path, signers: &[String], sequence: u64, rollback: bool) {
+        let Ok(entries) = fs::read_dir(&self.baselines_dir) else {
+            return;
+        };
+        let superseded_dir = self.baselines_dir.join("superseded");
+        for path in entries.flatten().map(|e| e.path()) {
+            if path == applied || path.extension().and_then(|s| s.to_str()) != Some("json") {
+                continue;
+            }
+            let Some(other) = fs::read(&path)
+                .ok()
+                .and_then(|d| Baseline::from_json(&d).ok())
+            else {
+                continue;
+            };
+            let other_seq = other.sequence.unwrap_or(0);
+            let replaced = other_seq < sequence || (rollback && other_seq > sequence);
+            let shared = self
+                .baseline_signers(&other)
+                .iter()
+                .any(|k| signers.contains(k));
+            if !(replaced && shared) {
+                continue;
+            }
+            let Some(name) = path.file_name() else {
+                continue;
+            };
+            let moved = fs::create_dir_all(&superseded_dir).is_ok()
+                && fs::rename(&path, superseded_dir.join(name)).is_ok();
             self.audit.lock().unwrap().log(
-                "revoke_reject",
-                Some(device_id.to_string()),
-                "invalid_input",
+                "baseline_superseded",
                 None,
-            );
-            return false;
+                &format!(
+                    "{}: {}",
+                    name.to_string_lossy(),
+                    if moved {
+                        "deactivated"
+                    } else {
+                        "deactivate_failed"
+                    }
+                ),
+                None,
+            );
         }
-        let ok = self.backend.revoke(device_id).await;
+    }
+
+    /// Move trusted keys past their `not_after` out of the trusted set.
+    pub fn sweep_expired_keys(&self) {
+        let expired = self.keys.sweep_expired(chrono::Utc::now());
+        for key in &expired {
+            self.audit.lock().unwrap().log_signed(
+                "key_expired",
+                None,
+                &format!("{}: deactivated", key.name),
+                None,
+                Some(key.key_id.clone()),
+            );
+        }
+        if !expired.is_empty() && self.regenerate_rules().is_err() {
+            self.audit
+                .lock()
+                .unwrap()
+                .log("key_expired", None, "rules_regenerate_failed", None);
+        }
+    }
+
+    /// Active baselines that would fall below their signature threshold without `key_id`.
+    fn baselines_depending_on(&self, key_id: &str) -> Vec<Baseline> {
+        self.active_baselines()
+            .into_iter()
+            .filter(|b| {
+                let signers = self.baseline_signers(b);
+                signers.iter().any(|k| k == key_id)
+                    && signers.len() - 1 < self.policy.required_signatures_for(b)
+            })
+            .collect()
+    }
+
+    /// Audit a key store change made by a D-Bus caller.
+    fn audit_key_change(&self, name: &str, key_id: Option<String>, action: &str, uid: Option<u32>) {
+        self.audit.lock().unwrap().log_signed(
+            "trusted_key",
+            None,
+            &format!("{}: {}", name, action),
+            uid,
+            key_id,
+        );
+    }
+
+    /// Verify a revocation list file and make it the applied list.
+    fn apply_revocation_file(&self, list_path: &str) -> Result<(), Error> {
+        let path = PathBuf::from(list_path);
+        if path
+            .components()
+            .any(|c| matches!(c, std::path::Component::ParentDir))
+        {
+            self.audit.lock().unwrap().log(
+                "security",
+                None,
+                "revocation_path_traversal_attempt",
+                None,
+            );
+            return Err(Error::InvalidInput(
+                "revocation list path must not contain `..`".into(),
+            ));
+        }
+        // Without the current list there is no sequence to check a new one against
+        let current = self.revocation_list()?;
+        let list: RevocationList = match fs::read(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|d| serde_json::from_slice(&d).map_err(|e| e.to_string()))
+        {
+            Ok(l) => l,
+            Err(e) => {
+                self.audit
+                    .lock()
+                    .unwrap()
+                    .log("revocation", None, "revocation_read_failed", None);
+                return Err(Error::InvalidInput(format!(
+                    "cannot read {}: {}",
+                    list_path, e
+                )));
+            }
+        };
+        // A list can revoke any device or key, so only unscoped keys count, up to the
+        // configured threshold
+        let signers = self.revocation_signers(&list);
+        let Some(signer_key_id) = signers.first().cloned() else {
+            self.audit
+                .lock()
+                .unwrap()
+                .log("security", None, "revocation_signature_invalid", None);
+            return Err(Error::SignatureInvalid(
+                "revocation list is not signed by an unscoped trusted key".into(),
+            ));
+        };
+        let required = self.policy.required_revocation_signatures();
+        if signers.len() < required {
+            self.audit.lock().unwrap().log_signed(
+                "security",
+                None,
+                &format!(
+                    "revocation_insufficient_signatures: {}/{}",
+                    signers.len(),
+                    required
+                ),
+                None,
+                Some(signer_key_id),
+            );
+            return Err(Error::PolicyDenied(format!(
+                "{} of {} required signatures",
+                signers.len(),
+                required
+            )));
+        }
+        if let Some(current) = current {
+            // An equal sequence could swap in different content and quietly un-revoke
+            if list.sequence <= current.sequence && list != current {
+                self.audit.lock().unwrap().log_signed(
+                    "security",
+                    None,
+                    &format!(
+                        "revocation_replay_rejected: sequence {} <= {}",
+                        list.sequence, current.sequence
+                    ),
+                    None,
+                    Some(signer_key_id),
+                );
+                return Err(Error::PolicyDenied(format!(
+                    "sequence {} is not newer than the applied {}",
+                    list.sequence, current.sequence
+                )));
+            }
+        }
+        let ok = self.write_revocation_list(&list).is_ok();
+        self.audit.lock().unwrap().log_signed(
+            "revocation",
+            None,
+            &if ok {
+                format!(
+                    "revocation_applied: sequence={} fingerprints={} devices={} keys={}",
+                    list.sequence,
+                    list.fingerprints.len(),
+                    list.devices.len(),
+                    list.revoked_keys.len()
+                )
+            } else {
+                "revocation_apply_failed".to_string()
+            },
+            None,
+            Some(signer_key_id),
+        );
+        if !ok {
+            return Err(Error::BackendFailed(format!(
+                "cannot write {}",
+                self.revocation_path.display()
+            )));
+        }
+        self.regenerate_rules()
+    }
+
+    /// Key ids of usable trusted keys without a device-type scope that signed `list`.
+    fn revocation_signers(&self, list: &RevocationList) -> Vec<String> {
+        self.trusted_keys()
+            .into_iter()
+            .filter(|(_, scope)| scope.device_types.is_empty())
+            .filter(|(vk, _)| matches!(list.verify_signature(vk), Ok(true)))
+            .map(|(vk, _)| key_id(&vk))
+            .collect()
+    }
+
+    /// Store `list` as the applied one: owner-only, written to a temporary file and renamed
+    /// so a crash never leaves a truncated list behind.
+    fn write_revocation_list(&self, list: &RevocationList) -> std::io::Result<()> {
+        if let Some(dir) = self.revocation_path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp = self.revocation_path.with_extension("json.tmp");
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            let mut f = fs::OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .mode(0o600)
+                .open(&tmp)?;
+            f.write_all(&serde_json::to_vec_pretty(list)?)?;
+            f.sync_all()?;
+        }
+        fs::rename(tmp, &self.revocation_path)
+    }
+
+    /// Remove connected devices that the revocation list bans.
+    pub async fn enforce_revocations(&self) {
+        let Ok(Some(list)) = self.revocation_list() else {
+            return;
+        };
+        for d in self.backend.list_devices().await {
+            self.revoke_banned(&list, &d).await;
+        }
+    }
+
+    /// Remove `device` if `list` bans it, and audit the outcome.
+    async fn revoke_banned(&self, list: &RevocationList, device: &DeviceInfo) {
+        if !list.blocks(device) {
+            return;
+        }
+        let ok = self.backend.revoke(&device.id).await;
+        self.inner.lock().unwrap().ephemeral.remove(&device.id);
         self.audit.lock().unwrap().log(
-            "revoke",
-            Some(device_id.to_string()),
-            if ok { "revoke_ok" } else { "revoke_fail" },
+            "revocation",
+            Some(device_subject(device)),
+            if ok {
+                "revoked_device_blocked"
+            } else {
+                "revoked_device_block_failed"
+            },
             None,
         );
-        ok
     }
 
-    async fn get_device_info(&self, device_id: &str) -> DeviceInfo {
+    /// True if the applied revocation list bans `device`, or cannot be read.
+    fn is_revoked(&self, device: &DeviceInfo) -> bool {
+        self.revocation_list()
+            .map_or(true, |l| l.is_some_and(|l| l.blocks(device)))
+    }
+
+    /// The active baselines folded into one device set, used for diffing.
+    fn active_set(&self) -> Baseline {
+        let active = self.active_baselines();
+        Baseline {
+            version: BASELINE_VERSION,
+            created_by: "active".into(),
+            created_at: chrono::Utc::now(),
+            devices: active.into_iter().flat_map(|b| b.devices).collect(),
+            not_before: None,
+            not_after: None,
+            hosts: Vec::new(),
+            sequence: None,
+            key_id: None,
+            signature: None,
+            signatures: Vec::new(),
+        }
+    }
+}
+
+/// A raw Ed25519 public key in base64, as sent by `lusbyctl keys add`.
+fn decode_verifying_key(b64: &str) -> Option<VerifyingKey> {
+    base64::engine::general_purpose::STANDARD
+        .decode(b64)
+        .ok()
+        .and_then(|b| <[u8; 32]>::try_from(b.as_slice()).ok())
+        .and_then(|arr| VerifyingKey::from_bytes(&arr).ok())
+}
+
+/// How the audit log names a device: its fingerprint, the value clients show, or the id if
+/// the backend has none.
+fn device_subject(device: &DeviceInfo) -> String {
+    if device.fingerprint.is_empty() {
+        device.id.clone()
+    } else {
+        device.fingerprint.clone()
+    }
+}
+
+fn valid_device_id(device_id: &str) -> bool {
+    !device_id.is_empty() && device_id.len() <= 64 && device_id.is_ascii()
+}
+
+/// Current Unix time in seconds.
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[derive(Default)]
+struct StateInner {
+    deny_unknown: bool,
+    ephemeral: HashMap<String, EphemeralGrant>,
+    /// Last answer of `UsbBackend::healthy`, refreshed by `publish_changes`.
+    backend_healthy: bool,
+    /// Property values last announced with `PropertiesChanged`.
+    published: Option<Published>,
+    /// Why the revocation list could not be read last time, so each failure is audited once.
+    revocation_error: Option<String>,
+}
+
+/// The mutable properties as last published, to emit change signals only for real changes.
+#[derive(Clone)]
+struct Published {
+    grants: Vec<EphemeralGrant>,
+    baseline_count: u32,
+    backend_healthy: bool,
+}
+
+impl DaemonState {
+    #[allow(dead_code)] // for tests, through the library target
+    pub fn new<B>(backend: B) -> Self
+    where
+        B: UsbBackend + 'static,
+    {
+        Self::from_config(backend, &DaemonConfig::default())
+    }
+
+    #[allow(dead_code)] // for tests, through the library target
+    pub fn new_with_audit_path<B>(backend: B, audit_path: PathBuf) -> Self
+    where
+        B: UsbBackend + 'static,
+    {
+        let mut config = DaemonConfig::default();
+        config.paths.audit_log = audit_path;
+        Self::from_config(backend, &config)
+    }
+
+    pub fn from_config<B>(backend: B, config: &DaemonConfig) -> Self
+    where
+        B: UsbBackend + 'static,
+    {
+        let audit = AuditLogger::new(config.paths.audit_log.clone()).expect("init audit");
+        Self {
+            inner: Arc::new(Mutex::new(StateInner {
+                deny_unknown: config.policy.deny_unknown,
+                ephemeral: HashMap::new(),
+                backend_healthy: true,
+                published: None,
+                revocation_error: None,
+            })),
+            backend: Arc::new(backend),
+            audit: Arc::new(Mutex::new(audit)),
+            policy: config.policy.clone(),
+            host: HostIdentity::detect(),
+            sequences: Arc::new(SequenceStore::new(
+                config.paths.state_dir.join("sequences.json"),
+            )),
+            baselines_dir: config.paths.baselines_system.clone(),
+            keys: Arc::new(KeyStore::new(config.paths.trusted_pubkeys.clone())),
+            revocation_path: config.paths.state_dir.join("revocation.json"),
+            draft_path: config.paths.state_dir.join("baseline-draft.json"),
+            device_objects: Arc::new(tokio::sync::Mutex::new(BTreeSet::new())),
+        }
+    }
+}
+
+#[interface(name = "org.lusby.Daemon")]
+impl DaemonState {
+    async fn get_policy_status(&self) -> PolicyStatus {
+        let deny: bool = self.inner.lock().unwrap().deny_unknown;
+        PolicyStatus { deny_unknown: deny }
+    }
+
+    async fn list_devices(&self) -> Vec<DeviceInfo> {
+        self.backend.list_devices().await
+    }
+
+    async fn request_ephemeral_allow(
+        &self,
+        device_id: &str,
+        ttl: u32,
+        requester_uid: u32,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+    ) -> Result<(), Error> {
+        if requester_uid == 0 {
+            self.audit.lock().unwrap().log(
+                "ephemeral_allow_reject",
+                Some(device_id.to_string()),
+                "invalid_input",
+                Some(requester_uid),
+            );
+            return Err(Error::InvalidInput("requester uid must not be 0".into()));
+        }
+        self.grant_ephemeral(device_id, ttl, requester_uid).await?;
+        self.publish_changes(&ctxt).await?;
+        Ok(())
+    }
+
+    async fn apply_persistent_allow(
+        &self,
+        baseline_path: &str,
+        signer_id: &str,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+    ) -> Result<(), Error> {
+        // Polkit authorization
+        if let Err(e) = require_authorization(conn, &header, MANAGE_ACTION).await {
+            self.audit
+                .lock()
+                .unwrap()
+                .log("policy_denied", None, "polkit_denied", None);
+            return Err(e);
+        }
+        self.apply_baseline_file(baseline_path, signer_id, false)?;
+        self.publish_changes(&ctxt).await?;
+        Ok(())
+    }
+
+    /// Apply a baseline older than the last applied sequence (separately polkit-gated).
+    async fn rollback_persistent_allow(
+        &self,
+        baseline_path: &str,
+        signer_id: &str,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+    ) -> Result<(), Error> {
+        if let Err(e) = require_authorization(conn, &header, ROLLBACK_ACTION).await {
+            self.audit
+                .lock()
+                .unwrap()
+                .log("policy_denied", None, "polkit_denied_rollback", None);
+            return Err(e);
+        }
+        self.apply_baseline_file(baseline_path, signer_id, true)?;
+        self.publish_changes(&ctxt).await?;
+        Ok(())
+    }
+
+    /// Apply a signed revocation list; it takes priority over all baselines.
+    async fn apply_revocation_list(
+        &self,
+        list_path: &str,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+    ) -> Result<(), Error> {
+        if let Err(e) = require_authorization(conn, &header, MANAGE_ACTION).await {
+            self.audit
+                .lock()
+                .unwrap()
+                .log("policy_denied", None, "polkit_denied", None);
+            return Err(e);
+        }
+        self.apply_revocation_file(list_path)?;
+        self.enforce_revocations().await;
+        self.publish_changes(&ctxt).await?;
+        Ok(())
+    }
+
+    /// The applied revocation list as JSON, or an empty string if none is applied.
+    async fn get_revocation_list(&self) -> String {
+        self.revocation_list()
+            .ok()
+            .flatten()
+            .and_then(|r| serde_json::to_string(&r).ok())
+            .unwrap_or_default()
+    }
+
+    async fn revoke_device(
+        &self,
+        device_id: &str,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+    ) -> Result<(), Error> {
+        self.reject_device(device_id).await?;
+        self.publish_changes(&ctxt).await?;
+        Ok(())
+    }
+
+    async fn get_device_info(&self, device_id: &str) -> Result<DeviceInfo, Error> {
         self.backend
             .get_device(device_id)
             .await
-            .unwrap_or(DeviceInfo {
-                id: String::new(),
-                vendor_id: String::new(),
-                product_id: String::new(),
-                serial: String::new(),
-                fingerprint: String::new(),
-                device_type: String::new(),
-                allowed: false,
-                persistent: false,
-            })
+            .ok_or_else(|| Error::NotFound(format!("no device {}", device_id)))
+    }
+
+    /// Diff a baseline (JSON text) against the currently active set; returns the diff as JSON.
+    async fn diff_active_baseline(&self, baseline_json: &str) -> Result<String, Error> {
+        let new = Baseline::from_json(baseline_json.as_bytes())
+            .map_err(|e| Error::InvalidInput(e.to_string()))?;
+        let diff = diff_baselines(&self.active_set(), &new, generate_rules_from_baseline);
+        Ok(serde_json::to_string(&diff).unwrap_or_default())
     }
 
     async fn get_policy_status_string(&self) -> String {
@@ -345,24 +1412,24 @@ impl DaemonState {
         &self,
         #[zbus(connection)] conn: &Connection,
         #[zbus(header)] header: Header<'_>,
-    ) -> Vec<String> {
-        if !check_manage_authorization(conn, &header)
-            .await
-            .unwrap_or(false)
-        {
-            return vec![];
-        }
-        let mut names = Vec::new();
-        if let Ok(entries) = fs::read_dir(&self.trusted_pubkeys_dir) {
-            for e in entries.flatten() {
-                if e.path().extension().and_then(|s| s.to_str()) == Some("pub") {
-                    if let Some(name) = e.file_name().to_str() {
-                        names.push(name.to_string());
-                    }
-                }
-            }
-        }
-        names
+    ) -> Result<Vec<String>, Error> {
+        require_authorization(conn, &header, MANAGE_ACTION).await?;
+        Ok(self
+            .keys
+            .list()
+            .into_iter()
+            .map(|k| format!("{}.pub", k.name))
+            .collect())
+    }
+
+    /// Trusted keys with their metadata and fingerprints as a JSON array.
+    async fn list_trusted_keys(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> Result<String, Error> {
+        require_authorization(conn, &header, MANAGE_ACTION).await?;
+        Ok(serde_json::to_string(&self.keys.list()).unwrap_or_default())
     }
 
     /// Add a trusted public key (raw 32-byte) as a file named `<name>.pub`
@@ -372,43 +1439,50 @@ impl DaemonState {
         key_bytes_b64: &str,
         #[zbus(connection)] conn: &Connection,
         #[zbus(header)] header: Header<'_>,
-    ) -> bool {
-        if !check_manage_authorization(conn, &header)
+    ) -> Result<(), Error> {
+        self.add_trusted_key(name, key_bytes_b64, "{}", conn, header)
             .await
-            .unwrap_or(false)
-        {
-            return false;
-        }
-        let bytes = match base64::engine::general_purpose::STANDARD.decode(key_bytes_b64) {
-            Ok(b) => b,
-            Err(_) => return false,
-        };
-        if bytes.len() != 32 {
-            return false;
-        }
-        let mut path = self.trusted_pubkeys_dir.clone();
-        let fname = if name.ends_with(".pub") {
-            name.to_string()
-        } else {
-            format!("{}.pub", name)
+    }
+
+    /// Add a trusted public key with metadata given as `TrustedKeyMeta` JSON
+    /// (display_name, owner, device_types, not_after).
+    async fn add_trusted_key(
+        &self,
+        name: &str,
+        key_bytes_b64: &str,
+        meta_json: &str,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> Result<(), Error> {
+        require_authorization(conn, &header, MANAGE_ACTION).await?;
+        let uid = sender_uid(conn, &header).await;
+        let Some(vk) = decode_verifying_key(key_bytes_b64) else {
+            self.audit_key_change(name, None, "add_failed: invalid key", uid);
+            return Err(Error::InvalidInput(
+                "expected a base64 Ed25519 public key".into(),
+            ));
         };
-        path.push(fname);
-        if let Some(dir) = path.parent() {
-            let _ = fs::create_dir_all(dir);
-        }
-        match fs::OpenOptions::new()
-            .write(true)
-            .create_new(true)
-            .open(&path)
-        {
-            Ok(mut f) => {
-                if f.write_all(&bytes).is_err() {
-                    return false;
-                }
-                true
+        let mut meta = match serde_json::from_str::<TrustedKeyMeta>(meta_json) {
+            Ok(meta) => meta,
+            Err(e) => {
+                self.audit_key_change(name, None, "add_failed: invalid metadata", uid);
+                return Err(Error::InvalidInput(format!("key metadata: {}", e)));
             }
-            Err(_) => false,
-        }
+        };
+        meta.added_at = Some(chrono::Utc::now());
+        meta.added_by_uid = uid;
+        meta.rotating_to = None;
+        let result = self.keys.add(name, &vk, &meta);
+        self.audit_key_change(
+            name,
+            Some(key_id(&vk)),
+            &match &result {
+                Ok(()) => "added".to_string(),
+                Err(e) => format!("add_failed: {}", e),
+            },
+            uid,
+        );
+        result.map_err(Error::InvalidInput)
     }
 
     /// Remove a trusted public key by file name
@@ -417,113 +1491,212 @@ impl DaemonState {
         name: &str,
         #[zbus(connection)] conn: &Connection,
         #[zbus(header)] header: Header<'_>,
-    ) -> bool {
-        if !check_manage_authorization(conn, &header)
-            .await
-            .unwrap_or(false)
-        {
-            return false;
-        }
-        let mut path = self.trusted_pubkeys_dir.clone();
-        let fname = if name.ends_with(".pub") {
-            name.to_string()
-        } else {
-            format!("{}.pub", name)
+    ) -> Result<(), Error> {
+        require_authorization(conn, &header, MANAGE_ACTION).await?;
+        let uid = sender_uid(conn, &header).await;
+        let Some(key) = self.keys.load(name) else {
+            self.audit_key_change(name, None, "remove_failed: no such key", uid);
+            return Err(Error::NotFound(format!("no trusted key {}", name)));
         };
-        path.push(fname);
-        fs::remove_file(path).is_ok()
+        match self.keys.remove(&key.name) {
+            Ok(key) => self.audit_key_change(&key.name, Some(key.key_id.clone()), "removed", uid),
+            Err(e) => {
+                self.audit_key_change(name, None, &format!("remove_failed: {}", e), uid);
+                return Err(Error::BackendFailed(e));
+            }
+        }
+        self.regenerate_rules()
     }
 
-    // Signals
-    #[zbus(signal)]
-    async fn unknown_device_inserted(
-        ctxt: &SignalContext<'_>,
-        device: &DeviceInfo,
-    ) -> zbus::Result<()>;
-
-    #[zbus(signal)]
-    async fn device_removed(ctxt: &SignalContext<'_>, device_id: &str) -> zbus::Result<()>;
-}
+    /// Start rotating `old_name` to a new key: the new key is trusted with the old key's
+    /// scope and owner, and the old key stays valid until `retire_trusted_key`.
+    async fn rotate_trusted_key(
+        &self,
+        old_name: &str,
+        new_name: &str,
+        new_key_b64: &str,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> Result<(), Error> {
+        require_authorization(conn, &header, MANAGE_ACTION).await?;
+        let uid = sender_uid(conn, &header).await;
+        let Some(old) = self.keys.load(old_name) else {
+            self.audit_key_change(old_name, None, "rotate_failed: no such key", uid);
+            return Err(Error::NotFound(format!("no trusted key {}", old_name)));
+        };
+        let Some(vk) = decode_verifying_key(new_key_b64) else {
+            self.audit_key_change(new_name, None, "rotate_failed: invalid key", uid);
+            return Err(Error::InvalidInput(
+                "expected a base64 Ed25519 public key".into(),
+            ));
+        };
+        let meta = TrustedKeyMeta {
+            added_at: Some(chrono::Utc::now()),
+            added_by_uid: uid,
+            rotating_to: None,
+            ..old.meta.clone()
+        };
+        if let Err(e) = self.keys.add(new_name, &vk, &meta) {
+            self.audit_key_change(
+                new_name,
+                Some(key_id(&vk)),
+                &format!("rotate_failed: {}", e),
+                uid,
+            );
+            return Err(Error::InvalidInput(e));
+        }
+        let mut old_meta = old.meta.clone();
+        old_meta.rotating_to = Some(new_name.to_string());
+        if let Err(e) = self.keys.write_meta(&old.name, &old_meta) {
+            // Unlinked, the new key would be trusted with the old key's scope for good
+            let rollback = match self.keys.remove(new_name) {
+                Ok(_) => "new key removed".to_string(),
+                Err(re) => format!("removing new key failed: {}", re),
+            };
+            self.audit_key_change(
+                &old.name,
+                Some(old.key_id.clone()),
+                &format!("rotate_failed: {}; {}", e, rollback),
+                uid,
+            );
+            return Err(Error::BackendFailed(e));
+        }
+        self.audit_key_change(
+            &old.name,
+            Some(old.key_id.clone()),
+            &format!("rotation_started: new key {} ({})", new_name, key_id(&vk)),
+            uid,
+        );
+        Ok(())
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use lusby_common::baseline::DeviceEntry;
+    /// Retire a key after rotation. Refused while an active baseline still needs its
+    /// signature to meet the threshold, unless `force` is set.
+    async fn retire_trusted_key(
+        &self,
+        name: &str,
+        force: bool,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> Result<(), Error> {
+        require_authorization(conn, &header, MANAGE_ACTION).await?;
+        let uid = sender_uid(conn, &header).await;
+        let Some(key) = self.keys.load(name) else {
+            self.audit_key_change(name, None, "retire_failed: no such key", uid);
+            return Err(Error::NotFound(format!("no trusted key {}", name)));
+        };
+        let dependent = self.baselines_depending_on(&key.key_id).len();
+        if dependent > 0 && !force {
+            let reason = format!("{} active baselines still need this key", dependent);
+            self.audit_key_change(
+                &key.name,
+                Some(key.key_id.clone()),
+                &format!("retire_refused: {}", reason),
+                uid,
+            );
+            return Err(Error::PolicyDenied(reason));
+        }
+        let result = self.keys.archive(&key.name, "retired");
+        self.audit_key_change(
+            &key.name,
+            Some(key.key_id.clone()),
+            &match &result {
+                Ok(_) => format!("retired (dependent baselines: {})", dependent),
+                Err(e) => format!("retire_failed: {}", e),
+            },
+            uid,
+        );
+        result.map_err(Error::BackendFailed)?;
+        self.regenerate_rules()
+    }
 
-    #[test]
-    fn test_generate_rules_safeguard() {
-        let b = Baseline {
-            version: 1,
-            created_by: "test".into(),
-            created_at: chrono::Utc::now(),
-            devices: vec![DeviceEntry {
-                vendor_id: "0x1234".into(),
-                product_id: "0x5678".into(),
-                serial: Some("AB\nC".into()), // malicious newline
-                bus_path: None,
-                descriptors_hash: "none".into(),
-                device_type: "hid".into(),
-                comment: None,
-            }],
-            signature: None,
+    /// Restrict which device types a trusted key may sign. An empty list lifts the restriction.
+    async fn set_trusted_key_scope(
+        &self,
+        name: &str,
+        device_types: Vec<String>,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> Result<(), Error> {
+        require_authorization(conn, &header, MANAGE_ACTION).await?;
+        let Some(key) = self.keys.load(name) else {
+            return Err(Error::NotFound(format!("no trusted key {}", name)));
         };
-        let rules = generate_rules_from_baseline(&b);
-        // We expect exactly one line because there is one device.
-        let lines: Vec<&str> = rules.lines().collect();
-        assert_eq!(
-            lines.len(),
-            1,
-            "Should produce exactly one line, found: {:?}",
-            lines
+        let mut meta = key.meta.clone();
+        meta.scope = KeyScope { device_types };
+        let written = self.keys.write_meta(&key.name, &meta);
+        self.audit.lock().unwrap().log(
+            "key_scope",
+            None,
+            &format!(
+                "{}: {}",
+                key.name,
+                if written.is_ok() {
+                    "scope_set"
+                } else {
+                    "scope_set_failed"
+                }
+            ),
+            None,
         );
-        assert!(lines[0].contains("serial \"AB?C\""));
+        written.map_err(Error::BackendFailed)
     }
 
-    use proptest::prelude::*;
+    // Properties
+    /// "enforcing" when unknown devices are blocked, otherwise "permissive".
+    #[zbus(property(emits_changed_signal = "const"))]
+    async fn mode(&self) -> String {
+        if self.inner.lock().unwrap().deny_unknown {
+            "enforcing".into()
+        } else {
+            "permissive".into()
+        }
+    }
 
-    proptest! {
-        #[test]
-        fn rule_generation_safety(
-            vid in "[0-9a-fA-F]{1,10}",
-            pid in "[0-9a-fA-F]{1,10}",
-            serial in proptest::option::of("\\PC*")
-        ) {
-             let b = Baseline {
-                version: 1,
-                created_by: "prop".into(),
-                created_at: chrono::Utc::now(),
-                devices: vec![DeviceEntry {
-                    vendor_id: vid,
-                    product_id: pid,
-                    serial,
-                    bus_path: None,
-                    descriptors_hash: "none".into(),
-                    device_type: "hid".into(),
-                    comment: None,
-                }],
-                signature: None,
-            };
-            let rules = generate_rules_from_baseline(&b);
-            let lines: Vec<&str> = rules.lines().collect();
+    #[zbus(property(emits_changed_signal = "const"))]
+    async fn deny_unknown(&self) -> bool {
+        self.inner.lock().unwrap().deny_unknown
+    }
 
-            // Safety assertion: The rules string must NOT contain more lines than devices (1).
-            // This ensures no newline injection was successfully performed to create extra rules.
-            // Note: The generator always adds a newline at the end of each rule, so we expect exactly 1 line
-            // if we filter out empty strings or simply count valid rules.
-            // Our generator produces "allow ...\n", so `lines()` (which splits on \n) will see 1 item.
+    #[zbus(property)]
+    async fn active_ephemeral_grants(&self) -> Vec<EphemeralGrant> {
+        self.active_grants()
+    }
 
-            prop_assert_eq!(lines.len(), 1, "Should produce exactly 1 rule line, found {}", lines.len());
+    /// Number of enforced baselines: installed, currently valid for this host and signed by
+    /// enough usable keys.
+    #[zbus(property)]
+    async fn baseline_count(&self) -> u32 {
+        self.enforced_baselines().len() as u32
+    }
 
-            // Further assertion: The rule should verify basic syntax
-            let rule = lines[0];
-            prop_assert!(rule.starts_with("allow id "));
+    /// Whether the USB backend answered at the last check.
+    #[zbus(property)]
+    async fn backend_healthy(&self) -> bool {
+        self.inner.lock().unwrap().backend_healthy
+    }
 
-            // Check for no control characters (except maybe the ones we sanitized to '?' which is safe)
-            // The output should be safe 7-bit ASCII or similar?
-            // Actually, we replaced controls with '?'.
-            // Let's ensure no raw newlines or CRs remain (already checked by lines.len()=1 mostly, but checking chars is good)
-            prop_assert!(!rule.contains('\r'));
-            prop_assert!(!rule.contains('\n'));
-        }
+    #[zbus(property(emits_changed_signal = "const"))]
+    async fn version(&self) -> String {
+        env!("CARGO_PKG_VERSION").into()
     }
+
+    // Signals
+    #[zbus(signal)]
+    pub async fn unknown_device_inserted(
+        ctxt: &SignalContext<'_>,
+        device: &DeviceInfo,
+    ) -> zbus::Result<()>;
+
+    /// A device matching an enforced baseline was plugged in; `entry` is the matching
+    /// baseline entry as JSON.
+    #[zbus(signal)]
+    pub async fn known_device_inserted(
+        ctxt: &SignalContext<'_>,
+        device: &DeviceInfo,
+        entry: &str,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    pub async fn device_removed(ctxt: &SignalContext<'_>, device_id: &str) -> zbus::Result<()>;
 }
@@ -0,0 +1,173 @@
+//! Per-device objects: every connected device is exported at
+//! `/org/lusby/Daemon/devices/<id>` with the `org.lusby.Device` interface. The objects are
+//! kept in step with the backend by `DaemonState::sync_device_objects`, and an
+//! `org.freedesktop.DBus.ObjectManager` at `/org/lusby/Daemon` announces them.
+
+use zbus::message::Header;
+use zbus::zvariant::OwnedObjectPath;
+use zbus::{interface, Connection, SignalContext};
+
+use lusby_common::error::Error;
+use lusby_common::types::DeviceInfo;
+
+use crate::dbus::{DaemonState, OBJECT_PATH};
+use crate::polkit::{require_authorization, sender_uid, MANAGE_ACTION};
+
+pub const DEVICES_PATH: &str = "/org/lusby/Daemon/devices";
+
+/// Object path of a device. Backend ids may contain any ASCII (usbguard uses `vid:pid`), so
+/// every byte other than `[A-Za-z0-9]` is escaped as `_xx`.
+pub fn device_path(device_id: &str) -> OwnedObjectPath {
+    let mut element = String::new();
+    for b in device_id.bytes() {
+        if b.is_ascii_alphanumeric() {
+            element.push(b as char);
+        } else {
+            element.push_str(&format!("_{:02x}", b));
+        }
+    }
+    if element.is_empty() {
+        element.push('_');
+    }
+    OwnedObjectPath::try_from(format!("{}/{}", DEVICES_PATH, element))
+        .expect("escaped device path is valid")
+}
+
+pub struct DeviceObject {
+    state: DaemonState,
+    info: DeviceInfo,
+}
+
+impl DeviceObject {
+    pub fn new(state: DaemonState, info: DeviceInfo) -> Self {
+        Self { state, info }
+    }
+
+    /// Take the backend's latest view of the device; true if its authorization changed.
+    pub fn update(&mut self, info: DeviceInfo) -> bool {
+        let changed = info.allowed != self.info.allowed;
+        self.info = info;
+        changed
+    }
+
+    /// Announce the new `State` and `GrantExpiry`, and the daemon's own properties.
+    async fn announce(&self, conn: &Connection, ctxt: &SignalContext<'_>) -> Result<(), Error> {
+        self.state_changed(ctxt).await?;
+        self.grant_expiry_changed(ctxt).await?;
+        let daemon = SignalContext::new(conn, OBJECT_PATH)?;
+        self.state.publish_changes(&daemon).await?;
+        Ok(())
+    }
+}
+
+#[interface(name = "org.lusby.Device")]
+impl DeviceObject {
+    /// Allow the device for `ttl` seconds on behalf of the caller.
+    async fn allow(
+        &mut self,
+        ttl: u32,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+    ) -> Result<(), Error> {
+        // The grant and the audit log name the caller; never guess one
+        let Some(uid) = sender_uid(conn, &header).await else {
+            return Err(Error::NotAuthorized(
+                "cannot determine the caller's uid".into(),
+            ));
+        };
+        self.state.grant_ephemeral(&self.info.id, ttl, uid).await?;
+        self.info.allowed = true;
+        self.announce(conn, &ctxt).await
+    }
+
+    /// Deauthorize the device; it stays connected.
+    async fn block(
+        &mut self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+    ) -> Result<(), Error> {
+        let uid = sender_uid(conn, &header).await;
+        self.state.block_device(&self.info.id, uid).await?;
+        self.info.allowed = false;
+        self.announce(conn, &ctxt).await
+    }
+
+    /// Reject the device; the backend treats it as removed until it is plugged in again.
+    async fn reject(
+        &mut self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+    ) -> Result<(), Error> {
+        self.state.reject_device(&self.info.id).await?;
+        self.info.allowed = false;
+        self.announce(conn, &ctxt).await
+    }
+
+    /// Add the device to the unsigned draft baseline; returns the draft's path.
+    async fn add_to_baseline_draft(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> Result<String, Error> {
+        require_authorization(conn, &header, MANAGE_ACTION).await?;
+        let uid = sender_uid(conn, &header).await;
+        let path = self.state.add_to_baseline_draft(&self.info, uid)?;
+        Ok(path.display().to_string())
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    async fn id(&self) -> String {
+        self.info.id.clone()
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    async fn vendor_id(&self) -> String {
+        self.info.vendor_id.clone()
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    async fn product_id(&self) -> String {
+        self.info.product_id.clone()
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    async fn serial(&self) -> String {
+        self.info.serial.clone()
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    async fn fingerprint(&self) -> String {
+        self.info.fingerprint.clone()
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    async fn device_type(&self) -> String {
+        self.info.device_type.clone()
+    }
+
+    /// Interface classes as `cc:ss:pp`.
+    #[zbus(property(emits_changed_signal = "const"))]
+    async fn interfaces(&self) -> Vec<String> {
+        self.info.interfaces.clone()
+    }
+
+    /// "allowed" or "blocked".
+    #[zbus(property)]
+    async fn state(&self) -> String {
+        if self.info.allowed {
+            "allowed".into()
+        } else {
+            "blocked".into()
+        }
+    }
+
+    /// Unix time in seconds when the ephemeral grant runs out, or 0 without one.
+    #[zbus(property)]
+    async fn grant_expiry(&self) -> u64 {
+        self.state
+            .grant_for(&self.info.id)
+            .map_or(0, |g| g.expires_at)
+    }
+}
@@ -0,0 +1,158 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use ed25519_dalek::VerifyingKey;
+use lusby_common::trust::{validate_key_name, TrustedKey, TrustedKeyMeta};
+
+/// Trusted keys as `<name>.pub` (raw 32 bytes) plus optional `<name>.json` metadata.
+/// Retired and expired keys are moved to subdirectories instead of being deleted.
+pub struct KeyStore {
+    dir: PathBuf,
+}
+
+impl KeyStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    fn paths(&self, stem: &str) -> (PathBuf, PathBuf) {
+        (
+            self.dir.join(format!("{}.pub", stem)),
+            self.dir.join(format!("{}.json", stem)),
+        )
+    }
+
+    /// Load a key by name (with or without `.pub`).
+    pub fn load(&self, name: &str) -> Option<TrustedKey> {
+        let stem = validate_key_name(name).ok()?;
+        let (pub_path, meta_path) = self.paths(stem);
+        let bytes = fs::read(pub_path).ok()?;
+        let arr = <[u8; 32]>::try_from(bytes.as_slice()).ok()?;
+        let vk = VerifyingKey::from_bytes(&arr).ok()?;
+        let meta = match fs::read(meta_path) {
+            // Unreadable metadata must not silently widen the key's scope
+            Ok(data) => serde_json::from_slice(&data).ok()?,
+            Err(_) => TrustedKeyMeta::default(),
+        };
+        Some(TrustedKey::new(stem, &vk, meta))
+    }
+
+    /// All loadable keys, sorted by name.
+    pub fn list(&self) -> Vec<TrustedKey> {
+        let mut names: Vec<String> = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries
+                .flatten()
+                .filter(|e| e.path().extension().and_then(|s| s.to_str()) == Some("pub"))
+                .filter_map(|e| e.file_name().to_str().map(str::to_string))
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        names.sort();
+        names.iter().filter_map(|n| self.load(n)).collect()
+    }
+
+    /// Store a new key. Fails if the name is invalid or already taken, or if the same key
+    /// is already trusted under another name.
+    pub fn add(&self, name: &str, vk: &VerifyingKey, meta: &TrustedKeyMeta) -> Result<(), String> {
+        let stem = validate_key_name(name)?;
+        let new = TrustedKey::new(stem, vk, meta.clone());
+        if let Some(existing) = self.list().into_iter().find(|k| k.key_id == new.key_id) {
+            return Err(format!("key already trusted as {}", existing.name));
+        }
+        fs::create_dir_all(&self.dir).map_err(|e| e.to_string())?;
+        let (pub_path, _) = self.paths(stem);
+        let mut f = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&pub_path)
+            .map_err(|e| e.to_string())?;
+        f.write_all(vk.as_bytes()).map_err(|e| e.to_string())?;
+        if let Err(e) = self.write_meta(stem, meta) {
+            let _ = fs::remove_file(&pub_path);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    pub fn write_meta(&self, name: &str, meta: &TrustedKeyMeta) -> Result<(), String> {
+        let stem = validate_key_name(name)?;
+        let (_, meta_path) = self.paths(stem);
+        let data = serde_json::to_vec_pretty(meta).map_err(|e| e.to_string())?;
+        let tmp = meta_path.with_extension("json.tmp");
+        fs::write(&tmp, data).map_err(|e| e.to_string())?;
+        fs::rename(tmp, meta_path).map_err(|e| e.to_string())
+    }
+
+    pub fn remove(&self, name: &str) -> Result<TrustedKey, String> {
+        let key = self.load(name).ok_or("no such key")?;
+        let (pub_path, meta_path) = self.paths(&key.name);
+        fs::remove_file(pub_path).map_err(|e| e.to_string())?;
+        let _ = fs::remove_file(meta_path);
+        Ok(key)
+    }
+
+    /// Move a key out of the trusted set into `<dir>/<subdir>/`, keeping its metadata.
+    pub fn archive(&self, name: &str, subdir: &str) -> Result<TrustedKey, String> {
+        let key = self.load(name).ok_or("no such key")?;
+        let dest = self.dir.join(subdir);
+        fs::create_dir_all(&dest).map_err(|e| e.to_string())?;
+        let stamp = Utc::now().format("%Y%m%dT%H%M%S");
+        let (pub_path, meta_path) = self.paths(&key.name);
+        let moved = |from: &Path, ext: &str| {
+            fs::rename(from, dest.join(format!("{}-{}.{}", key.name, stamp, ext)))
+        };
+        moved(&pub_path, "pub").map_err(|e| e.to_string())?;
+        if meta_path.exists() {
+            let _ = moved(&meta_path, "json");
+        }
+        Ok(key)
+    }
+
+    /// Archive every key whose `not_after` has passed and return them.
+    pub fn sweep_expired(&self, now: DateTime<Utc>) -> Vec<TrustedKey> {
+        self.list()
+            .into_iter()
+            .filter(|k| k.is_expired(now))
+            .filter_map(|k| self.archive(&k.name, "expired").ok())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::SigningKey;
+
+    #[test]
+    fn add_rotate_and_sweep() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = KeyStore::new(dir.path().to_path_buf());
+        let old = SigningKey::from_bytes(&[1; 32]).verifying_key();
+        let new = SigningKey::from_bytes(&[2; 32]).verifying_key();
+        let meta = TrustedKeyMeta {
+            display_name: "Release key".into(),
+            owner: "secops".into(),
+            ..Default::default()
+        };
+
+        store.add("release", &old, &meta).unwrap();
+        assert!(store.add("release", &new, &meta).is_err());
+        assert!(store.add("copy", &old, &meta).is_err());
+        assert!(store.add("../evil", &new, &meta).is_err());
+        assert_eq!(store.load("release.pub").unwrap().meta.owner, "secops");
+
+        store.add("release-2", &new, &meta).unwrap();
+        store.archive("release", "retired").unwrap();
+        assert!(store.load("release").is_none());
+        assert_eq!(fs::read_dir(dir.path().join("retired")).unwrap().count(), 2);
+
+        let mut expiring = meta.clone();
+        expiring.not_after = Some(Utc::now() - chrono::Duration::seconds(1));
+        store.write_meta("release-2", &expiring).unwrap();
+        let swept = store.sweep_expired(Utc::now());
+        assert_eq!(swept.len(), 1);
+        assert!(store.list().is_empty());
+    }
+}
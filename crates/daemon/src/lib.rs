@@ -1,4 +1,8 @@
 pub mod audit;
+pub mod config;
 pub mod dbus;
+pub mod devices;
+pub mod keystore;
 pub mod polkit;
+pub mod sequence;
 pub use dbus::DaemonState;
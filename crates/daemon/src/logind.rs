@@ -1,6 +1,6 @@
 use anyhow::Result;
 use futures_util::StreamExt;
-use zbus::Connection;
+use zbus::{Connection, SignalContext};
 
 // Listen to org.freedesktop.login1.Manager signals on system bus
 pub async fn run_logind_listener(
@@ -25,6 +25,10 @@ pub async fn run_logind_listener(
                     if going_to_sleep {
                         // Revoke all ephemeral approvals immediately
                         state.revoke_all_ephemeral().await;
+                        if let Ok(ctxt) = SignalContext::new(&connection, crate::dbus::OBJECT_PATH)
+                        {
+                            let _ = state.publish_changes(&ctxt).await;
+                        }
                     }
                 }
             }
@@ -2,12 +2,16 @@ use anyhow::Result;
 use tracing::info;
 
 mod dbus;
-use dbus::DaemonState;
+use dbus::{DaemonState, OBJECT_PATH};
+use lusby_backend_sysfs::SysfsBackend;
 use lusby_backend_usbguard::UsbguardBackend;
 mod audit;
+mod config;
+mod devices;
+mod keystore;
 mod logind;
 mod polkit;
-#[cfg(feature = "udev-monitor")]
+mod sequence;
 mod udev_monitor;
 
 #[tokio::main]
@@ -16,25 +20,60 @@ async fn main() -> Result<()> {
     info!(target: "lusby", event = "daemon_start", "lusby-daemon starting");
 
     // Register D-Bus service on system bus org.lusby.Daemon
-    let backend = UsbguardBackend;
-    let state = DaemonState::new(backend);
+    // Refuse to start on a broken config rather than enforce weaker defaults
+    let config = config::DaemonConfig::load(std::path::Path::new(config::DEFAULT_CONFIG_PATH))?;
+    let state = match config.backend.kind {
+        config::BackendKind::Usbguard => {
+            DaemonState::from_config(UsbguardBackend::default(), &config)
+        }
+        config::BackendKind::Sysfs => {
+            let backend = SysfsBackend::new(&config.backend.sysfs_root);
+            // Without usbguard, the kernel decides whether new devices start out authorized
+            if let Err(e) = backend.set_authorized_default(!config.policy.deny_unknown) {
+                tracing::warn!(error=?e, "cannot set authorized_default");
+            }
+            let state = DaemonState::from_config(backend, &config);
+            // The kernel keeps no rules, so baselines are enforced again on every start
+            if let Err(e) = state.restore_rules() {
+                tracing::warn!(error=?e, "cannot restore rules");
+            }
+            state
+        }
+    };
     // Keep a clone to use in background listeners
     let state_clone = state.clone();
     let connection = zbus::ConnectionBuilder::system()?
         .name("org.lusby.Daemon")?
-        .serve_at("/org/lusby/Daemon", state)?
+        .serve_at(OBJECT_PATH, state)?
+        .serve_at(OBJECT_PATH, zbus::fdo::ObjectManager)?
         .build()
         .await?;
+    if let Err(e) = state_clone.sync_device_objects(&connection).await {
+        tracing::warn!(error=?e, "cannot export device objects");
+    }
 
-    // Start udev listener to emit D-Bus signals (event-driven)
+    // Device signals come from the backend's event stream, or from udev if it has none
+    let events = state_clone.device_events();
     #[cfg(feature = "udev-monitor")]
-    {
-        let conn_clone = connection.clone();
-        tokio::spawn(async move {
-            if let Err(e) = udev_monitor::run_udev_listener(conn_clone).await {
-                tracing::error!(error=?e, "udev listener exited with error");
-            }
-        });
+    let events = events.or_else(|| match udev_monitor::event_stream(state_clone.backend()) {
+        Ok(stream) => Some(stream),
+        Err(e) => {
+            tracing::error!(error=?e, "cannot start udev monitor");
+            None
+        }
+    });
+    match events {
+        Some(events) => {
+            let conn_for_events = connection.clone();
+            let state_for_events = state_clone.clone();
+            tokio::spawn(async move {
+                state_for_events
+                    .run_device_events(&conn_for_events, events)
+                    .await;
+                tracing::error!("device event stream ended");
+            });
+        }
+        None => tracing::warn!("no device event source; devices refresh on the sweep only"),
     }
 
     // Start logind listener for suspend/lock to auto-revoke ephemeral approvals
@@ -46,6 +85,31 @@ async fn main() -> Result<()> {
         }
     });
 
+    // Periodically revoke expired grants, deactivate expired baselines, remove revoked devices
+    // and publish state; device objects are refreshed too, for backends without an event source
+    let state_for_sweep = state_clone.clone();
+    let conn_for_sweep = connection.clone();
+    let sweep_every = std::time::Duration::from_secs(config.policy.baseline_sweep_secs.max(1));
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(sweep_every);
+        loop {
+            interval.tick().await;
+            state_for_sweep.sweep_expired_grants().await;
+            state_for_sweep.sweep_expired_baselines().await;
+            state_for_sweep.sweep_expired_keys();
+            state_for_sweep.enforce_revocations().await;
+            if let Err(e) = state_for_sweep.sync_device_objects(&conn_for_sweep).await {
+                tracing::warn!(error=?e, "cannot refresh device objects");
+            }
+            // Also announces ephemeral grants that ran out and backend health changes
+            if let Ok(ctxt) = zbus::SignalContext::new(&conn_for_sweep, OBJECT_PATH) {
+                if let Err(e) = state_for_sweep.publish_changes(&ctxt).await {
+                    tracing::warn!(error=?e, "cannot emit PropertiesChanged");
+                }
+            }
+        }
+    });
+
     // Run until SIGINT/SIGTERM
     tokio::signal::ctrl_c().await?;
     info!("received ctrl_c, exiting");
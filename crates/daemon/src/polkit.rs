@@ -1,40 +1,68 @@
 use std::collections::HashMap;
 use zbus::message::Header;
-use zbus::zvariant::{OwnedObjectPath, OwnedValue};
+use zbus::zvariant::{OwnedValue, Value};
 use zbus::Connection;
 
-// Return true if the sender (from header) is authorized by polkit for org.lusby.manage
-pub async fn check_manage_authorization(
+use lusby_common::error::Error;
+
+pub const MANAGE_ACTION: &str = "org.lusby.manage";
+pub const ROLLBACK_ACTION: &str = "org.lusby.rollback";
+
+/// Fail with `NotAuthorized` unless polkit authorizes the sender for `action_id`.
+pub async fn require_authorization(
     conn: &Connection,
     header: &Header<'_>,
-) -> zbus::Result<bool> {
-    let sender = match header.sender() {
-        Some(s) => s,
-        None => return Ok(false),
-    };
-    // Query DBus daemon for the sender's Unix UID
+    action_id: &str,
+) -> Result<(), Error> {
+    match check_authorization(conn, header, action_id).await {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(Error::NotAuthorized(format!("polkit denied {}", action_id))),
+        Err(e) => Err(Error::NotAuthorized(format!(
+            "polkit check for {} failed: {}",
+            action_id, e
+        ))),
+    }
+}
+
+// Query the DBus daemon for the sender's Unix UID
+pub async fn sender_uid(conn: &Connection, header: &Header<'_>) -> Option<u32> {
+    let sender = header.sender()?;
     let dbus_proxy = zbus::Proxy::new(
         conn,
         "org.freedesktop.DBus",
         "/org/freedesktop/DBus",
         "org.freedesktop.DBus",
     )
-    .await?;
-    let uid: u32 = dbus_proxy
+    .await
+    .ok()?;
+    dbus_proxy
         .call("GetConnectionUnixUser", &(sender.clone()))
         .await
-        .unwrap_or(0);
+        .ok()
+}
+
+// Return true if the sender (from header) is authorized by polkit for `action_id`
+pub async fn check_authorization(
+    conn: &Connection,
+    header: &Header<'_>,
+    action_id: &str,
+) -> zbus::Result<bool> {
+    let Some(sender) = header.sender() else {
+        return Ok(false);
+    };
 
-    // Build polkit subject: ("unix-user", {"uid": <u32>}) with signature (sa{sv})
+    // Subject (sa{sv}): the calling bus name; polkit resolves its process and user itself
     let mut subject_details: HashMap<String, OwnedValue> = HashMap::new();
-    subject_details.insert("uid".to_string(), OwnedValue::from(uid));
-    let subject = ("unix-user", subject_details);
+    subject_details.insert(
+        "name".to_string(),
+        OwnedValue::try_from(Value::from(sender.as_str()))?,
+    );
+    let subject = ("system-bus-name", subject_details);
 
-    let action_id = "org.lusby.manage";
-    // details a{sv}
-    let details: HashMap<String, OwnedValue> = HashMap::new();
+    // details a{ss}, flags u, cancellation id s
+    let details: HashMap<String, String> = HashMap::new();
     let flags: u32 = 1; // AllowUserInteraction
-    let cancel: OwnedObjectPath = OwnedObjectPath::try_from("/org/lusby/Cancel").unwrap();
+    let cancel = "";
 
     // Call polkit
     let polkit = zbus::Proxy::new(
@@ -44,8 +72,8 @@ pub async fn check_manage_authorization(
         "org.freedesktop.PolicyKit1.Authority",
     )
     .await?;
-    // Returns (IsAuthorized: bool, IsChallenge: bool, Details: a{sv})
-    let (is_auth, _is_challenge, _ret_details): (bool, bool, HashMap<String, OwnedValue>) = polkit
+    // Returns (IsAuthorized: bool, IsChallenge: bool, Details: a{ss})
+    let (is_auth, _is_challenge, _ret_details): (bool, bool, HashMap<String, String>) = polkit
         .call(
             "CheckAuthorization",
             &(subject, action_id, details, flags, cancel),
@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Highest baseline sequence number applied per signer key id, persisted as JSON.
+pub struct SequenceStore {
+    path: PathBuf,
+}
+
+impl SequenceStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn load(&self) -> HashMap<String, u64> {
+        fs::read(&self.path)
+            .ok()
+            .and_then(|d| serde_json::from_slice(&d).ok())
+            .unwrap_or_default()
+    }
+
+    /// The first signer whose last applied sequence is newer than `sequence`, if any.
+    pub fn find_newer(&self, signers: &[String], sequence: u64) -> Option<(String, u64)> {
+        let seen = self.load();
+        signers.iter().find_map(|k| {
+            seen.get(k)
+                .filter(|last| **last > sequence)
+                .map(|last| (k.clone(), *last))
+        })
+    }
+
+    /// Record `sequence` for all `signers`. With `rollback`, lower values overwrite newer ones.
+    pub fn record(&self, signers: &[String], sequence: u64, rollback: bool) -> std::io::Result<()> {
+        let mut seen = self.load();
+        for k in signers {
+            let entry = seen.entry(k.clone()).or_insert(sequence);
+            if rollback || *entry < sequence {
+                *entry = sequence;
+            }
+        }
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        // Write-then-rename so a crash never leaves a truncated store behind
+        let tmp = self.path.with_extension("json.tmp");
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            let mut f = fs::OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .mode(0o600)
+                .open(&tmp)?;
+            f.write_all(&serde_json::to_vec_pretty(&seen)?)?;
+            f.sync_all()?;
+        }
+        fs::rename(tmp, &self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_older_and_allows_rollback() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SequenceStore::new(dir.path().join("sequences.json"));
+        let signers = vec!["sha256:aa".to_string(), "sha256:bb".to_string()];
+
+        assert!(store.find_newer(&signers, 0).is_none());
+        store.record(&signers, 5, false).unwrap();
+        assert!(store.find_newer(&signers, 5).is_none());
+        assert_eq!(
+            store.find_newer(&signers[1..], 4),
+            Some(("sha256:bb".into(), 5))
+        );
+
+        // Older values never lower the watermark unless rolling back
+        store.record(&signers, 3, false).unwrap();
+        assert!(store.find_newer(&signers, 4).is_some());
+        store.record(&signers, 3, true).unwrap();
+        assert!(store.find_newer(&signers, 3).is_none());
+    }
+}
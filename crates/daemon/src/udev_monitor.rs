@@ -1,218 +1,270 @@
-use std::os::unix::net::UnixDatagram;
+//! Device events from udev, for backends without an event source of their own.
+//!
+//! udev only knows sysfs devices, so each one is mapped to the backend's device (and id) by
+//! kernel name or by the fingerprint of its sysfs directory, which every backend derives the
+//! same way. Removals and policy changes are mapped back through the ids seen so far; devices
+//! present at startup are mapped first, so they can be told apart from identical newcomers.
+#![cfg_attr(not(feature = "udev-monitor"), allow(dead_code, unused_imports))]
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
 
-use anyhow::{Context, Result};
-use serde::{Deserialize, Serialize};
-use tokio::io::unix::AsyncFd;
-use zbus::Connection;
+use anyhow::{anyhow, Context, Result};
+use tokio::sync::mpsc;
 
-use lusby_common::fingerprint::{compute_fingerprint, FingerprintInput};
+use lusby_common::backend::{DeviceEvent, DeviceEventStream, UsbBackend};
+use lusby_common::fingerprint::FingerprintSource;
 use lusby_common::types::DeviceInfo;
 
-const DBUS_PATH: &str = "/org/lusby/Daemon";
+/// How often, and how far apart, an arrival is looked up before the backend has caught up.
+const RESOLVE_ATTEMPTS: u32 = 5;
+const RESOLVE_DELAY: Duration = Duration::from_millis(200);
+
+/// What happened to a udev USB device, as far as the async side cares.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    /// Present when the monitor started.
+    Existing,
+    Add,
+    Remove,
+    /// Attribute change or driver (un)binding, e.g. after `authorized` was written.
+    Change,
+}
 
-/// Minimal serialisable struct sent from the blocking thread to the async task.
-#[derive(Serialize, Deserialize, Debug)]
+/// The fields of a udev event that the async side needs.
+#[derive(Debug)]
 struct RawUdevEvent {
-    action: String,
-    devnode: String,
-    vendor_id: String,
-    product_id: String,
-    serial: String,
-    device_type: String,
-    vendor: Option<String>,
-    product: Option<String>,
+    action: Action,
+    syspath: PathBuf,
 }
 
-pub async fn run_udev_listener(connection: Connection) -> Result<()> {
-    // Create a Unix datagram pair for thread -> async notifications
-    let (sock1, sock2) = UnixDatagram::pair().context("creating unix datagram pair")?;
-    // Make the tokio async fd from one end (sock1)
-    let mut async_fd = AsyncFd::new(sock1).context("creating AsyncFd")?;
+impl RawUdevEvent {
+    #[cfg(feature = "udev-monitor")]
+    fn from_event(event: &udev::Event) -> Option<Self> {
+        let action = match event.event_type() {
+            udev::EventType::Add => Action::Add,
+            udev::EventType::Remove => Action::Remove,
+            udev::EventType::Change | udev::EventType::Bind | udev::EventType::Unbind => {
+                Action::Change
+            }
+            _ => return None,
+        };
+        Some(Self {
+            action,
+            syspath: event.syspath().to_path_buf(),
+        })
+    }
+
+    /// Kernel name of the device, e.g. `1-2`.
+    fn kernel_name(&self) -> &str {
+        self.syspath
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("")
+    }
+}
+
+/// The USB devices udev knows about now.
+#[cfg(feature = "udev-monitor")]
+fn existing_devices() -> std::io::Result<Vec<RawUdevEvent>> {
+    let mut enumerator = udev::Enumerator::new()?;
+    enumerator.match_subsystem("usb")?;
+    enumerator.match_property("DEVTYPE", "usb_device")?;
+    Ok(enumerator
+        .scan_devices()?
+        .map(|device| RawUdevEvent {
+            action: Action::Existing,
+            syspath: device.syspath().to_path_buf(),
+        })
+        .collect())
+}
 
-    // Spawn an OS thread that creates the udev monitor (so udev's non-Send types never cross thread boundary).
+/// Watch udev for USB devices coming, going and changing, and report them with `backend`'s
+/// ids.
+#[cfg(feature = "udev-monitor")]
+pub fn event_stream(backend: Arc<dyn UsbBackend>) -> Result<DeviceEventStream> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let (ready_tx, ready_rx) = std::sync::mpsc::channel();
+
+    // udev's types are not Send, so the monitor lives on its own thread.
     thread::spawn(move || {
-        // Create monitor inside this thread. This keeps the non-Send udev types on this thread only.
+        // Listen before enumerating so no arrival falls between the two
         let socket = match udev::MonitorBuilder::new()
-            .and_then(|b| b.match_subsystem("usb"))
+            .and_then(|b| b.match_subsystem_devtype("usb", "usb_device"))
             .and_then(|b| b.listen())
         {
-            Ok(s) => s,
+            Ok(socket) => {
+                let _ = ready_tx.send(Ok(()));
+                socket
+            }
             Err(e) => {
-                eprintln!("Failed to set up udev monitor: {}", e);
+                let _ = ready_tx.send(Err(e));
                 return;
             }
         };
-
-        // Iterate blocking for udev events, serialise some fields and send to the async side.
-        for event in socket.iter() {
-            // Build a RawUdevEvent with the fields we care about
-            let action = event
-                .action()
-                .and_then(|a| a.to_str())
-                .unwrap_or("unknown")
-                .to_string();
-            let devnode = event
-                .devnode()
-                .and_then(|p| p.to_str())
-                .unwrap_or("")
-                .to_string();
-
-            let vendor_id = event
-                .property_value("ID_VENDOR_ID")
-                .and_then(|s| s.to_str())
-                .map(|s| format!("0x{}", s))
-                .unwrap_or_default();
-            let product_id = event
-                .property_value("ID_MODEL_ID")
-                .and_then(|s| s.to_str())
-                .map(|s| format!("0x{}", s))
-                .unwrap_or_default();
-            let serial = event
-                .property_value("ID_SERIAL_SHORT")
-                .and_then(|s| s.to_str())
-                .unwrap_or("")
-                .to_string();
-            let device_type = event
-                .property_value("ID_USB_DRIVER")
-                .and_then(|s| s.to_str())
-                .unwrap_or("")
-                .to_string();
-
-            let vendor = event
-                .property_value("ID_VENDOR")
-                .and_then(|s| s.to_str())
-                .map(|s| s.to_string());
-            let product = event
-                .property_value("ID_MODEL")
-                .and_then(|s| s.to_str())
-                .map(|s| s.to_string());
-
-            let raw = RawUdevEvent {
-                action,
-                devnode,
-                vendor_id,
-                product_id,
-                serial,
-                device_type,
-                vendor,
-                product,
-            };
-
-            match serde_json::to_vec(&raw) {
-                Ok(j) => {
-                    // If send fails, print error and continue (peer may be closed on shutdown)
-                    if let Err(e) = sock2.send(&j) {
-                        eprintln!("Failed to send udev event to main thread: {}", e);
+        match existing_devices() {
+            Ok(devices) => {
+                for raw in devices {
+                    if tx.send(raw).is_err() {
+                        return;
                     }
                 }
-                Err(e) => {
-                    eprintln!("Failed to serialise udev event: {}", e);
+            }
+            Err(e) => tracing::warn!(error=?e, "cannot enumerate USB devices"),
+        }
+        loop {
+            for raw in socket.iter().filter_map(|e| RawUdevEvent::from_event(&e)) {
+                if tx.send(raw).is_err() {
+                    return;
                 }
             }
-
-            // Tiny sleep to avoid tight loop storms
-            thread::sleep(Duration::from_millis(10));
+            // The socket is non-blocking; wait for the next batch.
+            thread::sleep(Duration::from_millis(100));
         }
     });
+    ready_rx
+        .recv()
+        .map_err(|_| anyhow!("udev monitor thread exited"))?
+        .context("setting up udev monitor")?;
 
-    // Async loop: wait for datagrams, parse them and process the event.
-    let mut buf = vec![0u8; 16 * 1024]; // large enough buffer for the JSON payload
-    loop {
-        // Clone the underlying UnixDatagram (duplicates the fd) BEFORE taking the mutable read guard.
-        // The temporary immutable borrow taken by `get_ref()` ends immediately after try_clone() returns,
-        // so there is no overlap with the later mutable borrow `readable_mut()`.
-        let recv_sock = async_fd
-            .get_ref()
-            .try_clone()
-            .context("cloning unix datagram for recv")?;
-
-        // Now wait until the datagram socket is readable
-        let mut guard = async_fd.readable_mut().await?;
-
-        let result = guard.try_io(|_| {
-            match recv_sock.recv(&mut buf) {
-                Ok(len) => {
-                    if len == 0 {
-                        // peer closed
-                        return Ok(());
-                    }
+    Ok(Box::pin(futures_util::stream::unfold(
+        (rx, backend, HashMap::new()),
+        |(mut rx, backend, mut known)| async move {
+            loop {
+                let raw = rx.recv().await?;
+                if let Some(event) = translate(backend.as_ref(), &mut known, &raw).await {
+                    return Some((event, (rx, backend, known)));
+                }
+            }
+        },
+    )))
+}
 
-                    let slice = &buf[..len];
-                    match serde_json::from_slice::<RawUdevEvent>(slice) {
-                        Ok(raw) => {
-                            let fp = compute_fingerprint(&FingerprintInput {
-                                vendor_id: &raw.vendor_id,
-                                product_id: &raw.product_id,
-                                serial: if raw.serial.is_empty() {
-                                    None
-                                } else {
-                                    Some(&raw.serial)
-                                },
-                                manufacturer: raw.vendor.as_deref(),
-                                product: raw.product.as_deref(),
-                                raw_descriptors: None,
-                            });
-
-                            let info = DeviceInfo {
-                                id: raw.devnode.clone(),
-                                vendor_id: raw.vendor_id.clone(),
-                                product_id: raw.product_id.clone(),
-                                serial: raw.serial.clone(),
-                                fingerprint: fp,
-                                device_type: raw.device_type.clone(),
-                                allowed: false,
-                                persistent: false,
-                            };
-
-                            let conn = connection.clone();
-                            let info_clone = info.clone();
-                            let action = raw.action.clone();
-                            tokio::spawn(async move {
-                                if action == "add" || action == "bind" {
-                                    if let Err(e) = conn
-                                        .emit_signal(
-                                            Option::<&str>::None,
-                                            DBUS_PATH,
-                                            "org.lusby.Daemon",
-                                            "unknown_device_inserted",
-                                            &(&info_clone,),
-                                        )
-                                        .await
-                                    {
-                                        eprintln!(
-                                            "Failed to emit unknown_device_inserted signal: {}",
-                                            e
-                                        );
-                                    }
-                                } else if action == "remove" || action == "unbind" {
-                                    if let Err(e) = conn
-                                        .emit_signal(
-                                            Option::<&str>::None,
-                                            DBUS_PATH,
-                                            "org.lusby.Daemon",
-                                            "device_removed",
-                                            &(&info_clone.id,),
-                                        )
-                                        .await
-                                    {
-                                        eprintln!("Failed to emit DeviceRemoved signal: {}", e);
-                                    }
-                                }
-                            });
-                        }
-                        Err(e) => eprintln!("Failed to parse raw udev event JSON: {}", e),
-                    }
+/// The device event for `raw`, keeping `known` (syspath to backend id) up to date.
+async fn translate(
+    backend: &dyn UsbBackend,
+    known: &mut HashMap<PathBuf, String>,
+    raw: &RawUdevEvent,
+) -> Option<DeviceEvent> {
+    match raw.action {
+        Action::Existing | Action::Add => {
+            let device = resolve(backend, known, raw).await?;
+            known.insert(raw.syspath.clone(), device.id.clone());
+            (raw.action == Action::Add).then_some(DeviceEvent::Inserted(device))
+        }
+        Action::Remove => known
+            .remove(&raw.syspath)
+            .map(|device_id| DeviceEvent::Removed { device_id }),
+        Action::Change => {
+            let device_id = known.get(&raw.syspath)?;
+            backend
+                .get_device(device_id)
+                .await
+                .map(DeviceEvent::PolicyChanged)
+        }
+    }
+}
+
+/// The backend's view of a device udev announced; the backend may lag behind udev.
+async fn resolve(
+    backend: &dyn UsbBackend,
+    known: &HashMap<PathBuf, String>,
+    raw: &RawUdevEvent,
+) -> Option<DeviceInfo> {
+    let source = FingerprintSource::from_sysfs(&raw.syspath);
+    let fingerprint = source.as_ref().map(|s| s.fingerprint()).unwrap_or_default();
+    let claimed: Vec<&str> = known
+        .iter()
+        .filter(|(path, _)| **path != raw.syspath)
+        .map(|(_, id)| id.as_str())
+        .collect();
+    for attempt in 0..RESOLVE_ATTEMPTS {
+        if attempt > 0 {
+            tokio::time::sleep(RESOLVE_DELAY).await;
+        }
+        let devices = backend.list_devices().await;
+        if let Some(device) = match_device(&devices, raw.kernel_name(), &fingerprint, &claimed) {
+            let mut device = device.clone();
+            if device.fingerprint.is_empty() {
+                if let Some(source) = &source {
+                    device.fingerprint = source.fingerprint();
+                    device.legacy_fingerprint = source.legacy_fingerprint();
                 }
-                Err(e) => eprintln!("Error receiving on unix datagram: {}", e),
             }
-            Ok(())
-        });
+            return Some(device);
+        }
+    }
+    tracing::warn!(syspath = %raw.syspath.display(), "udev device not known to the backend");
+    None
+}
 
-        if let Err(e) = result {
-            eprintln!("Error processing udev notification: {:?}", e);
+/// Which of `devices` udev announced under `kernel_name`: the device with that id, for
+/// backends that use kernel names, or else the one device with its sysfs `fingerprint` whose
+/// id is not `claimed` by another udev device. Identical devices share a fingerprint, so
+/// while more than one of them is unclaimed none is picked rather than possibly the wrong one.
+fn match_device<'a>(
+    devices: &'a [DeviceInfo],
+    kernel_name: &str,
+    fingerprint: &str,
+    claimed: &[&str],
+) -> Option<&'a DeviceInfo> {
+    if let Some(device) = devices.iter().find(|d| d.id == kernel_name) {
+        return Some(device);
+    }
+    if fingerprint.is_empty() {
+        return None;
+    }
+    let mut candidates = devices
+        .iter()
+        .filter(|d| d.fingerprint == fingerprint && !claimed.contains(&d.id.as_str()));
+    match (candidates.next(), candidates.next()) {
+        (Some(device), None) => Some(device),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyboard(id: &str) -> DeviceInfo {
+        DeviceInfo {
+            id: id.into(),
+            vendor_id: "046d".into(),
+            product_id: "c31c".into(),
+            serial: String::new(),
+            fingerprint: "lfp2:same-model".into(),
+            device_type: "hid".into(),
+            allowed: false,
+            persistent: false,
+            interfaces: vec!["03:01:01".into()],
+            legacy_fingerprint: String::new(),
         }
-        guard.clear_ready();
+    }
+
+    #[test]
+    fn identical_devices_map_to_distinct_ids() {
+        let devices = vec![keyboard("5"), keyboard("6")];
+        // Neither is mapped yet: refusing beats picking the wrong one
+        assert!(match_device(&devices, "1-1", "lfp2:same-model", &[]).is_none());
+        // Once one is mapped (e.g. present at startup), a newcomer is the other
+        let found = match_device(&devices, "1-2", "lfp2:same-model", &["5"]).unwrap();
+        assert_eq!(found.id, "6");
+        assert!(match_device(&devices, "1-2", "lfp2:other-model", &["5"]).is_none());
+        assert!(match_device(&devices, "1-2", "", &["5"]).is_none());
+    }
+
+    #[test]
+    fn kernel_names_win_for_sysfs_style_ids() {
+        let devices = vec![keyboard("1-1"), keyboard("1-2")];
+        let raw = RawUdevEvent {
+            action: Action::Add,
+            syspath: "/sys/bus/usb/devices/1-2".into(),
+        };
+        let found = match_device(&devices, raw.kernel_name(), "", &[]).unwrap();
+        assert_eq!(found.id, "1-2");
     }
 }
@@ -1,5 +1,7 @@
 use anyhow::Result;
 use lusby_backend_mock::MockBackend;
+use lusby_backend_sysfs::SysfsBackend;
+use lusby_daemon::config::DaemonConfig;
 use lusby_daemon::DaemonState;
 
 #[test]
@@ -20,3 +22,43 @@ async fn dbus_name_conflict() -> Result<()> {
     assert!(result.is_err());
     Ok(())
 }
+
+#[tokio::test]
+async fn expired_grants_deauthorize_sysfs_devices() {
+    let dir = tempfile::tempdir().unwrap();
+    let device = dir.path().join("sys/bus/usb/devices/1-2");
+    std::fs::create_dir_all(&device).unwrap();
+    for (name, value) in [
+        ("idVendor", "046d\n"),
+        ("idProduct", "c534\n"),
+        ("authorized", "0\n"),
+        ("remove", ""),
+    ] {
+        std::fs::write(device.join(name), value).unwrap();
+    }
+    let mut config = DaemonConfig::default();
+    config.paths.audit_log = dir.path().join("audit.log");
+    config.paths.state_dir = dir.path().join("state");
+    config.paths.baselines_system = dir.path().join("baselines");
+    config.paths.trusted_pubkeys = dir.path().join("keys");
+    let state = DaemonState::from_config(SysfsBackend::new(dir.path().join("sys")), &config);
+
+    state.grant_ephemeral("1-2", 1, 1000).await.unwrap();
+    assert_eq!(
+        std::fs::read_to_string(device.join("authorized")).unwrap(),
+        "1"
+    );
+    // The kernel keeps a device authorized until told otherwise
+    state.sweep_expired_grants().await;
+    assert_eq!(
+        std::fs::read_to_string(device.join("authorized")).unwrap(),
+        "1"
+    );
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+    state.sweep_expired_grants().await;
+    assert_eq!(
+        std::fs::read_to_string(device.join("authorized")).unwrap(),
+        "0"
+    );
+    assert_eq!(state.ephemeral_count(), 0);
+}
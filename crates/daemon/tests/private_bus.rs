@@ -0,0 +1,789 @@
+//! End-to-end tests over a private bus (see `support`): calls go through `dbus-daemon`, so
+//! sender names, polkit checks, signals and property notifications behave as on the system
+//! bus.
+
+mod support;
+
+use base64::Engine as _;
+use ed25519_dalek::SigningKey;
+use futures_util::StreamExt;
+use lusby_backend_mock::{MockBackend, MockCall, MockOp};
+use lusby_client::{Error, PATH};
+use lusby_common::baseline::{Baseline, DeviceEntry};
+use lusby_common::revocation::RevocationList;
+use lusby_common::schema::BASELINE_VERSION;
+use lusby_common::types::DeviceInfo;
+use lusby_daemon::polkit::{MANAGE_ACTION, ROLLBACK_ACTION};
+use lusby_daemon::DaemonState;
+use support::Harness;
+use zbus::SignalContext;
+
+fn key(seed: u8) -> (SigningKey, String) {
+    let sk = SigningKey::from_bytes(&[seed; 32]);
+    let b64 = base64::engine::general_purpose::STANDARD.encode(sk.verifying_key().to_bytes());
+    (sk, b64)
+}
+
+fn signed_baseline(sk: &SigningKey, sequence: u64) -> Baseline {
+    let mut b = Baseline {
+        version: BASELINE_VERSION,
+        created_by: "ops".into(),
+        created_at: chrono::Utc::now(),
+        devices: vec![DeviceEntry {
+            vendor_id: "046d".into(),
+            product_id: "c534".into(),
+            serial: Some("ABC".into()),
+            bus_path: None,
+            descriptors_hash: String::new(),
+            device_type: "hid".into(),
+            comment: None,
+        }],
+        not_before: None,
+        not_after: None,
+        hosts: Vec::new(),
+        sequence: Some(sequence),
+        key_id: None,
+        signature: None,
+        signatures: Vec::new(),
+    };
+    b.sign_attach(sk).unwrap();
+    b
+}
+
+fn write_json<T: serde::Serialize>(h: &Harness, name: &str, value: &T) -> String {
+    let path = h.path(name);
+    std::fs::write(&path, serde_json::to_vec(value).unwrap()).unwrap();
+    path.display().to_string()
+}
+
+#[tokio::test]
+async fn ephemeral_allow_and_revoke_are_audited() {
+    let Some(h) = Harness::start().await else {
+        return;
+    };
+    let proxy = h.proxy().await;
+
+    proxy
+        .request_ephemeral_allow("dev1", 60, 1000)
+        .await
+        .unwrap();
+    let grants = proxy.active_ephemeral_grants().await.unwrap();
+    assert_eq!(grants.len(), 1);
+    assert_eq!(grants[0].requester_uid, 1000);
+    proxy.revoke_device("dev1").await.unwrap();
+    assert!(proxy.active_ephemeral_grants().await.unwrap().is_empty());
+
+    assert!(matches!(
+        proxy.request_ephemeral_allow("dev1", 0, 1000).await,
+        Err(Error::InvalidInput(_))
+    ));
+    assert!(matches!(
+        proxy.request_ephemeral_allow("dev1", 60, 0).await,
+        Err(Error::InvalidInput(_))
+    ));
+    assert!(matches!(
+        proxy.revoke_device("").await,
+        Err(Error::InvalidInput(_))
+    ));
+    assert!(matches!(
+        proxy.get_device_info("dev9").await,
+        Err(Error::NotFound(_))
+    ));
+
+    assert!(h.audited("ephemeral_allow", "allow_ok"));
+    // Devices are audited by the fingerprint clients show
+    assert!(h.audit().iter().any(|e| e.payload.event_type == "revoke"
+        && e.payload.device_fingerprint.as_deref() == Some("sha256:deadbeef")));
+    assert!(h.audited("revoke", "revoke_ok"));
+    assert!(h.audited("ephemeral_allow_reject", "invalid_input"));
+    assert!(h.audited("revoke_reject", "invalid_input"));
+    assert!(lusby_common::audit::verify_chain(&h.audit()));
+}
+
+#[tokio::test]
+async fn expired_grants_are_revoked_by_the_sweep() {
+    let Some(h) = Harness::start().await else {
+        return;
+    };
+    let proxy = h.proxy().await;
+    proxy
+        .request_ephemeral_allow("dev1", 1, 1000)
+        .await
+        .unwrap();
+    assert!(proxy.get_device_info("dev1").await.unwrap().allowed);
+
+    // The backend does not enforce the TTL; the sweep must
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+    assert!(proxy.active_ephemeral_grants().await.unwrap().is_empty());
+    h.state.sweep_expired_grants().await;
+    assert!(h.backend.calls().contains(&MockCall::Revoke("dev1".into())));
+    assert!(!proxy.get_device_info("dev1").await.unwrap().allowed);
+    assert!(h.audited("ephemeral_expire", "revoke_ok"));
+    assert_eq!(h.state.ephemeral_count(), 0);
+}
+
+#[tokio::test]
+async fn device_allow_records_the_calling_uid() {
+    let Some(h) = Harness::start().await else {
+        return;
+    };
+    h.state.sync_device_objects(&h.daemon).await.unwrap();
+    let device = lusby_client::DeviceProxy::builder(&h.client)
+        .path(lusby_daemon::devices::device_path("dev1"))
+        .unwrap()
+        .build()
+        .await
+        .unwrap();
+    device.allow(60).await.unwrap();
+
+    use std::os::unix::fs::MetadataExt;
+    let own_uid = std::fs::metadata(h.dir.path()).unwrap().uid();
+    let grants = h.proxy().await.active_ephemeral_grants().await.unwrap();
+    assert_eq!(grants[0].requester_uid, own_uid);
+    assert!(h
+        .audit()
+        .iter()
+        .any(|e| e.payload.event_type == "ephemeral_allow"
+            && e.payload.requester_uid == Some(own_uid)));
+}
+
+#[tokio::test]
+async fn privileged_calls_ask_polkit_about_the_caller() {
+    let Some(h) = Harness::start().await else {
+        return;
+    };
+    let proxy = h.proxy().await;
+
+    assert!(matches!(
+        proxy.list_trusted_keys().await,
+        Err(Error::NotAuthorized(_))
+    ));
+    let check = h.polkit.checks().pop().unwrap();
+    assert_eq!(check.action_id, MANAGE_ACTION);
+    assert_eq!(check.subject_kind, "system-bus-name");
+    assert_eq!(check.bus_name.as_deref(), Some(h.client_name().as_str()));
+
+    h.polkit.allow(MANAGE_ACTION);
+    assert_eq!(proxy.list_trusted_keys().await.unwrap(), "[]");
+    assert!(proxy.list_trusted_pubkeys().await.unwrap().is_empty());
+    h.polkit.deny(MANAGE_ACTION);
+    assert!(matches!(
+        proxy
+            .apply_persistent_allow("/nonexistent.json", "ops")
+            .await,
+        Err(Error::NotAuthorized(_))
+    ));
+    assert!(h.audited("policy_denied", "polkit_denied"));
+}
+
+#[tokio::test]
+async fn signed_baselines_apply_and_replays_are_refused() {
+    let Some(h) = Harness::start().await else {
+        return;
+    };
+    let proxy = h.proxy().await;
+    h.polkit.allow(MANAGE_ACTION);
+    let (sk, pk) = key(7);
+    proxy
+        .add_trusted_key("ops", &pk, r#"{"owner":"it"}"#)
+        .await
+        .unwrap();
+    assert!(proxy.list_trusted_keys().await.unwrap().contains("\"it\""));
+
+    let path = write_json(&h, "b2.json", &signed_baseline(&sk, 2));
+    proxy.apply_persistent_allow(&path, "ops").await.unwrap();
+    assert_eq!(proxy.baseline_count().await.unwrap(), 1);
+    assert!(h.backend.applied_rules().contains("046d:c534"));
+    assert!(h.audited("persistent_allow", "baseline_applied"));
+
+    // Error paths: unknown signer, tampering, replay, missing file, rollback without rights
+    assert!(matches!(
+        proxy.apply_persistent_allow(&path, "nobody").await,
+        Err(Error::NotFound(_))
+    ));
+    let mut tampered = signed_baseline(&sk, 3);
+    tampered.devices[0].serial = Some("XYZ".into());
+    let tampered = write_json(&h, "tampered.json", &tampered);
+    assert!(matches!(
+        proxy.apply_persistent_allow(&tampered, "ops").await,
+        Err(Error::SignatureInvalid(_))
+    ));
+    let old = write_json(&h, "b1.json", &signed_baseline(&sk, 1));
+    assert!(matches!(
+        proxy.apply_persistent_allow(&old, "ops").await,
+        Err(Error::PolicyDenied(_))
+    ));
+    assert!(matches!(
+        proxy
+            .apply_persistent_allow(&h.path("missing.json").display().to_string(), "ops")
+            .await,
+        Err(Error::NotFound(_))
+    ));
+    assert!(matches!(
+        proxy.rollback_persistent_allow(&old, "ops").await,
+        Err(Error::NotAuthorized(_))
+    ));
+    h.polkit.allow(ROLLBACK_ACTION);
+    proxy.rollback_persistent_allow(&old, "ops").await.unwrap();
+    assert!(h.audited("persistent_allow", "baseline_rolled_back"));
+
+    let diff = proxy
+        .diff_active_baseline(&serde_json::to_string(&signed_baseline(&sk, 4)).unwrap())
+        .await
+        .unwrap();
+    assert!(serde_json::from_str::<serde_json::Value>(&diff).is_ok());
+}
+
+#[tokio::test]
+async fn baselines_are_not_installed_without_their_sequence() {
+    let Some(h) = Harness::start().await else {
+        return;
+    };
+    let proxy = h.proxy().await;
+    h.polkit.allow(MANAGE_ACTION);
+    let (sk, pk) = key(7);
+    proxy.add_trusted_key("ops", &pk, "{}").await.unwrap();
+
+    // A directory where the sequence store belongs makes recording fail
+    std::fs::create_dir_all(h.config.paths.state_dir.join("sequences.json")).unwrap();
+    let path = write_json(&h, "b2.json", &signed_baseline(&sk, 2));
+    assert!(matches!(
+        proxy.apply_persistent_allow(&path, "ops").await,
+        Err(Error::BackendFailed(_))
+    ));
+    assert_eq!(proxy.baseline_count().await.unwrap(), 0);
+    assert!(!h.backend.applied_rules().contains("046d:c534"));
+    assert!(h.audited("security", "baseline_sequence_record_failed"));
+}
+
+#[tokio::test]
+async fn baseline_count_follows_enforcement() {
+    let Some(h) = Harness::start().await else {
+        return;
+    };
+    let proxy = h.proxy().await;
+    h.polkit.allow(MANAGE_ACTION);
+    let (sk, pk) = key(7);
+    proxy.add_trusted_key("ops", &pk, "{}").await.unwrap();
+    // Change streams need a caching proxy
+    let watcher = lusby_client::DaemonProxy::new(&h.client).await.unwrap();
+    let mut counts = watcher.receive_baseline_count_changed().await;
+    assert_eq!(counts.next().await.unwrap().get().await.unwrap(), 0);
+
+    let mut baseline = signed_baseline(&sk, 1);
+    baseline.not_after = Some(chrono::Utc::now() + chrono::Duration::seconds(1));
+    baseline.signatures.clear();
+    baseline.sign_attach(&sk).unwrap();
+    let path = write_json(&h, "b1.json", &baseline);
+    proxy.apply_persistent_allow(&path, "ops").await.unwrap();
+    assert_eq!(counts.next().await.unwrap().get().await.unwrap(), 1);
+
+    // Still installed once expired, but no longer enforced; the sweep announces it
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+    assert_eq!(h.state.active_baselines().len(), 1);
+    let ctxt = SignalContext::new(&h.daemon, PATH).unwrap();
+    h.state.publish_changes(&ctxt).await.unwrap();
+    assert_eq!(counts.next().await.unwrap().get().await.unwrap(), 0);
+    assert_eq!(proxy.baseline_count().await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn key_management_round_trip() {
+    let Some(h) = Harness::start().await else {
+        return;
+    };
+    let proxy = h.proxy().await;
+    h.polkit.allow(MANAGE_ACTION);
+    let (sk, pk) = key(1);
+    let (_, pk2) = key(2);
+    let (_, legacy) = key(3);
+
+    proxy.add_trusted_key("ops", &pk, "{}").await.unwrap();
+    proxy.add_trusted_pubkey("legacy", &legacy).await.unwrap();
+    assert_eq!(
+        proxy.list_trusted_pubkeys().await.unwrap().len(),
+        2,
+        "both keys are listed"
+    );
+    assert!(matches!(
+        proxy.add_trusted_key("bad", "not base64", "{}").await,
+        Err(Error::InvalidInput(_))
+    ));
+    proxy
+        .set_trusted_key_scope("ops", vec!["hid".into()])
+        .await
+        .unwrap();
+    assert!(matches!(
+        proxy.set_trusted_key_scope("nobody", vec![]).await,
+        Err(Error::NotFound(_))
+    ));
+
+    // An active baseline depends on `ops`, so retiring it needs `force`
+    let path = write_json(&h, "b.json", &signed_baseline(&sk, 1));
+    proxy.apply_persistent_allow(&path, "ops").await.unwrap();
+    // If the old key cannot be linked to the new one, the new key is not left trusted
+    let blocker = h.config.paths.trusted_pubkeys.join("ops.json.tmp");
+    std::fs::create_dir(&blocker).unwrap();
+    assert!(matches!(
+        proxy.rotate_trusted_key("ops", "ops-2", &pk2).await,
+        Err(Error::BackendFailed(_))
+    ));
+    assert!(!proxy
+        .list_trusted_pubkeys()
+        .await
+        .unwrap()
+        .contains(&"ops-2.pub".to_string()));
+    assert!(h.audited("trusted_key", "new key removed"));
+    std::fs::remove_dir(&blocker).unwrap();
+    proxy
+        .rotate_trusted_key("ops", "ops-2", &pk2)
+        .await
+        .unwrap();
+    assert!(matches!(
+        proxy.retire_trusted_key("ops", false).await,
+        Err(Error::PolicyDenied(_))
+    ));
+    proxy.retire_trusted_key("ops", true).await.unwrap();
+
+    proxy.remove_trusted_pubkey("legacy").await.unwrap();
+    assert!(matches!(
+        proxy.remove_trusted_pubkey("legacy").await,
+        Err(Error::NotFound(_))
+    ));
+    assert_eq!(
+        proxy.list_trusted_pubkeys().await.unwrap(),
+        vec!["ops-2.pub".to_string()]
+    );
+    for action in [
+        "added",
+        "rotation_started",
+        "retire_refused",
+        "retired",
+        "removed",
+    ] {
+        assert!(
+            h.audit().iter().any(|e| e.payload.action.contains(action)),
+            "no audit entry for {}",
+            action
+        );
+    }
+}
+
+#[tokio::test]
+async fn revocation_lists_block_devices() {
+    let Some(h) = Harness::start().await else {
+        return;
+    };
+    let proxy = h.proxy().await;
+    h.polkit.allow(MANAGE_ACTION);
+    let (sk, pk) = key(9);
+    proxy.add_trusted_key("secops", &pk, "{}").await.unwrap();
+    assert_eq!(proxy.get_revocation_list().await.unwrap(), "");
+
+    let mut list = RevocationList {
+        version: 1,
+        created_by: "secops".into(),
+        created_at: chrono::Utc::now(),
+        sequence: 1,
+        fingerprints: vec!["sha256:deadbeef".into()],
+        devices: Vec::new(),
+        revoked_keys: Vec::new(),
+        signatures: Vec::new(),
+    };
+    list.sign_attach(&sk).unwrap();
+    let path = write_json(&h, "revocation.json", &list);
+    proxy.apply_revocation_list(&path).await.unwrap();
+    let applied: RevocationList =
+        serde_json::from_str(&proxy.get_revocation_list().await.unwrap()).unwrap();
+    assert_eq!(applied.fingerprints, list.fingerprints);
+    assert!(h.audited("revocation", "revoked_device_blocked"));
+
+    // Stored for root only
+    let stored = h.config.paths.state_dir.join("revocation.json");
+    use std::os::unix::fs::PermissionsExt;
+    assert_eq!(
+        std::fs::metadata(&stored).unwrap().permissions().mode() & 0o777,
+        0o600
+    );
+
+    // The same list again is fine; other content under the same sequence is not
+    proxy.apply_revocation_list(&path).await.unwrap();
+    let mut swapped = list.clone();
+    swapped.fingerprints.clear();
+    swapped.signatures.clear();
+    swapped.sign_attach(&sk).unwrap();
+    let swapped = write_json(&h, "swapped.json", &swapped);
+    assert!(matches!(
+        proxy.apply_revocation_list(&swapped).await,
+        Err(Error::PolicyDenied(_))
+    ));
+
+    list.signatures.clear();
+    let unsigned = write_json(&h, "unsigned.json", &list);
+    assert!(matches!(
+        proxy.apply_revocation_list(&unsigned).await,
+        Err(Error::SignatureInvalid(_))
+    ));
+
+    // Keys scoped to a device type cannot sign lists that may revoke every other key
+    let (hid_sk, hid_pk) = key(10);
+    proxy
+        .add_trusted_key("hid-only", &hid_pk, r#"{"device_types":["hid"]}"#)
+        .await
+        .unwrap();
+    let mut lockout = list.clone();
+    lockout.sequence = 2;
+    lockout.revoked_keys = vec![lusby_common::crypto::key_id(&sk.verifying_key())];
+    lockout.sign_attach(&hid_sk).unwrap();
+    let lockout = write_json(&h, "lockout.json", &lockout);
+    assert!(matches!(
+        proxy.apply_revocation_list(&lockout).await,
+        Err(Error::SignatureInvalid(_))
+    ));
+}
+
+#[tokio::test]
+async fn revocation_lists_need_the_configured_signatures() {
+    let backend = MockBackend::new_with_sample();
+    let Some(h) = Harness::start_configured(backend, |c| {
+        c.policy.revocation_signatures = Some(2);
+    })
+    .await
+    else {
+        return;
+    };
+    let proxy = h.proxy().await;
+    h.polkit.allow(MANAGE_ACTION);
+    let (sk1, pk1) = key(11);
+    let (sk2, pk2) = key(12);
+    proxy.add_trusted_key("secops1", &pk1, "{}").await.unwrap();
+    proxy.add_trusted_key("secops2", &pk2, "{}").await.unwrap();
+
+    let mut list = RevocationList {
+        version: 1,
+        created_by: "secops".into(),
+        created_at: chrono::Utc::now(),
+        sequence: 1,
+        fingerprints: vec!["lfp2:00".into()],
+        devices: Vec::new(),
+        revoked_keys: Vec::new(),
+        signatures: Vec::new(),
+    };
+    list.sign_attach(&sk1).unwrap();
+    let one = write_json(&h, "one.json", &list);
+    assert!(matches!(
+        proxy.apply_revocation_list(&one).await,
+        Err(Error::PolicyDenied(_))
+    ));
+    assert!(h.audited("security", "revocation_insufficient_signatures: 1/2"));
+    list.sign_attach(&sk2).unwrap();
+    let two = write_json(&h, "two.json", &list);
+    proxy.apply_revocation_list(&two).await.unwrap();
+}
+
+#[tokio::test]
+async fn unreadable_revocation_lists_fail_closed() {
+    let Some(h) = Harness::start().await else {
+        return;
+    };
+    let proxy = h.proxy().await;
+    h.polkit.allow(MANAGE_ACTION);
+    let (sk, pk) = key(13);
+    proxy.add_trusted_key("ops", &pk, "{}").await.unwrap();
+    std::fs::create_dir_all(&h.config.paths.state_dir).unwrap();
+    std::fs::write(
+        h.config.paths.state_dir.join("revocation.json"),
+        b"{corrupt",
+    )
+    .unwrap();
+
+    assert!(matches!(
+        proxy.request_ephemeral_allow("dev1", 60, 1000).await,
+        Err(Error::BackendFailed(_))
+    ));
+    assert!(!proxy.get_device_info("dev1").await.unwrap().allowed);
+    let baseline = write_json(&h, "b1.json", &signed_baseline(&sk, 1));
+    assert!(matches!(
+        proxy.apply_persistent_allow(&baseline, "ops").await,
+        Err(Error::BackendFailed(_))
+    ));
+    // A list with any sequence would otherwise replace the one that cannot be read
+    let mut list = RevocationList {
+        version: 1,
+        created_by: "ops".into(),
+        created_at: chrono::Utc::now(),
+        sequence: 1,
+        fingerprints: Vec::new(),
+        devices: Vec::new(),
+        revoked_keys: Vec::new(),
+        signatures: Vec::new(),
+    };
+    list.sign_attach(&sk).unwrap();
+    let list = write_json(&h, "list.json", &list);
+    assert!(matches!(
+        proxy.apply_revocation_list(&list).await,
+        Err(Error::BackendFailed(_))
+    ));
+
+    let ctxt = SignalContext::new(&h.daemon, PATH).unwrap();
+    h.state.publish_changes(&ctxt).await.unwrap();
+    assert!(!proxy.backend_healthy().await.unwrap());
+    assert!(h.audited("security", "revocation_list_unreadable"));
+    assert!(h.audited("ephemeral_allow_reject", "revocation_list_unreadable"));
+    // Audited once, not on every read
+    let reports = h
+        .audit()
+        .iter()
+        .filter(|e| {
+            e.payload.event_type == "security"
+                && e.payload.action.starts_with("revocation_list_unreadable")
+        })
+        .count();
+    assert_eq!(reports, 1);
+}
+
+#[tokio::test]
+async fn signals_and_property_changes_reach_bus_clients() {
+    let Some(h) = Harness::start().await else {
+        return;
+    };
+    let proxy = lusby_client::DaemonProxy::new(&h.client).await.unwrap();
+    let mut inserted = proxy.receive_unknown_device_inserted().await.unwrap();
+    let mut removed = proxy.receive_device_removed().await.unwrap();
+    let mut grants = proxy.receive_active_ephemeral_grants_changed().await;
+    assert!(grants.next().await.unwrap().get().await.unwrap().is_empty());
+
+    let ctxt = SignalContext::new(&h.daemon, PATH).unwrap();
+    let device: DeviceInfo = proxy.get_device_info("dev1").await.unwrap();
+    DaemonState::unknown_device_inserted(&ctxt, &device)
+        .await
+        .unwrap();
+    DaemonState::device_removed(&ctxt, "dev1").await.unwrap();
+    assert_eq!(
+        inserted.next().await.unwrap().args().unwrap().device,
+        device
+    );
+    assert_eq!(
+        removed.next().await.unwrap().args().unwrap().device_id,
+        "dev1"
+    );
+
+    proxy
+        .request_ephemeral_allow("dev1", 60, 1000)
+        .await
+        .unwrap();
+    let granted = grants.next().await.unwrap().get().await.unwrap();
+    assert_eq!(granted[0].device_id, "dev1");
+
+    // Device objects are announced through the ObjectManager
+    let manager = zbus::fdo::ObjectManagerProxy::builder(&h.client)
+        .destination(lusby_client::SERVICE)
+        .unwrap()
+        .path(PATH)
+        .unwrap()
+        .build()
+        .await
+        .unwrap();
+    let mut added = manager.receive_interfaces_added().await.unwrap();
+    h.state.sync_device_objects(&h.daemon).await.unwrap();
+    let added = added.next().await.unwrap();
+    let args = added.args().unwrap();
+    assert!(args
+        .object_path()
+        .as_str()
+        .starts_with(lusby_client::DEVICES_PATH));
+}
+
+#[tokio::test]
+async fn simulated_plug_and_unplug_reach_clients() {
+    let Some(h) = Harness::start_with(MockBackend::default()).await else {
+        return;
+    };
+    h.forward_mock_events();
+    let proxy = h.proxy().await;
+    let mut inserted = proxy.receive_unknown_device_inserted().await.unwrap();
+    let mut removed = proxy.receive_device_removed().await.unwrap();
+    let manager = zbus::fdo::ObjectManagerProxy::builder(&h.client)
+        .destination(lusby_client::SERVICE)
+        .unwrap()
+        .path(PATH)
+        .unwrap()
+        .build()
+        .await
+        .unwrap();
+    let mut added = manager.receive_interfaces_added().await.unwrap();
+    let mut dropped = manager.receive_interfaces_removed().await.unwrap();
+
+    h.backend.plug(MockBackend::sample_device());
+    assert_eq!(
+        inserted.next().await.unwrap().args().unwrap().device.id,
+        "dev1"
+    );
+    let path = added
+        .next()
+        .await
+        .unwrap()
+        .args()
+        .unwrap()
+        .object_path()
+        .to_owned();
+    let device = lusby_client::DeviceProxy::builder(&h.client)
+        .path(path.clone())
+        .unwrap()
+        .build()
+        .await
+        .unwrap();
+    let mut states = device.receive_state_changed().await;
+    proxy
+        .request_ephemeral_allow("dev1", 60, 1000)
+        .await
+        .unwrap();
+    assert!(h.backend.devices()[0].allowed);
+    // The backend's policy-changed event updates the exported object
+    assert_eq!(states.next().await.unwrap().get().await.unwrap(), "allowed");
+
+    h.backend.unplug("dev1");
+    assert_eq!(
+        removed.next().await.unwrap().args().unwrap().device_id,
+        "dev1"
+    );
+    assert_eq!(
+        *dropped.next().await.unwrap().args().unwrap().object_path(),
+        path
+    );
+    assert!(proxy.list_devices().await.unwrap().is_empty());
+    assert!(proxy.active_ephemeral_grants().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn only_unmatched_blocked_devices_are_unknown() {
+    let Some(h) = Harness::start_with(MockBackend::default()).await else {
+        return;
+    };
+    h.forward_mock_events();
+    let proxy = h.proxy().await;
+    h.polkit.allow(MANAGE_ACTION);
+    let (sk, pk) = key(9);
+    proxy.add_trusted_key("ops", &pk, "{}").await.unwrap();
+    let path = write_json(&h, "b.json", &signed_baseline(&sk, 1));
+    proxy.apply_persistent_allow(&path, "ops").await.unwrap();
+    let mut unknown = proxy.receive_unknown_device_inserted().await.unwrap();
+    let mut known = proxy.receive_known_device_inserted().await.unwrap();
+
+    let device = |id: &str, vendor_id: &str, interface: &str, allowed: bool| DeviceInfo {
+        id: id.into(),
+        vendor_id: vendor_id.into(),
+        product_id: "0002".into(),
+        serial: String::new(),
+        fingerprint: String::new(),
+        device_type: String::new(),
+        allowed,
+        persistent: false,
+        interfaces: vec![interface.into()],
+        legacy_fingerprint: String::new(),
+    };
+    h.backend.plug(device("1", "1d6b", "09:00:00", false));
+    h.backend.plug(device("2", "0781", "08:06:50", true));
+    h.backend.plug(MockBackend::sample_device());
+    let args = known.next().await.unwrap();
+    let args = args.args().unwrap();
+    assert_eq!(args.device.id, "dev1");
+    let entry: DeviceEntry = serde_json::from_str(&args.entry).unwrap();
+    assert_eq!(entry.serial.as_deref(), Some("ABC"));
+
+    // The hub, the allowed device and the known one were skipped
+    h.backend.plug(device("3", "0781", "08:06:50", false));
+    assert_eq!(unknown.next().await.unwrap().args().unwrap().device.id, "3");
+}
+
+#[tokio::test]
+async fn revoked_devices_outrank_baselines_on_insertion() {
+    let Some(h) = Harness::start_with(MockBackend::default()).await else {
+        return;
+    };
+    h.forward_mock_events();
+    let proxy = h.proxy().await;
+    h.polkit.allow(MANAGE_ACTION);
+    let (sk, pk) = key(9);
+    proxy.add_trusted_key("ops", &pk, "{}").await.unwrap();
+    let path = write_json(&h, "b.json", &signed_baseline(&sk, 1));
+    proxy.apply_persistent_allow(&path, "ops").await.unwrap();
+    // Banned by fingerprint only, so the generated rules do not cover it
+    let mut list = RevocationList {
+        version: 1,
+        created_by: "secops".into(),
+        created_at: chrono::Utc::now(),
+        sequence: 1,
+        fingerprints: vec!["sha256:deadbeef".into()],
+        devices: Vec::new(),
+        revoked_keys: Vec::new(),
+        signatures: Vec::new(),
+    };
+    list.sign_attach(&sk).unwrap();
+    let list = write_json(&h, "revocation.json", &list);
+    proxy.apply_revocation_list(&list).await.unwrap();
+    let mut unknown = proxy.receive_unknown_device_inserted().await.unwrap();
+    let mut known = proxy.receive_known_device_inserted().await.unwrap();
+
+    // The sample matches the baseline entry but its fingerprint is revoked
+    let mut sample = MockBackend::sample_device();
+    sample.allowed = true;
+    h.backend.plug(sample);
+    let mut other = MockBackend::sample_device();
+    other.id = "dev2".into();
+    other.vendor_id = "0781".into();
+    other.fingerprint = String::new();
+    h.backend.plug(other);
+    // Events are handled in order, so the revoked device was dealt with first
+    assert_eq!(
+        unknown.next().await.unwrap().args().unwrap().device.id,
+        "dev2"
+    );
+    assert!(
+        tokio::time::timeout(std::time::Duration::from_millis(100), known.next())
+            .await
+            .is_err()
+    );
+    assert!(h.backend.calls().contains(&MockCall::Revoke("dev1".into())));
+    assert!(!proxy.get_device_info("dev1").await.unwrap().allowed);
+    assert!(h.audited("revocation", "revoked_device_blocked"));
+
+    assert!(matches!(
+        proxy.request_ephemeral_allow("dev1", 60, 1000).await,
+        Err(Error::PolicyDenied(_))
+    ));
+    assert!(h.audited("ephemeral_allow_reject", "revoked"));
+}
+
+#[tokio::test]
+async fn backend_failures_surface_as_errors() {
+    let Some(h) = Harness::start().await else {
+        return;
+    };
+    let proxy = h.proxy().await;
+    h.backend.fail(MockOp::AllowEphemeral);
+    assert!(matches!(
+        proxy.request_ephemeral_allow("dev1", 60, 1000).await,
+        Err(Error::BackendFailed(_))
+    ));
+    assert!(h.audited("ephemeral_allow", "allow_fail"));
+    assert!(h.backend.calls().contains(&MockCall::AllowEphemeral {
+        device_id: "dev1".into(),
+        ttl_secs: 60
+    }));
+
+    // Health is re-checked whenever the daemon publishes its state
+    h.backend.fail(MockOp::Healthy);
+    proxy.revoke_device("dev1").await.unwrap();
+    assert!(!proxy.backend_healthy().await.unwrap());
+
+    h.polkit.allow(MANAGE_ACTION);
+    h.backend.fail(MockOp::ApplyRules);
+    let (sk, pk) = key(5);
+    proxy.add_trusted_key("ops", &pk, "{}").await.unwrap();
+    let path = write_json(&h, "b.json", &signed_baseline(&sk, 1));
+    assert!(matches!(
+        proxy.apply_persistent_allow(&path, "ops").await,
+        Err(Error::BackendFailed(_))
+    ));
+}
@@ -0,0 +1,261 @@
+//! Private-bus harness: a throwaway `dbus-daemon`, the daemon served on it with a
+//! `MockBackend` and temporary paths, and a fake polkit authority that grants only the actions
+//! a test allows. Nothing needs root or the system bus.
+//!
+//! `Harness::start` returns `None` when no `dbus-daemon` binary is found (set
+//! `LUSBY_TEST_DBUS_DAEMON` to point at one); tests then skip.
+
+#![allow(dead_code)] // each test file uses a different part
+
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+
+use lusby_backend_mock::MockBackend;
+use lusby_client::{DaemonProxy, PATH, SERVICE};
+use lusby_common::audit::AuditEntry;
+use lusby_daemon::config::DaemonConfig;
+use lusby_daemon::DaemonState;
+use tempfile::TempDir;
+use zbus::proxy::CacheProperties;
+use zbus::zvariant::OwnedValue;
+use zbus::{connection, interface, Connection};
+
+const BUS_CONFIG: &str = r#"<!DOCTYPE busconfig PUBLIC "-//freedesktop//DTD D-BUS Bus Configuration 1.0//EN"
+ "http://www.freedesktop.org/standards/dbus/1.0/busconfig.dtd">
+<busconfig>
+  <type>session</type>
+  <listen>unix:path=@SOCKET@</listen>
+  <auth>EXTERNAL</auth>
+  <policy context="default">
+    <allow send_destination="*" eavesdrop="true"/>
+    <allow eavesdrop="true"/>
+    <allow own="*"/>
+  </policy>
+</busconfig>
+"#;
+
+/// One `CheckAuthorization` call as the fake authority saw it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolkitCheck {
+    pub action_id: String,
+    pub subject_kind: String,
+    /// The `name` detail of a `system-bus-name` subject.
+    pub bus_name: Option<String>,
+}
+
+/// Stands in for `org.freedesktop.PolicyKit1` with the real method signature.
+#[derive(Clone, Default)]
+pub struct FakePolkit {
+    allowed: Arc<Mutex<HashSet<String>>>,
+    checks: Arc<Mutex<Vec<PolkitCheck>>>,
+}
+
+impl FakePolkit {
+    pub fn allow(&self, action_id: &str) {
+        self.allowed.lock().unwrap().insert(action_id.to_string());
+    }
+
+    pub fn deny(&self, action_id: &str) {
+        self.allowed.lock().unwrap().remove(action_id);
+    }
+
+    pub fn checks(&self) -> Vec<PolkitCheck> {
+        self.checks.lock().unwrap().clone()
+    }
+}
+
+#[interface(name = "org.freedesktop.PolicyKit1.Authority")]
+impl FakePolkit {
+    async fn check_authorization(
+        &self,
+        subject: (String, HashMap<String, OwnedValue>),
+        action_id: String,
+        _details: HashMap<String, String>,
+        _flags: u32,
+        _cancellation_id: String,
+    ) -> (bool, bool, HashMap<String, String>) {
+        let bus_name = subject
+            .1
+            .get("name")
+            .and_then(|v| v.downcast_ref::<&str>().ok())
+            .map(str::to_string);
+        self.checks.lock().unwrap().push(PolkitCheck {
+            action_id: action_id.clone(),
+            subject_kind: subject.0,
+            bus_name,
+        });
+        let allowed = self.allowed.lock().unwrap().contains(&action_id);
+        (allowed, false, HashMap::new())
+    }
+}
+
+/// A running `dbus-daemon`, killed on drop.
+struct PrivateBus {
+    child: Child,
+    address: String,
+}
+
+impl PrivateBus {
+    fn start(dir: &Path) -> Option<Self> {
+        let program =
+            std::env::var("LUSBY_TEST_DBUS_DAEMON").unwrap_or_else(|_| "dbus-daemon".into());
+        let config = dir.join("bus.conf");
+        let socket = dir.join("bus.sock");
+        std::fs::write(
+            &config,
+            BUS_CONFIG.replace("@SOCKET@", &socket.display().to_string()),
+        )
+        .ok()?;
+        let mut child = Command::new(program)
+            .arg(format!("--config-file={}", config.display()))
+            .args(["--nofork", "--print-address"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()?;
+        let mut address = String::new();
+        let read = BufReader::new(child.stdout.take()?).read_line(&mut address);
+        if read.is_err() || address.trim().is_empty() {
+            let _ = child.kill();
+            return None;
+        }
+        Some(Self {
+            child,
+            address: address.trim().to_string(),
+        })
+    }
+}
+
+impl Drop for PrivateBus {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+pub struct Harness {
+    pub state: DaemonState,
+    pub backend: MockBackend,
+    pub polkit: FakePolkit,
+    pub config: DaemonConfig,
+    /// The daemon's own connection, owning `org.lusby.Daemon`.
+    pub daemon: Connection,
+    /// An ordinary client on the same bus.
+    pub client: Connection,
+    _polkit_conn: Connection,
+    _bus: PrivateBus,
+    pub dir: TempDir,
+}
+
+impl Harness {
+    pub async fn start() -> Option<Self> {
+        Self::start_with(MockBackend::new_with_sample()).await
+    }
+
+    pub async fn start_with(backend: MockBackend) -> Option<Self> {
+        Self::start_configured(backend, |_| {}).await
+    }
+
+    /// Like `start_with`, letting `configure` adjust the daemon config (paths are set after).
+    pub async fn start_configured(
+        backend: MockBackend,
+        configure: impl FnOnce(&mut DaemonConfig),
+    ) -> Option<Self> {
+        let dir = tempfile::tempdir().unwrap();
+        let Some(bus) = PrivateBus::start(dir.path()) else {
+            eprintln!("dbus-daemon not found; skipping private-bus test");
+            return None;
+        };
+
+        let mut config = DaemonConfig::default();
+        configure(&mut config);
+        config.paths.audit_log = dir.path().join("audit.log");
+        config.paths.state_dir = dir.path().join("state");
+        config.paths.baselines_system = dir.path().join("baselines");
+        config.paths.trusted_pubkeys = dir.path().join("keys");
+        let state = DaemonState::from_config(backend.clone(), &config);
+
+        let polkit = FakePolkit::default();
+        let polkit_conn = connection::Builder::address(bus.address.as_str())
+            .unwrap()
+            .name("org.freedesktop.PolicyKit1")
+            .unwrap()
+            .serve_at("/org/freedesktop/PolicyKit1/Authority", polkit.clone())
+            .unwrap()
+            .build()
+            .await
+            .unwrap();
+        let daemon = connection::Builder::address(bus.address.as_str())
+            .unwrap()
+            .name(SERVICE)
+            .unwrap()
+            .serve_at(PATH, state.clone())
+            .unwrap()
+            .serve_at(PATH, zbus::fdo::ObjectManager)
+            .unwrap()
+            .build()
+            .await
+            .unwrap();
+        let client = connection::Builder::address(bus.address.as_str())
+            .unwrap()
+            .build()
+            .await
+            .unwrap();
+        Some(Self {
+            state,
+            backend,
+            polkit,
+            config,
+            daemon,
+            client,
+            _polkit_conn: polkit_conn,
+            _bus: bus,
+            dir,
+        })
+    }
+
+    /// Run the daemon's device event loop on the mock's event stream, as `main` does.
+    pub fn forward_mock_events(&self) {
+        let events = self.state.device_events().expect("the mock streams events");
+        let state = self.state.clone();
+        let conn = self.daemon.clone();
+        tokio::spawn(async move { state.run_device_events(&conn, events).await });
+    }
+
+    /// A proxy on the client connection that reads properties fresh on every call.
+    pub async fn proxy(&self) -> DaemonProxy<'static> {
+        DaemonProxy::builder(&self.client)
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await
+            .unwrap()
+    }
+
+    /// Unique bus name of the client connection, as polkit sees it.
+    pub fn client_name(&self) -> String {
+        self.client.unique_name().unwrap().to_string()
+    }
+
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.dir.path().join(name)
+    }
+
+    /// The audit log entries written so far.
+    pub fn audit(&self) -> Vec<AuditEntry> {
+        std::fs::read_to_string(&self.config.paths.audit_log)
+            .unwrap_or_default()
+            .lines()
+            .map(|l| serde_json::from_str(l).expect("audit entries are JSON"))
+            .collect()
+    }
+
+    /// True if an entry has this event type and an action containing `action`.
+    pub fn audited(&self, event_type: &str, action: &str) -> bool {
+        self.audit()
+            .iter()
+            .any(|e| e.payload.event_type == event_type && e.payload.action.contains(action))
+    }
+}
@@ -1,3 +1,8 @@
+use std::future::Future;
+
+use lusby_client::{DaemonProxy, Error};
+use lusby_common::types::DeviceInfo;
+use notify_rust::Notification;
 use serde::Deserialize;
 
 #[derive(Debug, Deserialize)]
@@ -29,3 +34,56 @@ pub fn load_config_ttl() -> u32 {
     }
     default_ttl()
 }
+
+/// Notification text for a daemon call the user started from the tray.
+pub fn failure_message(action: &str, err: &Error) -> String {
+    match err {
+        Error::NotAuthorized(_) => format!("{} was not authorized", action),
+        Error::NotFound(_) => format!("{} failed: the device is no longer connected", action),
+        Error::PolicyDenied(m) => format!("{} is not allowed by policy: {}", action, m),
+        Error::ZBus(_) => format!("{} failed: the lusby daemon is not running", action),
+        e => format!("{} failed: {}", action, e),
+    }
+}
+
+/// A device's interface classes for display, e.g. "Keyboard, Mass Storage".
+pub fn interface_summary(dev: &DeviceInfo) -> String {
+    let classes = dev.interface_classes();
+    if classes.is_empty() {
+        "unknown".into()
+    } else {
+        classes.join(", ")
+    }
+}
+
+/// Tell the user why a daemon call failed; successes stay silent.
+pub fn report_result(action: &str, result: Result<(), Error>) {
+    if let Err(e) = result {
+        eprintln!("{}: {}", action, e);
+        let _ = Notification::new()
+            .summary("Lusby")
+            .body(&failure_message(action, &e))
+            .icon("dialog-error")
+            .show();
+    }
+}
+
+/// Run a daemon call for a menu or notification action on its own thread and runtime,
+/// so the UI never blocks, and report a failure to the user.
+pub fn call_daemon<F, Fut>(action: &'static str, call: F)
+where
+    F: FnOnce(DaemonProxy<'static>) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<(), Error>>,
+{
+    std::thread::spawn(move || {
+        let rt = match tokio::runtime::Runtime::new() {
+            Ok(rt) => rt,
+            Err(e) => return eprintln!("{}: {}", action, e),
+        };
+        let result = rt.block_on(async move {
+            let daemon = lusby_client::connect().await?;
+            call(daemon).await
+        });
+        report_result(action, result);
+    });
+}
@@ -6,6 +6,7 @@ use gtk::prelude::*;
 use libappindicator::{AppIndicator, AppIndicatorStatus};
 use libc::geteuid;
 use lusby_common::types::DeviceInfo;
+use lusby_tray::{call_daemon, interface_summary};
 use std::sync::{Arc, Mutex};
 
 // Minimal GTK/libappindicator system tray with approval actions.
@@ -43,28 +44,8 @@ pub fn start_indicator(
                 let ttl: u32 = ttl;
                 let end = std::time::Instant::now() + std::time::Duration::from_secs(ttl as u64);
                 *approval_end.lock().unwrap() = Some(end);
-                std::thread::spawn(move || {
-                    let rt = tokio::runtime::Runtime::new().unwrap();
-                    rt.block_on(async move {
-                        if let Ok(conn) = zbus::Connection::system().await {
-                            if let Ok(proxy) = zbus::Proxy::new(
-                                &conn,
-                                "org.lusby.Daemon",
-                                "/org/lusby/Daemon",
-                                "org.lusby.Daemon",
-                            )
-                            .await
-                            {
-                                let _: bool = proxy
-                                    .call_method("request_ephemeral_allow", &(device_id, ttl, uid))
-                                    .await
-                                    .expect("D-Bus call failed")
-                                    .body()
-                                    .deserialize()
-                                    .expect("Failed to deserialize response");
-                            }
-                        }
-                    });
+                call_daemon("Approval", move |daemon| async move {
+                    daemon.request_ephemeral_allow(&device_id, ttl, uid).await
                 });
             }
         });
@@ -104,28 +85,8 @@ pub fn start_indicator(
             if let Some(dev) = last_seen.lock().unwrap().clone() {
                 let device_id = dev.id.clone();
                 *approval_end.lock().unwrap() = None;
-                std::thread::spawn(move || {
-                    let rt = tokio::runtime::Runtime::new().unwrap();
-                    rt.block_on(async move {
-                        if let Ok(conn) = zbus::Connection::system().await {
-                            if let Ok(proxy) = zbus::Proxy::new(
-                                &conn,
-                                "org.lusby.Daemon",
-                                "/org/lusby/Daemon",
-                                "org.lusby.Daemon",
-                            )
-                            .await
-                            {
-                                let _: bool = proxy
-                                    .call_method("revoke_device", &(device_id))
-                                    .await
-                                    .expect("D-Bus call failed")
-                                    .body()
-                                    .deserialize()
-                                    .expect("Failed to deserialize response");
-                            }
-                        }
-                    });
+                call_daemon("Revoke", move |daemon| async move {
+                    daemon.revoke_device(&device_id).await
                 });
             }
         });
@@ -139,28 +100,8 @@ pub fn start_indicator(
         revoke_item.connect_activate(move |_| {
             if let Some(dev) = last_seen.lock().unwrap().clone() {
                 let device_id = dev.id.clone();
-                std::thread::spawn(move || {
-                    let rt = tokio::runtime::Runtime::new().unwrap();
-                    rt.block_on(async move {
-                        if let Ok(conn) = zbus::Connection::system().await {
-                            if let Ok(proxy) = zbus::Proxy::new(
-                                &conn,
-                                "org.lusby.Daemon",
-                                "/org/lusby/Daemon",
-                                "org.lusby.Daemon",
-                            )
-                            .await
-                            {
-                                let _: bool = proxy
-                                    .call_method("revoke_device", &(device_id))
-                                    .await
-                                    .expect("D-Bus call failed")
-                                    .body()
-                                    .deserialize()
-                                    .expect("Failed to deserialize response");
-                            }
-                        }
-                    });
+                call_daemon("Revoke", move |daemon| async move {
+                    daemon.revoke_device(&device_id).await
                 });
             }
         });
@@ -174,8 +115,13 @@ pub fn start_indicator(
         details_item.connect_activate(move |_| {
             if let Some(dev) = last_seen.lock().unwrap().clone() {
                 let text = format!(
-                    "Vendor: {}\nProduct: {}\nSerial: {}\nType: {}\nFingerprint: {}",
-                    dev.vendor_id, dev.product_id, dev.serial, dev.device_type, dev.fingerprint
+                    "Vendor: {}\nProduct: {}\nSerial: {}\nType: {}\nInterfaces: {}\nFingerprint: {}",
+                    dev.vendor_id,
+                    dev.product_id,
+                    dev.serial,
+                    dev.device_type,
+                    interface_summary(&dev),
+                    dev.fingerprint
                 );
                 let dialog = gtk::MessageDialog::new(
                     None::<&gtk::Window>,
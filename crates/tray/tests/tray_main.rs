@@ -15,3 +15,44 @@ fn test_config_policy_default() {
     let policy = ConfigPolicy::default();
     assert_eq!(policy.default_ttl_secs, 300);
 }
+
+#[test]
+fn test_failure_messages_name_the_reason() {
+    use lusby_common::error::Error;
+    use lusby_tray::failure_message;
+
+    assert_eq!(
+        failure_message("Approval", &Error::NotAuthorized("polkit".into())),
+        "Approval was not authorized"
+    );
+    assert_eq!(
+        failure_message("Revoke", &Error::NotFound("no device 3".into())),
+        "Revoke failed: the device is no longer connected"
+    );
+    assert_eq!(
+        failure_message("Approval", &Error::InvalidInput("ttl 0".into())),
+        "Approval failed: invalid input: ttl 0"
+    );
+}
+
+#[test]
+fn test_interface_summary_names_classes() {
+    use lusby_common::types::DeviceInfo;
+    use lusby_tray::interface_summary;
+
+    let mut dev = DeviceInfo {
+        id: "1-2".into(),
+        vendor_id: "0781".into(),
+        product_id: "5581".into(),
+        serial: String::new(),
+        fingerprint: String::new(),
+        device_type: "storage".into(),
+        allowed: false,
+        persistent: false,
+        interfaces: Vec::new(),
+        legacy_fingerprint: String::new(),
+    };
+    assert_eq!(interface_summary(&dev), "unknown");
+    dev.interfaces = vec!["08:06:50".into(), "03:01:01".into()];
+    assert_eq!(interface_summary(&dev), "Mass Storage, Keyboard");
+}